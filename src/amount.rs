@@ -0,0 +1,228 @@
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use tracing::info;
+
+use crate::{config::Config, error::SendError};
+
+const SOL_DECIMALS: usize = 9;
+
+/// parse_sol converts a decimal SOL amount such as `0.05` into lamports without going through
+/// floating point
+pub fn parse_sol(amount: &str) -> Result<u64, String> {
+    let amount = amount.trim();
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err("Amount is empty".to_string());
+    }
+    if !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(format!("Invalid SOL amount: {}", amount));
+    }
+    if fraction.len() > SOL_DECIMALS {
+        return Err(format!(
+            "SOL has {} decimals, {} is too precise",
+            SOL_DECIMALS, amount
+        ));
+    }
+
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole
+            .parse()
+            .map_err(|e| format!("Invalid SOL amount {}: {}", amount, e))?
+    };
+    let fraction: u64 = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<width$}", fraction, width = SOL_DECIMALS)
+            .parse()
+            .map_err(|e| format!("Invalid SOL amount {}: {}", amount, e))?
+    };
+
+    whole
+        .checked_mul(LAMPORTS_PER_SOL)
+        .and_then(|lamports| lamports.checked_add(fraction))
+        .ok_or_else(|| format!("SOL amount {} is too large", amount))
+}
+
+/// format_sol renders lamports as a SOL amount with all 9 decimals, e.g. `0.050000000`
+pub fn format_sol(lamports: u64) -> String {
+    format_units(lamports, SOL_DECIMALS as u8)
+}
+
+/// format_units renders an amount in base units as a decimal with the given number of decimals, or
+/// the raw base units if the decimals are too many to compute the unit
+pub fn format_units(amount: u64, decimals: u8) -> String {
+    let Some(unit) = 10u128.checked_pow(decimals as u32) else {
+        return amount.to_string();
+    };
+    let (whole, fraction) = (amount as u128 / unit, amount as u128 % unit);
+    match decimals {
+        0 => whole.to_string(),
+        decimals => format!("{}.{:0width$}", whole, fraction, width = decimals as usize),
+    }
+}
+
+/// check_rent_exemption rejects dust transfers and transfers to new accounts that would leave the
+/// receiver below the rent-exempt minimum, which the runtime refuses
+pub async fn check_rent_exemption(
+    rpc_client: &RpcClient,
+    receiver: &Pubkey,
+    lamports: u64,
+) -> Result<(), String> {
+    if lamports == 0 {
+        return Err("Transfer amount must be greater than zero".to_string());
+    }
+
+    let receiver_balance = rpc_client
+        .get_balance(receiver)
+        .await
+        .map_err(|e| format!("Error getting receiver balance: {}", e))?;
+    if receiver_balance > 0 {
+        return Ok(());
+    }
+
+    let minimum_balance = rpc_client
+        .get_minimum_balance_for_rent_exemption(0)
+        .await
+        .map_err(|e| format!("Error getting rent-exempt minimum: {}", e))?;
+    if lamports < minimum_balance {
+        return Err(format!(
+            "Receiver {} doesn't exist yet, the transfer must be at least {} lamports to be rent exempt, got {}",
+            receiver, minimum_balance, lamports
+        ));
+    }
+    Ok(())
+}
+
+/// sweep_amount returns the sender's whole balance minus the fee if the sender pays it
+pub async fn sweep_amount(
+    rpc_client: &RpcClient,
+    config: &Config,
+    fee: u64,
+) -> Result<u64, String> {
+    let sender = config.sender();
+    let balance = rpc_client
+        .get_balance(&sender)
+        .await
+        .map_err(|e| format!("Error getting sender balance: {}", e))?;
+
+    let fee = if config.fee_payer()? == sender {
+        fee
+    } else {
+        0
+    };
+    match balance.checked_sub(fee) {
+        Some(amount) if amount > 0 => {
+            info!(
+                "[ SWEEP ] - balance {}, fee {}, transferring {} lamports",
+                balance, fee, amount
+            );
+            Ok(amount)
+        }
+        _ => Err(format!(
+            "Sender balance of {} lamports doesn't cover the fee of {} lamports",
+            balance, fee
+        )),
+    }
+}
+
+/// check_balance verifies the sender and the fee payer can cover the transferred lamports, the fee
+/// and the rent of an associated token account created by the transaction
+pub async fn check_balance(
+    rpc_client: &RpcClient,
+    config: &Config,
+    lamports: u64,
+    fee: u64,
+) -> Result<(), SendError> {
+    let sender = config.sender();
+    let fee_payer = config.fee_payer()?;
+
+    let transferred = if config.token_transfer.is_none() && config.instructions.is_none() {
+        lamports
+    } else {
+        0
+    };
+    // the sender funds the associated token account
+    let rent = match &config.token_transfer {
+        Some(token_transfer) if token_transfer.create_destination => rpc_client
+            .get_minimum_balance_for_rent_exemption(token_transfer.account_len)
+            .await
+            .map_err(|e| format!("Error getting rent-exempt minimum: {}", e))?,
+        _ => 0,
+    };
+
+    let mut requirements = vec![(sender, transferred + rent)];
+    if fee_payer == sender {
+        requirements[0].1 += fee;
+    } else {
+        requirements.push((fee_payer, fee));
+    }
+
+    for (account, required) in requirements {
+        let available = rpc_client
+            .get_balance(&account)
+            .await
+            .map_err(|e| format!("Error getting balance of {}: {}", account, e))?;
+        if available < required {
+            return Err(SendError::InsufficientFunds {
+                required,
+                available,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_amounts_are_parsed_into_lamports() {
+        assert_eq!(parse_sol("0.05"), Ok(50_000_000));
+        assert_eq!(parse_sol(".5"), Ok(500_000_000));
+        assert_eq!(parse_sol("1."), Ok(LAMPORTS_PER_SOL));
+        assert_eq!(parse_sol("0.000000001"), Ok(1));
+    }
+
+    #[test]
+    fn units_are_formatted_with_their_decimals() {
+        assert_eq!(format_sol(50_000_000), "0.050000000");
+        assert_eq!(format_units(1_234, 0), "1234");
+        assert_eq!(format_units(1_234, 2), "12.34");
+        assert_eq!(
+            format_units(7, 38),
+            "0.00000000000000000000000000000000000007"
+        );
+    }
+
+    #[test]
+    fn units_past_u128_precision_are_formatted_raw() {
+        assert_eq!(format_units(1_234, 39), "1234");
+        assert_eq!(format_units(u64::MAX, u8::MAX), u64::MAX.to_string());
+    }
+
+    #[test]
+    fn more_than_nine_decimals_are_rejected() {
+        assert!(parse_sol("1.0000000001").is_err());
+    }
+
+    #[test]
+    fn negative_amounts_are_rejected() {
+        assert!(parse_sol("-1").is_err());
+    }
+
+    #[test]
+    fn amounts_past_u64_lamports_are_rejected() {
+        let max_sol = u64::MAX / LAMPORTS_PER_SOL;
+        assert_eq!(parse_sol(&format!("{}.709551615", max_sol)), Ok(u64::MAX));
+        assert!(parse_sol(&format!("{}.709551616", max_sol)).is_err());
+        assert!(parse_sol(&format!("{}", max_sol + 1)).is_err());
+        assert!(parse_sol("99999999999999999999999").is_err());
+    }
+}
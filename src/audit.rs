@@ -0,0 +1,312 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_sdk::{
+    instruction::CompiledInstruction, message::Message, pubkey::Pubkey, signature::Signature,
+    system_instruction::SystemInstruction, system_program, transaction::Transaction,
+};
+use spl_token_2022::{
+    extension::transfer_fee::instruction::TransferFeeInstruction, instruction::TokenInstruction,
+};
+
+/// Transfer is a SOL or SPL token transfer of an audited transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transfer {
+    /// Receiver account, the token account for SPL tokens
+    pub receiver: String,
+    /// Amount in lamports, or in the base units of the mint
+    pub amount: u64,
+    /// Mint of the SPL token, missing for SOL
+    pub mint: Option<String>,
+}
+
+/// Record is one line of the audit log, written before the transaction is first sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// Unix time of the submission, in seconds
+    pub timestamp: u64,
+    /// Fee payer, the first signer
+    pub signer: String,
+    pub transfers: Vec<Transfer>,
+    pub signature: String,
+    /// Network and template the transaction was built with, e.g. `mainnet/payouts`
+    pub profile: String,
+    /// Hash of the previous line with hash chaining
+    pub prev_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    record: Record,
+    /// Hex SHA-256 of the record, covering the hash of the previous line
+    hash: Option<String>,
+}
+
+impl Entry {
+    fn new(record: Record, chained: bool) -> Result<Self, String> {
+        let hash = match chained {
+            true => Some(hash(&record)?),
+            false => None,
+        };
+        Ok(Self { record, hash })
+    }
+}
+
+fn hash(record: &Record) -> Result<String, String> {
+    let json = serde_json::to_string(record)
+        .map_err(|e| format!("Failed to serialize audit record: {}", e))?;
+    Ok(hex::encode(Sha256::digest(json.as_bytes())))
+}
+
+/// AuditLog appends a record of every signed transaction to an append-only JSONL file before it's
+/// submitted. With hash chaining every line carries the hash of the previous one, so editing or
+/// removing a line breaks the chain, see `verify`. Appends hold an exclusive lock on the file, so
+/// processes sharing the log chain to each other's lines
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    profile: String,
+    chained: bool,
+    /// Signatures recorded by this process
+    audited: Arc<Mutex<HashSet<Signature>>>,
+}
+
+impl AuditLog {
+    /// open continues the log at the path, checking that it can be read if hash chaining is on
+    pub fn open(path: PathBuf, profile: String, chained: bool) -> Result<Self, String> {
+        if chained {
+            read_entries(&path)?;
+        }
+        Ok(Self {
+            path,
+            profile,
+            chained,
+            audited: Arc::default(),
+        })
+    }
+
+    /// record appends the record of the transaction, once per signature since resending the
+    /// same transaction doesn't sign anything new. The transaction must not be sent if it fails
+    pub fn record(&self, transaction: &Transaction) -> Result<(), String> {
+        let signature = *transaction
+            .signatures
+            .first()
+            .ok_or("No signature found in the transaction")?;
+        let mut audited = self
+            .audited
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if audited.contains(&signature) {
+            return Ok(());
+        }
+
+        let message = &transaction.message;
+        let record = Record {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            signer: message
+                .account_keys
+                .first()
+                .map(Pubkey::to_string)
+                .unwrap_or_default(),
+            transfers: message
+                .instructions
+                .iter()
+                .filter_map(|instruction| transfer(message, instruction))
+                .collect(),
+            signature: signature.to_string(),
+            profile: self.profile.clone(),
+            prev_hash: None,
+        };
+        self.append(record)?;
+        audited.insert(signature);
+        Ok(())
+    }
+
+    /// append writes the record as the last line, chained to the line before it. The file stays
+    /// locked from reading that line to writing the new one, another process appending waits
+    fn append(&self, mut record: Record) -> Result<(), String> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let write_error =
+            |e: std::io::Error| format!("Failed to write {}: {}", self.path.display(), e);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(write_error)?;
+        // released when the file is closed
+        file.lock().map_err(write_error)?;
+        if self.chained {
+            record.prev_hash = last_hash(&mut file, &self.path)?;
+        }
+        let entry = Entry::new(record, self.chained)?;
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize audit record: {}", e))?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .and_then(|()| file.sync_data())
+            .map_err(write_error)
+    }
+}
+
+/// last_hash returns the hash of the last line of the locked log
+fn last_hash(file: &mut File, path: &Path) -> Result<Option<String>, String> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0))
+        .and_then(|_| file.read_to_string(&mut content))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let Some(line) = content.lines().rev().find(|line| !line.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let entry: Entry =
+        serde_json::from_str(line).map_err(|e| format!("{}: last line: {}", path.display(), e))?;
+    Ok(entry.hash)
+}
+
+/// verify checks the hash chain of the audit log at the path and returns the number of records.
+/// The lines must all be chained
+pub fn verify(path: &Path) -> Result<usize, String> {
+    let entries = read_entries(path)?;
+    let mut prev_hash = None;
+    for (index, entry) in entries.iter().enumerate() {
+        let line = index + 1;
+        let Some(expected) = entry.hash.as_ref() else {
+            return Err(format!("{}: line {} isn't chained", path.display(), line));
+        };
+        if entry.record.prev_hash != prev_hash {
+            return Err(format!(
+                "{}: line {} doesn't follow line {}, a line was removed or inserted",
+                path.display(),
+                line,
+                index
+            ));
+        }
+        if hash(&entry.record)? != *expected {
+            return Err(format!(
+                "{}: line {} doesn't match its hash, it was modified",
+                path.display(),
+                line
+            ));
+        }
+        prev_hash = entry.hash.clone();
+    }
+    Ok(entries.len())
+}
+
+fn read_entries(path: &Path) -> Result<Vec<Entry>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("{}: line {}: {}", path.display(), index + 1, e))
+        })
+        .collect()
+}
+
+/// transfer decodes a system transfer or a checked SPL token transfer, None for any other
+/// instruction
+fn transfer(message: &Message, instruction: &CompiledInstruction) -> Option<Transfer> {
+    let program_id = message
+        .account_keys
+        .get(instruction.program_id_index as usize)?;
+    let account = |position: usize| {
+        let index = *instruction.accounts.get(position)?;
+        message
+            .account_keys
+            .get(index as usize)
+            .map(Pubkey::to_string)
+    };
+
+    if *program_id == system_program::id() {
+        return match bincode::deserialize(&instruction.data).ok()? {
+            SystemInstruction::Transfer { lamports } => Some(Transfer {
+                receiver: account(1)?,
+                amount: lamports,
+                mint: None,
+            }),
+            _ => None,
+        };
+    }
+    if *program_id == spl_token::id() || *program_id == spl_token_2022::id() {
+        // both programs share the layout of the transfer instructions
+        let amount = match TokenInstruction::unpack(&instruction.data).ok()? {
+            TokenInstruction::TransferChecked { amount, .. }
+            | TokenInstruction::TransferFeeExtension(
+                TransferFeeInstruction::TransferCheckedWithFee { amount, .. },
+            ) => amount,
+            _ => return None,
+        };
+        return Some(Transfer {
+            receiver: account(2)?,
+            amount,
+            mint: account(1),
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use solana_sdk::{hash::Hash, signature::Keypair, system_transaction};
+
+    use super::*;
+
+    #[test]
+    fn logs_shared_by_processes_stay_chained() {
+        let path = std::env::temp_dir().join(format!("quic-audit-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        // every log stands for a process of its own, with nothing but the file in common
+        let logs: Vec<AuditLog> = (0..4)
+            .map(|_| AuditLog::open(path.clone(), "devnet".to_string(), true).unwrap())
+            .collect();
+
+        let sender = Keypair::new();
+        let transactions: Vec<Transaction> = (0..200)
+            .map(|lamports| {
+                system_transaction::transfer(
+                    &sender,
+                    &Pubkey::new_unique(),
+                    lamports,
+                    Hash::default(),
+                )
+            })
+            .collect();
+
+        thread::scope(|scope| {
+            for (log, transactions) in logs.iter().zip(transactions.chunks(50)) {
+                scope.spawn(move || {
+                    for transaction in transactions {
+                        log.record(transaction).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(verify(&path), Ok(200));
+        fs::remove_file(&path).unwrap();
+    }
+}
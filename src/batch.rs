@@ -0,0 +1,399 @@
+use std::{fs, ops::Range, str::FromStr, sync::Arc};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_request::MAX_MULTIPLE_ACCOUNTS};
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, message::Message, pubkey::Pubkey, signature::Signature,
+    system_instruction::transfer, transaction::Transaction,
+};
+use tracing::{error, info, instrument, warn};
+
+use crate::{
+    amount,
+    config::Config,
+    confirmation,
+    error::SendError,
+    fees,
+    leader_tracker::LeaderTracker,
+    outcome, tx_builder,
+    tx_tracker::{TxState, TxTracker},
+    worker_pool::SenderPool,
+};
+
+#[derive(Debug, Clone)]
+pub struct Payout {
+    /// Line number in the CSV file, used in the per-row report
+    pub row: usize,
+    pub recipient: Pubkey,
+    /// Amount in lamports
+    pub amount: u64,
+}
+
+/// read_payouts parses `recipient,amount` rows, skipping empty lines, `#` comments and an
+/// optional header
+pub fn read_payouts(path: &str) -> Result<Vec<Payout>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    let mut payouts = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let row = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (recipient, amount) = line
+            .split_once(',')
+            .ok_or_else(|| format!("Row {}: expected `recipient,amount`", row))?;
+        let recipient = match Pubkey::from_str(recipient.trim()) {
+            Ok(recipient) => recipient,
+            Err(_) if row == 1 => continue, // header
+            Err(e) => return Err(format!("Row {}: invalid recipient: {}", row, e)),
+        };
+        let amount = amount
+            .trim()
+            .parse()
+            .map_err(|e| format!("Row {}: invalid amount: {}", row, e))?;
+
+        payouts.push(Payout {
+            row,
+            recipient,
+            amount,
+        });
+    }
+
+    if payouts.is_empty() {
+        return Err(format!("No payouts found in {}", path));
+    }
+    Ok(payouts)
+}
+
+/// check_payouts rejects dust rows and rows that would leave a new recipient below the rent-exempt
+/// minimum, which the runtime refuses, the same checks as a single transfer. The recipients are
+/// looked up in chunks of as many accounts as one request takes
+pub async fn check_payouts(rpc_client: &RpcClient, payouts: &[Payout]) -> Result<(), String> {
+    if let Some(payout) = payouts.iter().find(|payout| payout.amount == 0) {
+        return Err(format!(
+            "Row {}: transfer amount must be greater than zero",
+            payout.row
+        ));
+    }
+
+    let minimum_balance = rpc_client
+        .get_minimum_balance_for_rent_exemption(0)
+        .await
+        .map_err(|e| format!("Error getting rent-exempt minimum: {}", e))?;
+    for chunk in payouts.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let recipients: Vec<Pubkey> = chunk.iter().map(|payout| payout.recipient).collect();
+        let accounts = rpc_client
+            .get_multiple_accounts(&recipients)
+            .await
+            .map_err(|e| format!("Error getting recipient accounts: {}", e))?;
+        for (payout, account) in chunk.iter().zip(accounts) {
+            let exists = account.is_some_and(|account| account.lamports > 0);
+            if !exists && payout.amount < minimum_balance {
+                return Err(format!(
+                    "Row {}: receiver {} doesn't exist yet, the transfer must be at least {} lamports to be rent exempt, got {}",
+                    payout.row, payout.recipient, minimum_balance, payout.amount
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// pack_messages packs as many transfers per message as fit into a single packet, returning each
+/// message with the range of payouts it carries. The instructions are built once
+pub fn pack_messages(
+    config: &Config,
+    payouts: &[Payout],
+    blockhash: Hash,
+    compute_unit_price: u64,
+) -> Result<Vec<(Message, Range<usize>)>, String> {
+    let sender = config.sender();
+    let fee_payer = config.fee_payer()?;
+    let compute_budget =
+        config.compute_budget_instructions(config.compute_unit_limit, compute_unit_price);
+    let transfers: Vec<Instruction> = payouts
+        .iter()
+        .map(|payout| transfer(&sender, &payout.recipient, payout.amount))
+        .collect();
+    let create_message = |rows: Range<usize>| {
+        let instructions: Vec<Instruction> = compute_budget
+            .iter()
+            .chain(&transfers[rows])
+            .cloned()
+            .collect();
+        Message::new_with_blockhash(&instructions, Some(&fee_payer), &blockhash)
+    };
+
+    let mut messages = Vec::new();
+    let mut start = 0;
+    while start < payouts.len() {
+        let mut end = start + 1;
+        if config.check_size(&create_message(start..end)).is_err() {
+            return Err(format!(
+                "Row {}: a single transfer doesn't fit into a transaction",
+                payouts[start].row
+            ));
+        }
+        while end < payouts.len() && config.check_size(&create_message(start..end + 1)).is_ok() {
+            end += 1;
+        }
+
+        messages.push((create_message(start..end), start..end));
+        start = end;
+    }
+    Ok(messages)
+}
+
+/// sign_messages signs the packed messages in parallel, unless an external signer has to sign
+/// them one by one
+pub fn sign_messages(
+    config: &Config,
+    messages: Vec<(Message, Range<usize>)>,
+    blockhash: Hash,
+) -> Result<Vec<(Transaction, Range<usize>)>, String> {
+    let signers = config.load_signers()?;
+    let sign = |(message, rows): (Message, Range<usize>)| {
+        signers
+            .sign(message, blockhash)
+            .map(|transaction| (transaction, rows))
+    };
+    match signers.is_local() {
+        true => messages.into_par_iter().map(sign).collect(),
+        false => messages.into_iter().map(sign).collect(),
+    }
+}
+
+/// send_batch sends every payout of the CSV file through the leader-targeted QUIC path with a
+/// pool of sender workers, reports the result of each row and returns the number of failed rows
+#[instrument(name = "batch", skip_all, fields(request_id = %tx_tracker.request_id()))]
+pub async fn send_batch(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: &impl LeaderTracker,
+    tx_tracker: &TxTracker,
+    path: &str,
+) -> Result<usize, String> {
+    let payouts = read_payouts(path)?;
+    check_payouts(&rpc_client, &payouts).await?;
+
+    let tracker_slot = Some(tracker.current_slot()).filter(|slot| *slot > 0);
+    let recent_blockhash = tx_builder::recent_blockhash(&rpc_client, config, tracker_slot).await?;
+    tx_tracker.blockhash_fetched(&recent_blockhash);
+    let last_valid_block_height = recent_blockhash.last_valid_block_height;
+    let blockhash = recent_blockhash.blockhash;
+    let compute_unit_price = fees::get_compute_unit_price(&rpc_client, config).await;
+    let messages = pack_messages(config, &payouts, blockhash, compute_unit_price)?;
+
+    // every transaction pays its own fee, checked against the balance before anything is signed
+    let lamports = payouts
+        .iter()
+        .try_fold(0u64, |total, payout| total.checked_add(payout.amount))
+        .ok_or_else(|| "The payouts add up to more lamports than exist".to_string())?;
+    let fee = messages
+        .iter()
+        .map(|(message, _)| {
+            fees::expected_fee(message, config.compute_unit_limit, compute_unit_price)
+        })
+        .sum();
+    amount::check_balance(&rpc_client, config, lamports, fee)
+        .await
+        .map_err(|e| e.to_string())?;
+    let transactions = sign_messages(config, messages, blockhash)?;
+    info!(
+        "[ BATCH ] - {} payouts packed into {} transactions",
+        payouts.len(),
+        transactions.len()
+    );
+
+    for (transaction, _) in &transactions {
+        tx_tracker.built(transaction.signatures[0]);
+    }
+
+    let signed: Vec<&Transaction> = transactions
+        .iter()
+        .map(|(transaction, _)| transaction)
+        .collect();
+    let sent: Vec<_> = SenderPool::new(config, rpc_client.clone(), tracker, tx_tracker)
+        .send_all(&signed, compute_unit_price)
+        .await
+        .into_iter()
+        .zip(transactions.iter().map(|(_, rows)| rows.clone()))
+        .collect();
+
+    let signatures: Vec<Signature> = sent
+        .iter()
+        .filter_map(|(result, _)| result.as_ref().ok().copied())
+        .collect();
+    let mut confirmations = confirmation::confirm_many(
+        &rpc_client,
+        config,
+        &signatures,
+        last_valid_block_height,
+        |signature, result| match result {
+            Ok(slot) => {
+                info!("{} confirmed in slot {}", signature, slot);
+                tx_tracker.confirmed(*signature, *slot);
+            }
+            Err(e @ SendError::BlockhashExpired { .. }) => {
+                error!("{} not confirmed: {}", signature, e);
+                tx_tracker.expired(*signature);
+            }
+            Err(e) => {
+                error!("{} not confirmed: {}", signature, e);
+                tx_tracker.failed(*signature, e.to_string());
+            }
+        },
+    )
+    .await;
+
+    let mut failed = 0;
+    let mut fees_paid = 0;
+    // confirmed transactions whose fee couldn't be read, missing from `fees_paid`
+    let mut fees_unknown = 0;
+    for ((transaction, _), (result, rows)) in transactions.iter().zip(sent) {
+        let status = match result {
+            Ok(signature) => match confirmations.remove(&signature) {
+                Some(Ok(_)) => {
+                    match outcome::fetch_paid_fee(
+                        &rpc_client,
+                        &signature,
+                        config.confirm_commitment,
+                    )
+                    .await
+                    {
+                        Ok(paid_fee) => fees_paid += paid_fee.fee,
+                        Err(e) => {
+                            error!("{}, fee not counted", e);
+                            fees_unknown += 1;
+                        }
+                    }
+                    Ok(signature)
+                }
+                Some(Err(e)) => Err(format!("{} not confirmed: {}", signature, e)),
+                None => Err(format!("{} not confirmed", signature)),
+            },
+            Err(e) => {
+                tx_tracker.failed(transaction.signatures[0], e.to_string());
+                Err(e.to_string())
+            }
+        };
+
+        for payout in &payouts[rows] {
+            match &status {
+                Ok(signature) => info!(
+                    "Row {}: {} lamports to {} confirmed, {}",
+                    payout.row, payout.amount, payout.recipient, signature
+                ),
+                Err(e) => {
+                    failed += 1;
+                    error!(
+                        "Row {}: {} lamports to {} failed: {}",
+                        payout.row, payout.amount, payout.recipient, e
+                    );
+                }
+            }
+        }
+    }
+
+    let expired = transactions
+        .iter()
+        .filter(|(transaction, _)| {
+            tx_tracker.get(&transaction.signatures[0]) == Some(TxState::Expired)
+        })
+        .count();
+    info!(
+        "Batch finished: {} of {} rows confirmed, {} failed, {} of {} transactions expired, {} lamports paid in fees",
+        payouts.len() - failed,
+        payouts.len(),
+        failed,
+        expired,
+        transactions.len(),
+        fees_paid
+    );
+    if fees_unknown > 0 {
+        warn!(
+            "[ BATCH ] - The fees of {} confirmed transactions are unknown and not included",
+            fees_unknown
+        );
+    }
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::rpc_server::MockRpcServer;
+
+    const RENT_EXEMPT_MINIMUM: u64 = 890_880;
+
+    fn payout(row: usize, amount: u64) -> Payout {
+        Payout {
+            row,
+            recipient: Pubkey::new_unique(),
+            amount,
+        }
+    }
+
+    /// server answers the rent-exempt minimum and every recipient with an account funded with
+    /// `lamports`, or none if `None`
+    async fn server(lamports: Option<u64>) -> MockRpcServer {
+        let server = MockRpcServer::start().await;
+        server.set_result(
+            "getMinimumBalanceForRentExemption",
+            json!(RENT_EXEMPT_MINIMUM),
+        );
+        let account = lamports.map(|lamports| {
+            json!({
+                "lamports": lamports,
+                "data": ["", "base64"],
+                "owner": Pubkey::default().to_string(),
+                "executable": false,
+                "rentEpoch": 0,
+                "space": 0,
+            })
+        });
+        server.set_result(
+            "getMultipleAccounts",
+            json!({ "context": { "slot": 1 }, "value": [account.clone(), account] }),
+        );
+        server
+    }
+
+    #[tokio::test]
+    async fn dust_row_is_rejected() {
+        let server = server(Some(1)).await;
+        let payouts = [payout(1, RENT_EXEMPT_MINIMUM), payout(2, 0)];
+
+        let err = check_payouts(&server.rpc_client(), &payouts)
+            .await
+            .unwrap_err();
+
+        assert!(err.starts_with("Row 2:"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn row_below_rent_exemption_to_a_new_account_is_rejected() {
+        let server = server(None).await;
+        let payouts = [payout(1, RENT_EXEMPT_MINIMUM), payout(3, 1_000)];
+
+        let err = check_payouts(&server.rpc_client(), &payouts)
+            .await
+            .unwrap_err();
+
+        assert!(err.starts_with("Row 3:"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn small_rows_to_existing_accounts_are_accepted() {
+        let server = server(Some(1)).await;
+        let payouts = [payout(1, 1_000), payout(2, 1)];
+
+        assert!(check_payouts(&server.rpc_client(), &payouts).await.is_ok());
+    }
+}
@@ -0,0 +1,1011 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::future::join_all;
+use hdrhistogram::Histogram;
+use serde::Serialize;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+use solana_sdk::{
+    clock::Slot, hash::Hash, message::Message, signature::Signature, system_instruction::transfer,
+    transaction::Transaction,
+};
+use tokio::{
+    sync::mpsc,
+    time::{Instant, MissedTickBehavior},
+};
+use tracing::{error, info, instrument};
+
+use crate::{
+    config::Config,
+    fees,
+    leader_tracker::LeaderTracker,
+    quic_manager::{self, ConnectionPool, QuicManager},
+    rpc_api::RpcApi,
+    tx_builder,
+    tx_tracker::TxTracker,
+};
+
+/// Age after which the blockhash of the next transactions is refreshed, well within the ~60s it's
+/// valid for
+const BLOCKHASH_REFRESH: Duration = Duration::from_secs(20);
+const MAX_SIGNATURE_STATUSES: usize = 256;
+/// Time the warm-up waits for the handshake with a leader
+const WARM_UP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Duration of the sends of a ramp step at its rate
+const RAMP_STEP: Duration = Duration::from_secs(10);
+const MAX_RAMP_STEPS: usize = 12;
+/// Rate increase after a sustained step, until a step drops too many transactions
+const RAMP_FACTOR: f64 = 1.5;
+/// The search between the sustained and the failed rate stops once they are this close
+const RAMP_PRECISION: f64 = 1.1;
+/// Lowest rate of a bench, one transaction every 1000 seconds
+const MIN_TPS: f64 = 0.001;
+
+/// Route is how a bench transaction reaches the leader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Route {
+    /// Straight to the TPU QUIC port of the upcoming leader
+    Quic,
+    /// Through `sendTransaction` of the RPC node, which forwards it to the leaders
+    Rpc,
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Route::Quic => write!(f, "QUIC"),
+            Route::Rpc => write!(f, "RPC"),
+        }
+    }
+}
+
+/// Sent is a bench transaction handed to a leader
+#[derive(Debug, Clone)]
+struct Sent {
+    route: Route,
+    signature: Signature,
+    /// Identity of the leader, unknown through the RPC node
+    leader: Option<String>,
+    sent_at: Instant,
+    /// Slot of the leader tracker at the send
+    target_slot: Slot,
+}
+
+/// BenchReport is the landing rate and latency of the transactions of a bench run sent through a
+/// route
+#[derive(Debug)]
+pub struct BenchReport {
+    pub route: Route,
+    pub count: u64,
+    /// Transactions handed to a leader
+    pub sent: u64,
+    /// Transactions no leader could be reached for
+    pub send_failures: u64,
+    pub landed: u64,
+    /// Transactions that landed with an error
+    pub failed: u64,
+    /// Transactions sent that never landed
+    pub dropped: u64,
+    /// Slots from the tracker slot at the send to the slot the transaction landed in
+    pub inclusion_slots: Histogram<u64>,
+    /// Time from the send to the confirmation, in microseconds, at the resolution of the poll
+    /// interval
+    pub latency: Histogram<u64>,
+    /// Landing of the transactions sent to each leader identity
+    pub leaders: BTreeMap<String, LeaderLanding>,
+    pub compute_unit_price: u64,
+    pub elapsed: Duration,
+}
+
+/// LeaderLanding is how many of the transactions sent to a leader landed
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct LeaderLanding {
+    pub sent: u64,
+    pub landed: u64,
+}
+
+impl BenchReport {
+    fn new(route: Route) -> Self {
+        let histogram = || Histogram::new(3).expect("3 significant figures are supported");
+        Self {
+            route,
+            count: 0,
+            sent: 0,
+            send_failures: 0,
+            landed: 0,
+            failed: 0,
+            dropped: 0,
+            inclusion_slots: histogram(),
+            latency: histogram(),
+            leaders: BTreeMap::new(),
+            compute_unit_price: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// landing_rate returns the share of the sent transactions that landed, in percent
+    pub fn landing_rate(&self) -> f64 {
+        match self.sent {
+            0 => 0.0,
+            sent => self.landed as f64 * 100.0 / sent as f64,
+        }
+    }
+
+    /// drop_rate returns the share of the transactions that didn't land, including those no
+    /// leader could be reached for, in percent
+    pub fn drop_rate(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            count => count.saturating_sub(self.landed) as f64 * 100.0 / count as f64,
+        }
+    }
+
+    /// rows returns the labelled values of the report
+    fn rows(&self) -> Vec<(&'static str, String)> {
+        let percentiles =
+            |histogram: &Histogram<u64>, format: &dyn Fn(u64) -> String| match histogram.is_empty()
+            {
+                true => "none".to_string(),
+                false => format!(
+                    "p50 {}, p90 {}, p99 {}, max {}",
+                    format(histogram.value_at_quantile(0.5)),
+                    format(histogram.value_at_quantile(0.9)),
+                    format(histogram.value_at_quantile(0.99)),
+                    format(histogram.max())
+                ),
+            };
+        vec![
+            (
+                "TRANSACTIONS",
+                format!(
+                    "{} in {:?}, {:.1} TPS",
+                    self.count,
+                    self.elapsed,
+                    self.sent as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+                ),
+            ),
+            (
+                "SENT",
+                format!("{}, {} send failures", self.sent, self.send_failures),
+            ),
+            (
+                "LANDED",
+                format!(
+                    "{} ({:.1}%), {} failed, {} dropped",
+                    self.landed,
+                    self.landing_rate(),
+                    self.failed,
+                    self.dropped
+                ),
+            ),
+            (
+                "SLOTS TO INCLUSION",
+                percentiles(&self.inclusion_slots, &|slots| slots.to_string()),
+            ),
+            (
+                "SLOTS TO INCLUSION BY RANGE",
+                inclusion_buckets(&self.inclusion_slots),
+            ),
+            (
+                "CONFIRMATION LATENCY",
+                percentiles(&self.latency, &|micros| {
+                    format!("{:?}", Duration::from_micros(micros))
+                }),
+            ),
+        ]
+    }
+}
+
+/// log_reports prints the report of every route, side by side when comparing them
+pub fn log_reports(reports: &[BenchReport]) {
+    let title = reports
+        .iter()
+        .map(|report| report.route.to_string())
+        .collect::<Vec<_>>()
+        .join(" vs ");
+    let rows: Vec<_> = reports.iter().map(BenchReport::rows).collect();
+    let lines = rows
+        .first()
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(index, (label, _))| {
+            let values = rows
+                .iter()
+                .map(|rows| rows[index].1.as_str())
+                .collect::<Vec<_>>()
+                .join(" | ");
+            format!("\n\t{}: {}", label, values)
+        })
+        .collect::<String>();
+    info!("[ BENCH {}{}\n]", title, lines);
+}
+
+/// JsonReport is the structured report of a bench or a ramp, written with `--json-report` so
+/// runs on different machines and providers can be compared over time
+#[derive(Debug, Serialize)]
+pub struct JsonReport {
+    /// Version of the tool
+    pub version: &'static str,
+    /// Unix time the report was written at, in seconds
+    pub timestamp: u64,
+    pub settings: Settings,
+    pub environment: Environment,
+    /// Report of every route of a bench
+    pub routes: Vec<RouteReport>,
+    pub ramp: Option<JsonRamp>,
+}
+
+/// Settings is the configuration the bench ran with
+#[derive(Debug, Serialize)]
+pub struct Settings {
+    pub network: &'static str,
+    /// Host of the RPC node, without the path and query that may carry an API key
+    pub rpc_host: String,
+    /// Rate of the bench, the starting rate of a ramp
+    pub tps: f64,
+    pub compare_rpc: bool,
+    pub payload_bytes: usize,
+    pub compute_unit_limit: u32,
+    pub confirm_commitment: String,
+    pub confirm_poll_interval_ms: u64,
+}
+
+impl Settings {
+    pub fn new(config: &Config, tps: f64, compare_rpc: bool) -> Self {
+        Self {
+            network: config.network.name(),
+            rpc_host: reqwest::Url::parse(&config.rpc_url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_default(),
+            tps,
+            compare_rpc,
+            payload_bytes: config.memo.as_ref().map_or(0, String::len),
+            compute_unit_limit: config.compute_unit_limit,
+            confirm_commitment: config.confirm_commitment.commitment.to_string(),
+            confirm_poll_interval_ms: config.confirm_poll_interval.as_millis() as u64,
+        }
+    }
+}
+
+/// Environment is the machine the bench ran on
+#[derive(Debug, Serialize)]
+pub struct Environment {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub cpus: usize,
+}
+
+impl Environment {
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            cpus: std::thread::available_parallelism().map_or(1, |cpus| cpus.get()),
+        }
+    }
+}
+
+/// Percentiles of a histogram, missing if nothing was recorded
+#[derive(Debug, Serialize)]
+pub struct Percentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+impl Percentiles {
+    pub(crate) fn of(histogram: &Histogram<u64>, scale: u64) -> Option<Self> {
+        if histogram.is_empty() {
+            return None;
+        }
+        Some(Self {
+            p50: histogram.value_at_quantile(0.5) / scale,
+            p90: histogram.value_at_quantile(0.9) / scale,
+            p99: histogram.value_at_quantile(0.99) / scale,
+            max: histogram.max() / scale,
+        })
+    }
+}
+
+/// RouteReport is the JSON form of a `BenchReport`
+#[derive(Debug, Serialize)]
+pub struct RouteReport {
+    pub route: Route,
+    pub count: u64,
+    pub sent: u64,
+    pub send_failures: u64,
+    pub landed: u64,
+    pub failed: u64,
+    pub dropped: u64,
+    /// In percent of the sent transactions
+    pub landing_rate: f64,
+    pub compute_unit_price: u64,
+    pub elapsed_ms: u64,
+    pub inclusion_slots: Option<Percentiles>,
+    pub latency_ms: Option<Percentiles>,
+    pub leaders: Vec<LeaderReport>,
+}
+
+impl From<&BenchReport> for RouteReport {
+    fn from(report: &BenchReport) -> Self {
+        Self {
+            route: report.route,
+            count: report.count,
+            sent: report.sent,
+            send_failures: report.send_failures,
+            landed: report.landed,
+            failed: report.failed,
+            dropped: report.dropped,
+            landing_rate: report.landing_rate(),
+            compute_unit_price: report.compute_unit_price,
+            elapsed_ms: report.elapsed.as_millis() as u64,
+            inclusion_slots: Percentiles::of(&report.inclusion_slots, 1),
+            latency_ms: Percentiles::of(&report.latency, 1_000),
+            leaders: report
+                .leaders
+                .iter()
+                .map(|(leader, landing)| LeaderReport {
+                    leader: leader.clone(),
+                    sent: landing.sent,
+                    landed: landing.landed,
+                    landing_rate: landing.landed as f64 * 100.0 / landing.sent.max(1) as f64,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// LeaderReport is the landing rate of the transactions sent to a leader
+#[derive(Debug, Serialize)]
+pub struct LeaderReport {
+    pub leader: String,
+    pub sent: u64,
+    pub landed: u64,
+    /// In percent
+    pub landing_rate: f64,
+}
+
+/// JsonRamp is the JSON form of a `RampReport`
+#[derive(Debug, Serialize)]
+pub struct JsonRamp {
+    pub max_drop_rate: f64,
+    pub max_sustainable_tps: Option<f64>,
+    pub steps: Vec<JsonRampStep>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonRampStep {
+    pub tps: f64,
+    /// In percent of the transactions of the step
+    pub drop_rate: f64,
+    pub report: RouteReport,
+}
+
+impl JsonReport {
+    pub fn bench(settings: Settings, reports: &[BenchReport]) -> Self {
+        Self::new(
+            settings,
+            reports.iter().map(RouteReport::from).collect(),
+            None,
+        )
+    }
+
+    pub fn ramp(settings: Settings, report: &RampReport) -> Self {
+        let ramp = JsonRamp {
+            max_drop_rate: report.max_drop_rate,
+            max_sustainable_tps: report.max_sustainable_tps,
+            steps: report
+                .steps
+                .iter()
+                .map(|step| JsonRampStep {
+                    tps: step.tps,
+                    drop_rate: step.report.drop_rate(),
+                    report: RouteReport::from(&step.report),
+                })
+                .collect(),
+        };
+        Self::new(settings, Vec::new(), Some(ramp))
+    }
+
+    fn new(settings: Settings, routes: Vec<RouteReport>, ramp: Option<JsonRamp>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            settings,
+            environment: Environment::current(),
+            routes,
+            ramp,
+        }
+    }
+
+    /// write writes the report as pretty-printed JSON to the path
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize the bench report: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// inclusion_buckets returns the number of transactions per range of slots to inclusion
+fn inclusion_buckets(histogram: &Histogram<u64>) -> String {
+    let mut buckets: BTreeMap<u64, u64> = BTreeMap::new();
+    for value in histogram.iter_recorded() {
+        // 0, 1, 2-3, 4-7, ...
+        let bucket = match value.value_iterated_to() {
+            0 => 0,
+            slots => 1 << slots.ilog2(),
+        };
+        *buckets.entry(bucket).or_default() += value.count_at_value();
+    }
+    match buckets.is_empty() {
+        true => "none".to_string(),
+        false => buckets
+            .iter()
+            .map(|(&start, count)| match start {
+                0 | 1 => format!("{}: {}", start, count),
+                _ => format!("{}-{}: {}", start, start * 2 - 1, count),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// RampStep is a load step of a ramp at a rate
+#[derive(Debug)]
+pub struct RampStep {
+    pub tps: f64,
+    pub report: BenchReport,
+}
+
+/// RampReport is the result of a ramp, the highest rate that kept the drop rate under the
+/// threshold if any
+#[derive(Debug)]
+pub struct RampReport {
+    pub steps: Vec<RampStep>,
+    /// Threshold of the drop rate, in percent
+    pub max_drop_rate: f64,
+    pub max_sustainable_tps: Option<f64>,
+}
+
+/// log_ramp prints the drop rate of every step of the ramp and the sustainable rate
+pub fn log_ramp(report: &RampReport) {
+    let steps = report
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| {
+            format!(
+                "\n\tSTEP {}: {:.1} TPS, {} of {} landed, {:.1}% dropped",
+                index + 1,
+                step.tps,
+                step.report.landed,
+                step.report.count,
+                step.report.drop_rate()
+            )
+        })
+        .collect::<String>();
+    let sustainable = match report.max_sustainable_tps {
+        Some(tps) => format!("{:.1} TPS", tps),
+        None => "none".to_string(),
+    };
+    info!(
+        "[ BENCH RAMP{}\n\tMAX SUSTAINABLE RATE: {} with at most {}% dropped\n]",
+        steps, sustainable, report.max_drop_rate
+    );
+}
+
+/// ramp looks for the highest rate the current stake and QoS of the sender sustain. Starting at
+/// `tps`, it runs load steps of RAMP_STEP worth of transactions each, raising the rate by
+/// RAMP_FACTOR while the drop rate of a step stays within `max_drop_rate` percent. After the first
+/// step above it, the rate backs off between the highest sustained and the lowest failed rate until
+/// they are within RAMP_PRECISION or MAX_RAMP_STEPS ran
+#[instrument(name = "ramp", skip_all, fields(request_id = %tx_tracker.request_id()))]
+pub async fn ramp<T: LeaderTracker + 'static>(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: Arc<T>,
+    tx_tracker: &TxTracker,
+    tps: f64,
+    max_drop_rate: f64,
+) -> Result<RampReport, String> {
+    send_interval(tps)?;
+    let mut steps = Vec::new();
+    let (mut sustained, mut failed): (Option<f64>, Option<f64>) = (None, None);
+    let mut tps = tps;
+    // the lamports of the self-transfers keep increasing across the steps so that no signature
+    // repeats on the same blockhash
+    let mut lamports = 0;
+    while steps.len() < MAX_RAMP_STEPS {
+        let count = (tps * RAMP_STEP.as_secs_f64()).ceil().max(1.0) as u64;
+        info!(
+            "[ RAMP ] - Step {}: {} transactions at {:.1} TPS",
+            steps.len() + 1,
+            count,
+            tps
+        );
+        let load = Load {
+            count,
+            tps,
+            compare_rpc: false,
+            lamports,
+        };
+        let report = run(
+            config,
+            rpc_client.clone(),
+            tracker.clone(),
+            tx_tracker,
+            load,
+        )
+        .await?
+        .remove(0);
+        lamports += count;
+        let drop_rate = report.drop_rate();
+        info!("[ RAMP ] - {:.1} TPS: {:.1}% dropped", tps, drop_rate);
+        match drop_rate <= max_drop_rate {
+            true => sustained = Some(sustained.map_or(tps, |sustained| sustained.max(tps))),
+            false => failed = Some(failed.map_or(tps, |failed| failed.min(tps))),
+        }
+        steps.push(RampStep { tps, report });
+
+        tps = match (sustained, failed) {
+            (_, None) => tps * RAMP_FACTOR,
+            (None, Some(failed)) => failed / 2.0,
+            (Some(sustained), Some(failed)) if failed / sustained > RAMP_PRECISION => {
+                (sustained + failed) / 2.0
+            }
+            (Some(_), Some(_)) => break,
+        };
+    }
+
+    Ok(RampReport {
+        steps,
+        max_drop_rate,
+        max_sustainable_tps: sustained,
+    })
+}
+
+/// bench sends `count` self-transfers of the sender at `tps` transactions per second through the
+/// leader-targeted QUIC path while following their confirmation, and reports how many landed, how
+/// many slots after the send and how fast. Every transfer moves a different number of lamports so
+/// that the signatures differ. With `compare_rpc` every second transaction is sent through the RPC
+/// node instead, at the same compute unit price, and each route gets its own report
+#[instrument(name = "bench", skip_all, fields(request_id = %tx_tracker.request_id()))]
+pub async fn bench<T: LeaderTracker + 'static>(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: Arc<T>,
+    tx_tracker: &TxTracker,
+    count: u64,
+    tps: f64,
+    compare_rpc: bool,
+) -> Result<Vec<BenchReport>, String> {
+    let load = Load {
+        count,
+        tps,
+        compare_rpc,
+        lamports: 0,
+    };
+    run(config, rpc_client, tracker, tx_tracker, load).await
+}
+
+/// send_interval returns the time between two sends at the rate
+fn send_interval(tps: f64) -> Result<Duration, String> {
+    if !tps.is_finite() || tps < MIN_TPS {
+        return Err(format!("The TPS must be at least {}, got {}", MIN_TPS, tps));
+    }
+    Ok(Duration::from_secs_f64(1.0 / tps).max(Duration::from_micros(1)))
+}
+
+/// Load is the transactions of a bench run
+struct Load {
+    count: u64,
+    tps: f64,
+    compare_rpc: bool,
+    /// The self-transfers move `lamports + 1`, `lamports + 2`, ... lamports
+    lamports: u64,
+}
+
+async fn run<T: LeaderTracker + 'static>(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: Arc<T>,
+    tx_tracker: &TxTracker,
+    load: Load,
+) -> Result<Vec<BenchReport>, String> {
+    let Load {
+        count,
+        tps,
+        compare_rpc,
+        lamports,
+    } = load;
+    if count == 0 {
+        return Err("The count must be positive".to_string());
+    }
+    let send_interval = send_interval(tps)?;
+    let compute_unit_price = fees::get_compute_unit_price(&rpc_client, config).await;
+    let sample = self_transfer_message(config, compute_unit_price, u64::MAX, Hash::default())?;
+    config.check_size(&sample).map_err(|e| e.to_string())?;
+    info!(
+        "[ BENCH ] - Sending {} transactions at {} TPS, compute unit price {}, {} bytes of memo",
+        count,
+        tps,
+        compute_unit_price,
+        config.memo.as_ref().map_or(0, String::len)
+    );
+
+    let mut blockhashes = Blockhashes::default();
+    let connections = quic_manager::connection_pool();
+    warm_up(
+        config,
+        &rpc_client,
+        tracker.as_ref(),
+        tx_tracker,
+        &connections,
+        &mut blockhashes,
+    )
+    .await?;
+
+    let started = Instant::now();
+    let (sent_tx, mut sent_rx) = mpsc::unbounded_channel();
+    let mut quic = BenchReport::new(Route::Quic);
+    let mut rpc = BenchReport::new(Route::Rpc);
+    let mut pending: HashMap<Signature, Sent> = HashMap::new();
+    let mut interval = tokio::time::interval(send_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    let mut poll = tokio::time::interval(config.confirm_poll_interval);
+    poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut built = 0;
+    let mut finished = 0;
+    // the confirmation timeout runs from the last send on
+    let mut all_sent_at = None;
+
+    // sending and polling interleave so the latency of the first transactions doesn't include
+    // the time the last ones are sent
+    loop {
+        tokio::select! {
+            _ = interval.tick(), if built < count => {
+                let tracker_slot = Some(tracker.current_slot()).filter(|slot| *slot > 0);
+                let blockhash = blockhashes.get(config, &rpc_client, tx_tracker, tracker_slot).await?;
+                built += 1;
+                let route = match compare_rpc && built % 2 == 0 {
+                    true => Route::Rpc,
+                    false => Route::Quic,
+                };
+                let transaction = self_transfer(config, compute_unit_price, lamports + built, blockhash)?;
+                tx_tracker.built(transaction.signatures[0]);
+                config.audit(&transaction)?;
+                tokio::spawn(send(
+                    route,
+                    rpc_client.clone(),
+                    connections.clone(),
+                    tracker.clone(),
+                    tx_tracker.clone(),
+                    transaction,
+                    sent_tx.clone(),
+                ));
+            }
+            Some((route, result)) = sent_rx.recv() => {
+                finished += 1;
+                let report = match route {
+                    Route::Quic => &mut quic,
+                    Route::Rpc => &mut rpc,
+                };
+                report.count += 1;
+                match result {
+                    Ok(sent) => {
+                        report.sent += 1;
+                        if let Some(leader) = &sent.leader {
+                            report.leaders.entry(leader.clone()).or_default().sent += 1;
+                        }
+                        pending.insert(sent.signature, sent);
+                    }
+                    Err(_) => report.send_failures += 1,
+                }
+            }
+            _ = poll.tick(), if !pending.is_empty() => {
+                poll_statuses(config, rpc_client.as_ref(), tx_tracker, &mut pending, &mut quic, &mut rpc).await;
+                if let Some(all_sent_at) = all_sent_at {
+                    if lost(config, &rpc_client, all_sent_at, blockhashes.last_valid_block_height).await {
+                        break;
+                    }
+                }
+            }
+            else => break,
+        }
+
+        if finished == count {
+            all_sent_at.get_or_insert_with(Instant::now);
+            if pending.is_empty() {
+                break;
+            }
+        }
+    }
+    drop(sent_tx);
+
+    for (signature, sent) in pending.drain() {
+        tx_tracker.expired(signature);
+        match sent.route {
+            Route::Quic => quic.dropped += 1,
+            Route::Rpc => rpc.dropped += 1,
+        }
+    }
+    let elapsed = started.elapsed();
+    for report in [&mut quic, &mut rpc] {
+        report.elapsed = elapsed;
+        report.compute_unit_price = compute_unit_price;
+    }
+    Ok(match compare_rpc {
+        true => vec![quic, rpc],
+        false => vec![quic],
+    })
+}
+
+/// warm_up opens the connections to the upcoming leaders and fetches the first blockhash before
+/// the measurement starts, so the reports don't include the handshakes and the blockhash fetch.
+/// A leader that can't be reached is left to the sends
+async fn warm_up(
+    config: &Config,
+    rpc_client: &Arc<RpcClient>,
+    tracker: &impl LeaderTracker,
+    tx_tracker: &TxTracker,
+    connections: &ConnectionPool,
+    blockhashes: &mut Blockhashes,
+) -> Result<(), String> {
+    let started = Instant::now();
+    let leaders = tracker.get_leaders();
+    let handshakes = leaders.iter().filter_map(|leader| {
+        let manager = connections.manager(rpc_client.clone(), leader.tpu_quic?);
+        Some(async move {
+            let result = match tokio::time::timeout(WARM_UP_TIMEOUT, manager.handshake()).await {
+                Ok(result) => result.map_err(|e| e.to_string()),
+                Err(_) => Err("timed out".to_string()),
+            };
+            (manager.tpu_quic, result)
+        })
+    });
+    let mut connected = 0;
+    for (tpu_quic, result) in join_all(handshakes).await {
+        match result {
+            Ok(()) => connected += 1,
+            Err(e) => error!(
+                "[ BENCH ] - Warm-up connection to {} failed: {}",
+                tpu_quic, e
+            ),
+        }
+    }
+
+    let tracker_slot = Some(tracker.current_slot()).filter(|slot| *slot > 0);
+    blockhashes
+        .get(config, rpc_client, tx_tracker, tracker_slot)
+        .await?;
+    info!(
+        "[ BENCH ] - Warmed up {} of {} leader connections and the blockhash in {:?}",
+        connected,
+        leaders.len(),
+        started.elapsed()
+    );
+    Ok(())
+}
+
+/// Blockhashes hands out the blockhash of the next transactions, fetching a new one every
+/// BLOCKHASH_REFRESH
+#[derive(Debug, Default)]
+struct Blockhashes {
+    current: Option<(Hash, Instant)>,
+    /// Last block height the newest blockhash is valid for
+    last_valid_block_height: u64,
+}
+
+impl Blockhashes {
+    async fn get(
+        &mut self,
+        config: &Config,
+        rpc_client: &RpcClient,
+        tx_tracker: &TxTracker,
+        tracker_slot: Option<Slot>,
+    ) -> Result<Hash, String> {
+        if let Some((blockhash, fetched_at)) = self.current {
+            if fetched_at.elapsed() < BLOCKHASH_REFRESH {
+                return Ok(blockhash);
+            }
+        }
+        let fetched = tx_builder::recent_blockhash(rpc_client, config, tracker_slot).await?;
+        tx_tracker.blockhash_fetched(&fetched);
+        self.last_valid_block_height = fetched.last_valid_block_height;
+        self.current = Some((fetched.blockhash, Instant::now()));
+        Ok(fetched.blockhash)
+    }
+}
+
+/// self_transfer signs a transfer of the lamports from the sender to itself
+fn self_transfer(
+    config: &Config,
+    compute_unit_price: u64,
+    lamports: u64,
+    blockhash: Hash,
+) -> Result<Transaction, String> {
+    let message = self_transfer_message(config, compute_unit_price, lamports, blockhash)?;
+    config.sign_message(message, blockhash)
+}
+
+/// self_transfer_message builds the self-transfer, with the memo of the config as its payload
+fn self_transfer_message(
+    config: &Config,
+    compute_unit_price: u64,
+    lamports: u64,
+    blockhash: Hash,
+) -> Result<Message, String> {
+    let sender = config.sender();
+    let mut instructions =
+        config.compute_budget_instructions(config.compute_unit_limit, compute_unit_price);
+    instructions.push(transfer(&sender, &sender, lamports));
+    if let Some(memo) = &config.memo {
+        instructions.push(spl_memo::build_memo(memo.as_bytes(), &[&sender]));
+    }
+    Ok(Message::new_with_blockhash(
+        &instructions,
+        Some(&config.fee_payer()?),
+        &blockhash,
+    ))
+}
+
+/// payload returns a memo of `bytes` bytes padding the bench transactions
+pub fn payload(bytes: usize) -> String {
+    "x".repeat(bytes)
+}
+
+/// send hands the transaction to the current leader, or to the RPC node, and reports it on the
+/// channel. The RPC sends aren't reported to the tracker as sent since no leader is known
+async fn send(
+    route: Route,
+    rpc_client: Arc<RpcClient>,
+    connections: ConnectionPool,
+    tracker: Arc<impl LeaderTracker>,
+    tx_tracker: TxTracker,
+    transaction: Transaction,
+    sent_tx: mpsc::UnboundedSender<(Route, Result<Sent, String>)>,
+) {
+    let target_slot = tracker.current_slot();
+    let sent = match route {
+        Route::Quic => {
+            match QuicManager::for_current_leader_in(
+                rpc_client,
+                tracker.as_ref(),
+                Some(&connections),
+            )
+            .await
+            {
+                Ok(manager) => manager
+                    .send_signed_transaction(&transaction, &tx_tracker)
+                    .await
+                    .map(|signature| (signature, manager.leader.clone()))
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            }
+        }
+        Route::Rpc => rpc_client
+            .send_transaction_with_config(
+                &transaction,
+                RpcSendTransactionConfig {
+                    // the QUIC path doesn't simulate either
+                    skip_preflight: true,
+                    ..RpcSendTransactionConfig::default()
+                },
+            )
+            .await
+            .map(|signature| (signature, None))
+            .map_err(|e| format!("Failed to send transaction via RPC: {}", e)),
+    };
+    let result = match sent {
+        Ok((signature, leader)) => Ok(Sent {
+            route,
+            signature,
+            leader,
+            sent_at: Instant::now(),
+            target_slot,
+        }),
+        Err(e) => {
+            error!("[ BENCH ] - {}", e);
+            tx_tracker.failed(transaction.signatures[0], e.clone());
+            Err(e)
+        }
+    };
+    let _ = sent_tx.send((route, result));
+}
+
+/// poll_statuses moves the pending transactions that landed into the report
+async fn poll_statuses(
+    config: &Config,
+    rpc_client: &dyn RpcApi,
+    tx_tracker: &TxTracker,
+    pending: &mut HashMap<Signature, Sent>,
+    quic: &mut BenchReport,
+    rpc: &mut BenchReport,
+) {
+    let signatures: Vec<Signature> = pending.keys().copied().collect();
+    for chunk in signatures.chunks(MAX_SIGNATURE_STATUSES) {
+        let statuses = match rpc_client.get_signature_statuses(chunk).await {
+            Ok(statuses) => statuses,
+            Err(e) => {
+                error!("Failed to get signature statuses: {}", e);
+                continue;
+            }
+        };
+        for (signature, status) in chunk.iter().zip(statuses) {
+            let Some(status) = status else {
+                continue;
+            };
+            match &status.err {
+                Some(err) => tx_tracker.failed(*signature, format!("Transaction failed: {}", err)),
+                None if status.satisfies_commitment(config.confirm_commitment) => {
+                    tx_tracker.confirmed(*signature, status.slot)
+                }
+                None => continue,
+            }
+            let Some(sent) = pending.remove(signature) else {
+                continue;
+            };
+            let report = match sent.route {
+                Route::Quic => &mut *quic,
+                Route::Rpc => &mut *rpc,
+            };
+            report.landed += 1;
+            if let Some(leader) = &sent.leader {
+                report.leaders.entry(leader.clone()).or_default().landed += 1;
+            }
+            if status.err.is_some() {
+                report.failed += 1;
+            }
+            report
+                .inclusion_slots
+                .saturating_record(status.slot.saturating_sub(sent.target_slot));
+            report.latency.saturating_record(
+                u64::try_from(sent.sent_at.elapsed().as_micros()).unwrap_or(u64::MAX),
+            );
+        }
+    }
+}
+
+/// lost tells whether the transactions still pending can't land anymore, because their blockhash
+/// expired or the confirmation timeout passed since the last send
+async fn lost(
+    config: &Config,
+    rpc_client: &RpcClient,
+    all_sent_at: Instant,
+    last_valid_block_height: u64,
+) -> bool {
+    if all_sent_at.elapsed() >= config.confirm_timeout {
+        return true;
+    }
+    matches!(
+        rpc_client.get_block_height().await,
+        Ok(block_height) if block_height > last_valid_block_height
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_too_low_to_send_at_are_rejected() {
+        assert_eq!(send_interval(10.0), Ok(Duration::from_millis(100)));
+        assert_eq!(send_interval(MIN_TPS), Ok(Duration::from_secs(1000)));
+        assert_eq!(send_interval(1e9), Ok(Duration::from_micros(1)));
+        for tps in [1e-300, 0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(send_interval(tps).is_err(), "{} TPS", tps);
+        }
+    }
+}
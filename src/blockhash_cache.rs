@@ -0,0 +1,205 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use solana_sdk::{
+    clock::{Slot, MAX_PROCESSING_AGE},
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    hash::Hash,
+};
+use tokio::time::{sleep, Instant};
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::supervisor;
+
+/// Age after which a cached blockhash isn't handed out anymore, the subscription went silent
+const MAX_AGE: Duration = Duration::from_secs(10);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// CachedBlockhash is the blockhash of the newest block pushed by the subscription
+#[derive(Debug, Clone, Copy)]
+pub struct CachedBlockhash {
+    pub blockhash: Hash,
+    pub last_valid_block_height: u64,
+    /// Slot of the block
+    pub slot: Slot,
+    pub received: Instant,
+}
+
+/// BlockhashCache follows the blocks over the `blockSubscribe` WebSocket subscription, so the
+/// send path gets its blockhash without an RPC round-trip. Only nodes started with
+/// `--rpc-pubsub-enable-block-subscription` serve it, the cache stays empty on the others
+#[derive(Debug, Clone, Default)]
+pub struct BlockhashCache {
+    latest: Arc<RwLock<Option<CachedBlockhash>>>,
+}
+
+impl BlockhashCache {
+    /// subscribe starts following the blocks at the commitment level, reconnecting when the
+    /// connection drops, until `cancel` is cancelled. Processed blocks can't be subscribed to,
+    /// confirmed ones are used instead
+    pub fn subscribe(
+        ws_url: String,
+        commitment: CommitmentConfig,
+        cancel: CancellationToken,
+    ) -> Self {
+        let cache = Self::default();
+        let commitment = match commitment.commitment {
+            CommitmentLevel::Finalized => CommitmentLevel::Finalized,
+            _ => CommitmentLevel::Confirmed,
+        };
+        let listener = cache.clone();
+        supervisor::global().spawn("blockhash-websocket", cancel, move || {
+            let (listener, ws_url) = (listener.clone(), ws_url.clone());
+            async move {
+                loop {
+                    match listener.listen(&ws_url, commitment).await {
+                        Ok(()) => warn!("[ BLOCKHASH ] - WebSocket closed, reconnecting"),
+                        Err(e) => {
+                            error!("[ BLOCKHASH ] - {}, falling back to RPC", e);
+                            return Ok(());
+                        }
+                    }
+                    sleep(RECONNECT_DELAY).await;
+                }
+            }
+        });
+        cache
+    }
+
+    /// latest returns the newest blockhash unless the subscription went silent
+    pub fn latest(&self) -> Option<CachedBlockhash> {
+        self.latest
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .filter(|cached| cached.received.elapsed() < MAX_AGE)
+    }
+
+    /// listen feeds the cache until the connection closes. It fails if the node refuses the
+    /// subscription, retrying wouldn't help
+    async fn listen(&self, ws_url: &str, commitment: CommitmentLevel) -> Result<(), String> {
+        let (ws_stream, _) = match tokio_tungstenite::connect_async(ws_url).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("[ BLOCKHASH ] - Failed to connect: {}", e);
+                return Ok(());
+            }
+        };
+        let (mut write, mut read) = ws_stream.split();
+
+        let subscribe = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "blockSubscribe",
+            "params": ["all", {
+                "commitment": commitment,
+                "encoding": "base64",
+                "transactionDetails": "none",
+                "showRewards": false,
+                "maxSupportedTransactionVersion": 0,
+            }],
+        });
+        if let Err(e) = write.send(Message::Text(subscribe.to_string())).await {
+            error!("[ BLOCKHASH ] - Failed to send subscribe message: {:#?}", e);
+            return Ok(());
+        }
+
+        while let Some(Ok(message)) = read.next().await {
+            let Message::Text(text) = message else {
+                continue;
+            };
+            let Ok(response) = serde_json::from_str::<serde_json::Value>(&text) else {
+                continue;
+            };
+            if let Some(e) = response.get("error") {
+                return Err(format!("blockSubscribe refused: {}", e["message"]));
+            }
+            if response["id"] == 1 {
+                info!(
+                    "[ BLOCKHASH ] - WebSocket subscribed to {:?} blocks",
+                    commitment
+                );
+                continue;
+            }
+
+            let value = &response["params"]["result"]["value"];
+            let block = &value["block"];
+            let (Some(slot), Some(blockhash), Some(block_height)) = (
+                value["slot"].as_u64(),
+                block["blockhash"].as_str(),
+                block["blockHeight"].as_u64(),
+            ) else {
+                continue;
+            };
+            let Ok(blockhash) = Hash::from_str(blockhash) else {
+                continue;
+            };
+            self.update(CachedBlockhash {
+                blockhash,
+                last_valid_block_height: block_height + MAX_PROCESSING_AGE as u64,
+                slot,
+                received: Instant::now(),
+            });
+        }
+        Ok(())
+    }
+
+    /// update caches the blockhash unless a newer block is cached already
+    fn update(&self, cached: CachedBlockhash) {
+        let mut latest = self.latest.write().unwrap_or_else(PoisonError::into_inner);
+        // notifications of older forks may arrive late
+        if latest.is_some_and(|latest| latest.slot >= cached.slot) {
+            return;
+        }
+        *latest = Some(cached);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(slot: Slot) -> CachedBlockhash {
+        CachedBlockhash {
+            blockhash: Hash::new_unique(),
+            last_valid_block_height: slot + MAX_PROCESSING_AGE as u64,
+            slot,
+            received: Instant::now(),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn blockhash_expires_when_the_subscription_goes_silent() {
+        let cache = BlockhashCache::default();
+        let cached = block(10);
+        cache.update(cached);
+
+        sleep(MAX_AGE - Duration::from_millis(1)).await;
+        assert_eq!(
+            cache.latest().map(|latest| latest.blockhash),
+            Some(cached.blockhash)
+        );
+        sleep(Duration::from_millis(1)).await;
+        assert!(cache.latest().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn late_blocks_of_older_slots_are_ignored() {
+        let cache = BlockhashCache::default();
+        let newer = block(11);
+        cache.update(newer);
+        sleep(Duration::from_secs(1)).await;
+        cache.update(block(10));
+        cache.update(block(11));
+
+        let latest = cache.latest().unwrap();
+        assert_eq!(latest.blockhash, newer.blockhash);
+        assert_eq!(latest.received.elapsed(), Duration::from_secs(1));
+    }
+}
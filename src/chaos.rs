@@ -0,0 +1,157 @@
+use std::{fmt, sync::OnceLock, time::Duration};
+
+use async_trait::async_trait;
+use solana_client::{
+    client_error::Result as ClientResult,
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_request::{RpcError, RpcRequest},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use tracing::{debug, warn};
+
+use crate::recording;
+
+/// Faults of the process, none unless installed
+static FAULTS: OnceLock<Faults> = OnceLock::new();
+
+/// Faults are degraded conditions injected on purpose, to check that the retry, resubmit and
+/// fanout settings survive them before trusting them in production
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Faults {
+    /// Share of the QUIC sends dropped as if the packets were lost, in percent
+    pub quic_drop_rate: f64,
+    /// Delay of every slot update of the WebSocket
+    pub slot_delay: Duration,
+    /// Share of the RPC requests failing with HTTP 429, in percent
+    pub rpc_429_rate: f64,
+    /// Lifetime of every slot WebSocket connection before it's killed, never if zero
+    pub ws_disconnect_interval: Duration,
+}
+
+impl Faults {
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// drops_quic_send tells whether the next QUIC send is dropped
+    pub fn drops_quic_send(&self) -> bool {
+        hit(self.quic_drop_rate)
+    }
+}
+
+impl fmt::Display for Faults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}% of the QUIC sends dropped, slot updates {:?} late, {}% of the RPC requests \
+             throttled, slot WebSocket killed every {:?}",
+            self.quic_drop_rate, self.slot_delay, self.rpc_429_rate, self.ws_disconnect_interval
+        )
+    }
+}
+
+/// install injects the faults for the rest of the process. Only the first call has an effect
+pub fn install(faults: Faults) {
+    if faults.is_active() && FAULTS.set(faults).is_ok() {
+        warn!("[ CHAOS ] - Injecting faults: {}", faults);
+    }
+}
+
+/// faults returns the installed faults
+pub fn faults() -> Faults {
+    FAULTS.get().copied().unwrap_or_default()
+}
+
+fn hit(rate: f64) -> bool {
+    rate > 0.0 && rand::random::<f64>() * 100.0 < rate
+}
+
+/// rpc_client returns a client of the RPC node at the URL that fails the share of the requests
+/// set by the faults as if the node throttled them. The requests are recorded or replayed if a
+/// recording was started
+pub fn rpc_client(url: String, commitment: CommitmentConfig, faults: &Faults) -> RpcClient {
+    RpcClient::new_sender(
+        ThrottledSender {
+            inner: recording::rpc_sender(url),
+            rate: faults.rpc_429_rate,
+        },
+        RpcClientConfig::with_commitment(commitment),
+    )
+}
+
+/// ThrottledSender fails a share of the requests with the error of an HTTP 429 response, passing
+/// the others to the inner sender
+pub struct ThrottledSender {
+    pub inner: Box<dyn RpcSender + Send + Sync>,
+    /// Share of the requests failed, in percent
+    pub rate: f64,
+}
+
+#[async_trait]
+impl RpcSender for ThrottledSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        if hit(self.rate) {
+            debug!("[ CHAOS ] - {} throttled", request);
+            return Err(RpcError::RpcRequestError(format!(
+                "HTTP status client error (429 Too Many Requests) for url ({}), injected",
+                self.url()
+            ))
+            .into());
+        }
+        self.inner.send(request, params).await
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_rpc_client::mock_sender::MockSender;
+
+    use super::*;
+
+    fn client(rate: f64) -> RpcClient {
+        RpcClient::new_sender(
+            ThrottledSender {
+                inner: Box::new(MockSender::new("succeeds".to_string())),
+                rate,
+            },
+            RpcClientConfig::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn every_request_is_throttled_at_full_rate() {
+        let error = client(100.0).get_slot().await.unwrap_err();
+        assert!(error.to_string().contains("429 Too Many Requests"));
+    }
+
+    #[tokio::test]
+    async fn no_request_is_throttled_without_faults() {
+        assert!(client(0.0).get_slot().await.is_ok());
+        assert!(!Faults::default().drops_quic_send());
+        assert!(!Faults::default().is_active());
+    }
+
+    #[test]
+    fn every_send_is_dropped_at_full_rate() {
+        let faults = Faults {
+            quic_drop_rate: 100.0,
+            ..Faults::default()
+        };
+        assert!(faults.is_active());
+        assert!((0..100).all(|_| faults.drops_quic_send()));
+    }
+}
@@ -1,33 +1,255 @@
-use std::str::FromStr;
+use std::{collections::BTreeMap, fs, future::Future, str::FromStr, sync::Arc, time::Duration};
 
+use serde::Deserialize;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
     compute_budget::ComputeBudgetInstruction,
     hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    packet::PACKET_DATA_SIZE,
     pubkey::Pubkey,
     signature::{read_keypair_file, Keypair},
     signer::Signer,
     system_instruction::transfer,
     transaction::Transaction,
 };
+use tokio_util::sync::CancellationToken;
 
-#[derive(Debug)]
+use crate::{
+    audit::AuditLog,
+    blockhash_cache::BlockhashCache,
+    confirmation::ConfirmMethod,
+    error::SendError,
+    explorer::{self, Explorer},
+    fees::PriorityFeeSource,
+    signer::{self, ExternalSigner, LoadedKeypair},
+    solana_cli::SolanaCliConfig,
+    token::TokenTransfer,
+    worker_pool,
+};
+
+/// ConfigFile is the optional TOML file passed with `--config`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub fees: FeesSection,
+    #[serde(default)]
+    pub explorer: ExplorerSection,
+    #[serde(default)]
+    pub metrics: MetricsSection,
+    /// Named transfer presets, invoked with `send --template <name>`
+    #[serde(default)]
+    pub templates: BTreeMap<String, Template>,
+}
+
+/// Template is a `[templates.<name>]` section presetting a repetitive transfer
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Template {
+    /// Receiver public key
+    pub receiver: Option<String>,
+    /// Amount in lamports
+    pub amount: Option<u64>,
+    pub memo: Option<String>,
+    pub compute_unit_limit: Option<u32>,
+    pub compute_unit_price: Option<u64>,
+    pub priority_fee_source: Option<PriorityFeeSource>,
+    pub max_fee_lamports: Option<u64>,
+}
+
+/// FeesSection is the `[fees]` section, tuning the dynamic priority fee estimation
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeesSection {
+    /// Percentile of the recent prioritization fees to pay, 50 is the median
+    pub percentile: Option<u8>,
+    /// Only consider fees paid by transactions locking the same writable accounts
+    pub scope_to_writable_accounts: Option<bool>,
+    /// Only consider fees of the last N slots, the RPC returns up to 150
+    pub lookback_slots: Option<u64>,
+    /// Raise the compute unit price after this many attempts without landing
+    pub escalate_after: Option<u8>,
+    /// Factor applied to the compute unit price on each escalated attempt
+    pub escalation_multiplier: Option<f64>,
+    /// Upper bound of the escalated compute unit price, in micro-lamports
+    pub max_compute_unit_price: Option<u64>,
+}
+
+/// ExplorerSection is the `[explorer]` section choosing where result links point to
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExplorerSection {
+    /// Built-in explorer, overridden by `--explorer`
+    pub name: Option<Explorer>,
+    /// Custom transaction URL template with `{signature}` and `{cluster}` placeholders, used
+    /// instead of a built-in explorer
+    pub url: Option<String>,
+}
+
+/// MetricsSink is where the metrics are pushed to while the tool runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsSink {
+    /// Only the end-of-run summary
+    #[default]
+    None,
+    /// StatsD or Datadog DogStatsD agent, over UDP
+    Statsd,
+}
+
+/// MetricsSection is the `[metrics]` section choosing the metrics sink
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsSection {
+    #[serde(default)]
+    pub sink: MetricsSink,
+    /// `host:port` of the StatsD agent, `127.0.0.1:8125` by default
+    pub statsd_host: Option<String>,
+    /// Prefix of the metric names, `quic` by default
+    pub prefix: Option<String>,
+    /// DogStatsD tags added to every metric, e.g. `env:prod`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Slots the leader tracker may fall behind the RPC node before an alert
+    pub max_slot_lag: Option<u64>,
+}
+
+impl ConfigFile {
+    pub fn read(path: &str) -> Result<Self, String> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let file: Self =
+            toml::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path, e))?;
+        if file
+            .fees
+            .percentile
+            .is_some_and(|percentile| percentile > 100)
+        {
+            return Err(format!("{}: fees.percentile must be at most 100", path));
+        }
+        if file
+            .explorer
+            .url
+            .as_ref()
+            .is_some_and(|url| !url.contains(explorer::SIGNATURE_PLACEHOLDER))
+        {
+            return Err(format!(
+                "{}: explorer.url must contain the {} placeholder",
+                path,
+                explorer::SIGNATURE_PLACEHOLDER
+            ));
+        }
+        Ok(file)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Network {
     Mainnet,
     Devnet,
+    Testnet,
     HeliosMainnet,
+    /// A `solana-test-validator` on this machine, sent to without leader tracking
+    Localnet,
 }
 
-#[derive(Debug)]
+impl Network {
+    /// is_mainnet reports whether sends on the network move real funds
+    pub fn is_mainnet(&self) -> bool {
+        matches!(self, Network::Mainnet | Network::HeliosMainnet)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Devnet => "devnet",
+            Network::Testnet => "testnet",
+            Network::HeliosMainnet => "helios-mainnet",
+            Network::Localnet => "localnet",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Config {
     pub rpc_url: String,
     pub ws_url: String,
     pub sender_key: String,
+    /// Sender keypair resolved at startup, e.g. from a seed phrase, overriding `sender_key`
+    pub sender_keypair: Option<LoadedKeypair>,
+    /// Sender key held outside the process (Ledger, KMS), the transaction is signed remotely
+    pub sender_signer: Option<Arc<dyn ExternalSigner>>,
     pub receiver_key: String,
     pub amount: u64,
+    /// Transfer the whole sender balance minus the fee instead of `amount`
+    pub sweep: bool,
     pub retry: u8,
     pub network: Network,
     pub commitment_level: CommitmentConfig,
+    /// Commitment level the transaction must reach to count as confirmed
+    pub confirm_commitment: CommitmentConfig,
+    /// WebSocket subscription the confirmation is detected with, next to status polling
+    pub confirm_method: ConfirmMethod,
+    /// Yellowstone gRPC endpoint used by the Geyser confirmation method
+    pub geyser_url: Option<String>,
+    /// Keep following a confirmed transaction until it's finalized or dropped with its fork
+    pub wait_finalized: bool,
+    /// Total time to wait for a confirmation
+    pub confirm_timeout: Duration,
+    /// Interval between signature status polls
+    pub confirm_poll_interval: Duration,
+    /// Number of slots without confirmation after which the signed transaction is resent, never
+    /// if not set
+    pub resubmit_after_slots: Option<u64>,
+    pub priority_fee_source: PriorityFeeSource,
+    /// Percentile of the recent prioritization fees used by the dynamic fee sources
+    pub priority_fee_percentile: u8,
+    /// Scope the prioritization fee lookup to the writable accounts of the transaction
+    pub priority_fee_scope_accounts: bool,
+    /// Number of recent slots considered by the fee estimation, all returned slots if not set
+    pub priority_fee_lookback_slots: Option<u64>,
+    /// Number of attempts sent at the estimated price before escalating it, never if not set
+    pub fee_escalation_after: Option<u8>,
+    pub fee_escalation_multiplier: f64,
+    /// Cap of the escalated compute unit price
+    pub max_compute_unit_price: Option<u64>,
+    /// Number of escalations applied to the compute unit price of the current attempt
+    pub fee_escalation_step: u32,
+    pub compute_unit_price: u64,
+    pub compute_unit_limit: u32,
+    pub skip_compute_budget: bool,
+    pub simulate_compute_units: bool,
+    /// Margin in percent added on top of the simulated compute units
+    pub compute_unit_margin: u32,
+    pub max_fee_lamports: Option<u64>,
+    /// SPL token to transfer instead of SOL
+    pub token_transfer: Option<TokenTransfer>,
+    /// Text of an SPL Memo instruction appended to the transfer
+    pub memo: Option<String>,
+    /// Custom instructions sent instead of a transfer
+    pub instructions: Option<Vec<Instruction>>,
+    /// Keypair files of additional signers required by the instructions
+    pub signer_keys: Vec<String>,
+    /// Keypair file of the account paying the fees, the sender pays if not set
+    pub fee_payer_key: Option<String>,
+    /// Simulate the signed transaction before sending it
+    pub simulate: bool,
+    /// Send even if the simulation fails
+    pub force: bool,
+    /// URL the outcome of every transaction is posted to
+    pub webhook_url: Option<String>,
+    /// Blockhashes pushed over the WebSocket, fetched over RPC for every transaction if not set
+    pub blockhash_cache: Option<BlockhashCache>,
+    /// Number of concurrent sender workers of batch sends
+    pub workers: usize,
+    /// Log every signed transaction is recorded in before it's sent
+    pub audit_log: Option<AuditLog>,
+    /// Transaction URL template of the explorer, with `{signature}` and `{cluster}` placeholders
+    pub explorer_url: String,
+    /// Cancelling it aborts the sends and confirmations run with this config, shared by its clones
+    pub cancel: CancellationToken,
 }
 
 impl Config {
@@ -36,90 +258,547 @@ impl Config {
             Network::Mainnet => Self {
                 rpc_url: String::from("https://api.mainnet-beta.solana.com"),
                 ws_url: String::from("wss://api.mainnet-beta.solana.com"),
+                sender_keypair: None,
+                sender_signer: None,
                 sender_key: String::from("5HXCzcx2hyJ4N4hNimytnfff5dGMLjuMv9M6kCkEWASWnUsckoNgGEb3SqQQBFQP5SCc6xDuoHtE9Rx3ESrH98xu"),
                 receiver_key: String::from("HXeJrqomDdf4KoDfx36D27Lfffu7jmdVGjUSeEAprLRk"),
                 amount: 1_000,
+                sweep: false,
                 retry,
                 network: Network::Mainnet,
-                commitment_level: CommitmentConfig::finalized()
+                commitment_level: CommitmentConfig::finalized(),
+                confirm_commitment: CommitmentConfig::confirmed(),
+                confirm_method: ConfirmMethod::Signature,
+                geyser_url: None,
+                wait_finalized: false,
+                confirm_timeout: Duration::from_secs(90),
+                confirm_poll_interval: Duration::from_secs(2),
+                resubmit_after_slots: Some(10),
+                priority_fee_source: PriorityFeeSource::Static,
+                priority_fee_percentile: 50,
+                priority_fee_scope_accounts: true,
+                priority_fee_lookback_slots: None,
+                fee_escalation_after: None,
+                fee_escalation_multiplier: 2.0,
+                max_compute_unit_price: None,
+                fee_escalation_step: 0,
+                compute_unit_price: 10_000,
+                compute_unit_limit: 50_000,
+                skip_compute_budget: false,
+                simulate_compute_units: false,
+                compute_unit_margin: 10,
+                max_fee_lamports: None,
+                token_transfer: None,
+                memo: None,
+                instructions: None,
+                signer_keys: Vec::new(),
+                fee_payer_key: None,
+                simulate: false,
+                force: false,
+                webhook_url: None,
+                blockhash_cache: None,
+                workers: worker_pool::DEFAULT_WORKERS,
+                audit_log: None,
+                explorer_url: Explorer::Explorer.url_template(&Network::Mainnet),
+                cancel: CancellationToken::new(),
             },
             Network::Devnet => Self {
                 rpc_url: String::from("https://api.devnet.solana.com"),
                 ws_url: String::from("wss://api.devnet.solana.com"),
+                sender_keypair: None,
+                sender_signer: None,
                 sender_key: String::from("/home/user/.config/solana/devnet.json"),
                 receiver_key: String::from("/home/user/.config/solana/test-receiver.json"),
                 amount: 1_000,
+                sweep: false,
                 retry,
                 network: Network::Devnet,
-                commitment_level: CommitmentConfig::finalized()
+                commitment_level: CommitmentConfig::finalized(),
+                confirm_commitment: CommitmentConfig::confirmed(),
+                confirm_method: ConfirmMethod::Signature,
+                geyser_url: None,
+                wait_finalized: false,
+                confirm_timeout: Duration::from_secs(90),
+                confirm_poll_interval: Duration::from_secs(2),
+                resubmit_after_slots: Some(10),
+                priority_fee_source: PriorityFeeSource::Static,
+                priority_fee_percentile: 50,
+                priority_fee_scope_accounts: true,
+                priority_fee_lookback_slots: None,
+                fee_escalation_after: None,
+                fee_escalation_multiplier: 2.0,
+                max_compute_unit_price: None,
+                fee_escalation_step: 0,
+                compute_unit_price: 10_000,
+                compute_unit_limit: 50_000,
+                skip_compute_budget: false,
+                simulate_compute_units: false,
+                compute_unit_margin: 10,
+                max_fee_lamports: None,
+                token_transfer: None,
+                memo: None,
+                instructions: None,
+                signer_keys: Vec::new(),
+                fee_payer_key: None,
+                simulate: false,
+                force: false,
+                webhook_url: None,
+                blockhash_cache: None,
+                workers: worker_pool::DEFAULT_WORKERS,
+                audit_log: None,
+                explorer_url: Explorer::Explorer.url_template(&Network::Devnet),
+                cancel: CancellationToken::new(),
             },
             Network::HeliosMainnet => Self {
                 rpc_url: String::from("https://mainnet.helius-rpc.com/?api-key=cbf2e1f7-5c84-4ba5-bfba-a66ef18a7faf"),
                 ws_url: String::from("wss://mainnet.helius-rpc.com/?api-key=cb57e1r2-5c84-4ba5-bfba-a66ef18a7faf"),
+                sender_keypair: None,
+                sender_signer: None,
                 sender_key: String::from("5HXCzcx2hyJ4N4hNimytnfff5dGMLjuMv9M6kCkEWASWnUsckoNgGEb3SqQQBFQP5SCc6xDuoHtE9Rx3ESrH98xu"),
                 receiver_key: String::from("HXeJrqomDdf4KoDfx36D2fffdu7jmdVGjUSeEAprLRk"),
                 amount: 1_000,
+                sweep: false,
                 retry,
                 network: Network::HeliosMainnet,
-                commitment_level: CommitmentConfig::finalized()
+                commitment_level: CommitmentConfig::finalized(),
+                confirm_commitment: CommitmentConfig::confirmed(),
+                confirm_method: ConfirmMethod::Signature,
+                geyser_url: None,
+                wait_finalized: false,
+                confirm_timeout: Duration::from_secs(90),
+                confirm_poll_interval: Duration::from_secs(2),
+                resubmit_after_slots: Some(10),
+                priority_fee_source: PriorityFeeSource::Static,
+                priority_fee_percentile: 50,
+                priority_fee_scope_accounts: true,
+                priority_fee_lookback_slots: None,
+                fee_escalation_after: None,
+                fee_escalation_multiplier: 2.0,
+                max_compute_unit_price: None,
+                fee_escalation_step: 0,
+                compute_unit_price: 10_000,
+                compute_unit_limit: 50_000,
+                skip_compute_budget: false,
+                simulate_compute_units: false,
+                compute_unit_margin: 10,
+                max_fee_lamports: None,
+                token_transfer: None,
+                memo: None,
+                instructions: None,
+                signer_keys: Vec::new(),
+                fee_payer_key: None,
+                simulate: false,
+                force: false,
+                webhook_url: None,
+                blockhash_cache: None,
+                workers: worker_pool::DEFAULT_WORKERS,
+                audit_log: None,
+                explorer_url: Explorer::Explorer.url_template(&Network::HeliosMainnet),
+                cancel: CancellationToken::new(),
+            },
+            Network::Testnet => Self {
+                rpc_url: String::from("https://api.testnet.solana.com"),
+                ws_url: String::from("wss://api.testnet.solana.com"),
+                sender_key: String::from("/home/user/.config/solana/testnet.json"),
+                network: Network::Testnet,
+                explorer_url: Explorer::Explorer.url_template(&Network::Testnet),
+                ..Self::new(Network::Devnet, retry)
+            },
+            Network::Localnet => Self {
+                rpc_url: String::from("http://127.0.0.1:8899"),
+                ws_url: String::from("ws://127.0.0.1:8900"),
+                sender_key: String::from("/home/user/.config/solana/id.json"),
+                network: Network::Localnet,
+                commitment_level: CommitmentConfig::confirmed(),
+                explorer_url: Explorer::Explorer.url_template(&Network::Localnet),
+                ..Self::new(Network::Devnet, retry)
             },
         }
     }
 
-    fn setup_sender(&self) -> Keypair {
+    /// apply_file overrides the defaults with the values of the config file
+    pub fn apply_file(&mut self, file: &ConfigFile) {
+        if let Some(percentile) = file.fees.percentile {
+            self.priority_fee_percentile = percentile;
+        }
+        if let Some(scope) = file.fees.scope_to_writable_accounts {
+            self.priority_fee_scope_accounts = scope;
+        }
+        if let Some(lookback_slots) = file.fees.lookback_slots {
+            self.priority_fee_lookback_slots = Some(lookback_slots);
+        }
+        if let Some(escalate_after) = file.fees.escalate_after {
+            self.fee_escalation_after = Some(escalate_after);
+        }
+        if let Some(multiplier) = file.fees.escalation_multiplier {
+            self.fee_escalation_multiplier = multiplier;
+        }
+        if let Some(max_price) = file.fees.max_compute_unit_price {
+            self.max_compute_unit_price = Some(max_price);
+        }
+        if let Some(explorer) = file.explorer.name {
+            self.explorer_url = explorer.url_template(&self.network);
+        }
+        if let Some(url) = &file.explorer.url {
+            self.explorer_url = url.clone();
+        }
+    }
+
+    /// apply_template overrides the config with the values set by the template
+    pub fn apply_template(&mut self, template: &Template) {
+        if let Some(receiver) = &template.receiver {
+            self.receiver_key = receiver.clone();
+        }
+        if let Some(amount) = template.amount {
+            self.amount = amount;
+        }
+        if let Some(memo) = &template.memo {
+            self.memo = Some(memo.clone());
+        }
+        if let Some(limit) = template.compute_unit_limit {
+            self.compute_unit_limit = limit;
+        }
+        if let Some(price) = template.compute_unit_price {
+            self.compute_unit_price = price;
+        }
+        if let Some(source) = template.priority_fee_source {
+            self.priority_fee_source = source;
+        }
+        if let Some(max_fee) = template.max_fee_lamports {
+            self.max_fee_lamports = Some(max_fee);
+        }
+    }
+
+    /// apply_solana_cli points the config at the cluster of the solana-cli config file: its RPC
+    /// and WebSocket URLs and its commitment level
+    pub fn apply_solana_cli(&mut self, solana_cli: &SolanaCliConfig) -> Result<(), String> {
+        if let Some(url) = solana_cli.rpc_url() {
+            self.rpc_url = url.to_string();
+        }
+        if let Some(url) = solana_cli.ws_url() {
+            self.ws_url = url;
+        }
+        if let Some(commitment) = solana_cli.commitment()? {
+            self.commitment_level = commitment;
+        }
+        Ok(())
+    }
+
+    /// for_attempt returns the config of the 0-based send attempt, with the compute unit price
+    /// escalated if the previous attempts didn't land
+    pub fn for_attempt(&self, attempt: u8) -> Config {
+        let fee_escalation_step = match self.fee_escalation_after {
+            // after `escalate_after` attempts, the next one is the first escalated
+            Some(escalate_after) => attempt
+                .saturating_add(1)
+                .saturating_sub(escalate_after)
+                .into(),
+            None => 0,
+        };
+        Config {
+            fee_escalation_step,
+            ..self.clone()
+        }
+    }
+
+    /// until_cancelled runs the operation unless the config is cancelled first, in which case it
+    /// is dropped and `SendError::Cancelled` returned
+    pub async fn until_cancelled<T>(
+        &self,
+        operation: impl Future<Output = Result<T, SendError>>,
+    ) -> Result<T, SendError> {
+        tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => Err(SendError::Cancelled),
+            result = operation => result,
+        }
+    }
+
+    pub fn setup_sender(&self) -> Keypair {
+        if let Some(sender_keypair) = &self.sender_keypair {
+            return sender_keypair.keypair();
+        }
         match self.network {
             Network::Mainnet => Keypair::from_base58_string(&self.sender_key),
             Network::HeliosMainnet => Keypair::from_base58_string(&self.sender_key),
-            Network::Devnet => {
+            Network::Devnet | Network::Testnet | Network::Localnet => {
                 read_keypair_file(&self.sender_key).expect("Unable to read keypair file")
             }
         }
     }
 
-    fn setup_receiver(&self) -> Pubkey {
+    /// sender returns the sender public key, wherever its key is held
+    pub fn sender(&self) -> Pubkey {
+        match &self.sender_signer {
+            Some(signer) => signer.pubkey(),
+            None => self.setup_sender().pubkey(),
+        }
+    }
+
+    /// try_sender is `sender` failing instead of panicking if the sender key can't be loaded
+    pub fn try_sender(&self) -> Result<Pubkey, String> {
+        if let Some(signer) = &self.sender_signer {
+            return Ok(signer.pubkey());
+        }
+        if let Some(sender_keypair) = &self.sender_keypair {
+            return Ok(sender_keypair.keypair().pubkey());
+        }
+        let keypair = match self.network {
+            Network::Mainnet | Network::HeliosMainnet => signer::keypair_from_str(&self.sender_key),
+            Network::Devnet | Network::Testnet | Network::Localnet => {
+                read_keypair_file(&self.sender_key).map_err(|e| {
+                    format!("Failed to read sender keypair {}: {}", self.sender_key, e)
+                })
+            }
+        };
+        Ok(keypair?.pubkey())
+    }
+
+    pub fn setup_receiver(&self) -> Pubkey {
         match self.network {
             Network::Mainnet => Pubkey::from_str(&self.receiver_key).expect("Invalid pubkey"),
             Network::HeliosMainnet => Pubkey::from_str(&self.receiver_key).expect("Invalid pubkey"),
-            Network::Devnet => read_keypair_file(&self.receiver_key)
-                .expect("Failed to read receiver keypair from file")
-                .pubkey(),
+            // a keypair file by default, or a public key set by a template
+            Network::Devnet | Network::Testnet | Network::Localnet => {
+                Pubkey::from_str(&self.receiver_key).unwrap_or_else(|_| {
+                    read_keypair_file(&self.receiver_key)
+                        .expect("Failed to read receiver keypair from file")
+                        .pubkey()
+                })
+            }
         }
     }
 
-    pub fn create_transaction(&self, blockhash: Hash) -> Transaction {
-        let sender = Config::setup_sender(self);
-        let receiver = Config::setup_receiver(self);
+    /// writable_accounts returns the accounts locked for writing by the transfer, used to scope
+    /// priority fee estimation
+    pub fn writable_accounts(&self) -> Vec<Pubkey> {
+        let sender = self.sender();
+        let mut accounts = vec![sender];
+        if let Ok(fee_payer) = self.fee_payer() {
+            if fee_payer != sender {
+                accounts.push(fee_payer);
+            }
+        }
 
-        let compute_unit_limit_instruction =
-            ComputeBudgetInstruction::set_compute_unit_limit(50_000);
-        let compute_unit_price_instruction =
-            ComputeBudgetInstruction::set_compute_unit_price(10000);
+        if let Some(instructions) = &self.instructions {
+            for account in instructions.iter().flat_map(|ix| &ix.accounts) {
+                if account.is_writable && !accounts.contains(&account.pubkey) {
+                    accounts.push(account.pubkey);
+                }
+            }
+            return accounts;
+        }
 
-        let transfer_instruction = transfer(&sender.pubkey(), &receiver, self.amount);
+        let receiver = self.setup_receiver();
+        match &self.token_transfer {
+            Some(token_transfer) => {
+                let (source, destination) = token_transfer.token_accounts(&sender, &receiver);
+                accounts.extend([source, destination]);
+            }
+            None => accounts.push(receiver),
+        }
+        accounts
+    }
 
-        Transaction::new_signed_with_payer(
-            &[
-                compute_unit_limit_instruction,
-                compute_unit_price_instruction,
-                transfer_instruction,
-            ],
-            Some(&sender.pubkey()),
-            &[&sender],
-            blockhash,
-        )
+    /// compute_budget_instructions returns the compute unit limit and price instructions, or
+    /// nothing if the compute budget is skipped
+    pub fn compute_budget_instructions(
+        &self,
+        compute_unit_limit: u32,
+        compute_unit_price: u64,
+    ) -> Vec<Instruction> {
+        if self.skip_compute_budget {
+            return Vec::new();
+        }
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price),
+        ]
     }
 
-    pub fn generate_url(&self, transaction_number: &str) -> String {
-        let base_url = "https://explorer.solana.com/tx/";
-        let cluster = match self.network {
-            Network::Mainnet => "",
-            Network::HeliosMainnet => "",
-            Network::Devnet => "?cluster=devnet",
-        };
+    /// create_message builds the unsigned transfer (or custom instructions) message paid by the
+    /// fee payer
+    pub fn create_message(
+        &self,
+        blockhash: Hash,
+        compute_unit_limit: u32,
+        compute_unit_price: u64,
+    ) -> Result<Message, String> {
+        let sender = self.sender();
+
+        let mut instructions =
+            self.compute_budget_instructions(compute_unit_limit, compute_unit_price);
+        if let Some(custom_instructions) = &self.instructions {
+            instructions.extend(custom_instructions.iter().cloned());
+        } else {
+            let receiver = Config::setup_receiver(self);
+            match &self.token_transfer {
+                Some(token_transfer) => {
+                    instructions.extend(token_transfer.instructions(&sender, &receiver))
+                }
+                None => instructions.push(transfer(&sender, &receiver, self.amount)),
+            }
+        }
+        if let Some(memo) = &self.memo {
+            instructions.push(spl_memo::build_memo(memo.as_bytes(), &[&sender]));
+        }
 
+        Ok(Message::new_with_blockhash(
+            &instructions,
+            Some(&self.fee_payer()?),
+            &blockhash,
+        ))
+    }
+
+    /// setup_fee_payer reads the fee payer keypair if one is configured
+    pub fn setup_fee_payer(&self) -> Result<Option<Keypair>, String> {
+        self.fee_payer_key
+            .as_ref()
+            .map(|path| {
+                read_keypair_file(path)
+                    .map_err(|e| format!("Failed to read fee payer keypair {}: {}", path, e))
+            })
+            .transpose()
+    }
+
+    /// fee_payer returns the account paying the transaction fees
+    pub fn fee_payer(&self) -> Result<Pubkey, String> {
+        Ok(match self.setup_fee_payer()? {
+            Some(fee_payer) => fee_payer.pubkey(),
+            None => self.sender(),
+        })
+    }
+
+    /// check_size verifies the signed transaction of the message fits into a single packet, a
+    /// larger one can't be delivered over QUIC
+    pub fn check_size(&self, message: &Message) -> Result<(), SendError> {
+        // an unsigned transaction carries placeholder signatures of the same size
+        let transaction = Transaction::new_unsigned(message.clone());
+        let size = bincode::serialized_size(&transaction)
+            .map_err(|e| format!("Failed to serialize transaction: {}", e))?
+            as usize;
+        if size > PACKET_DATA_SIZE {
+            return Err(SendError::TransactionTooLarge {
+                size,
+                max: PACKET_DATA_SIZE,
+            });
+        }
+        Ok(())
+    }
+
+    /// setup_signers reads the keypairs of the additional signers
+    pub fn setup_signers(&self) -> Result<Vec<Keypair>, String> {
+        self.signer_keys
+            .iter()
+            .map(|path| {
+                read_keypair_file(path)
+                    .map_err(|e| format!("Failed to read signer keypair {}: {}", path, e))
+            })
+            .collect()
+    }
+
+    /// sign_message signs the message with the sender, the fee payer and every additional signer,
+    /// failing if a required signature is missing or a signer isn't required by the message. An
+    /// external sender signs the serialized message after the local keypairs
+    pub fn sign_message(&self, message: Message, blockhash: Hash) -> Result<Transaction, String> {
+        self.load_signers()?.sign(message, blockhash)
+    }
+
+    /// load_signers reads every keypair signing the transactions once, for signing many of them
+    pub fn load_signers(&self) -> Result<Signers, String> {
+        let mut keypairs: Vec<Keypair> = self.setup_fee_payer()?.into_iter().collect();
+        if self.sender_signer.is_none() {
+            keypairs.push(self.setup_sender());
+        }
+        keypairs.extend(self.setup_signers()?);
+        Ok(Signers {
+            keypairs,
+            external: self.sender_signer.clone(),
+        })
+    }
+
+    /// audit records the signed transaction in the audit log, if any. It must not be sent if this
+    /// fails
+    pub fn audit(&self, transaction: &Transaction) -> Result<(), String> {
+        match &self.audit_log {
+            Some(audit_log) => audit_log.record(transaction),
+            None => Ok(()),
+        }
+    }
+
+    pub fn generate_url(&self, transaction_number: &str) -> String {
         format!(
-            "Check transaction {}{}{}",
-            base_url, transaction_number, cluster
+            "Check transaction {}",
+            self.transaction_url(transaction_number)
         )
     }
+
+    /// transaction_url returns the explorer link of the transaction
+    pub fn transaction_url(&self, signature: &str) -> String {
+        explorer::transaction_url(&self.explorer_url, &self.network, signature)
+    }
+}
+
+/// Signers holds the keypairs signing the transactions, and the external sender signer if any
+pub struct Signers {
+    keypairs: Vec<Keypair>,
+    external: Option<Arc<dyn ExternalSigner>>,
+}
+
+impl Signers {
+    /// is_local reports whether every signature is made in the process, so transactions can be
+    /// signed in parallel. An external signer, e.g. a Ledger, signs one at a time
+    pub fn is_local(&self) -> bool {
+        self.external.is_none()
+    }
+
+    /// sign is `Config::sign_message` with the loaded signers
+    pub fn sign(&self, message: Message, blockhash: Hash) -> Result<Transaction, String> {
+        let keypairs: Vec<&Keypair> = self.keypairs.iter().collect();
+        let mut transaction = Transaction::new_unsigned(message);
+        let Some(external_signer) = &self.external else {
+            transaction
+                .try_sign(&keypairs, blockhash)
+                .map_err(|e| format!("Failed to sign transaction: {}", e))?;
+            return Ok(transaction);
+        };
+
+        transaction
+            .try_partial_sign(&keypairs, blockhash)
+            .map_err(|e| format!("Failed to sign transaction: {}", e))?;
+        let position = transaction
+            .get_signing_keypair_positions(&[external_signer.pubkey()])
+            .map_err(|e| format!("Failed to sign transaction: {}", e))?[0]
+            .ok_or("The sender key is not a signer of the transaction")?;
+        transaction.signatures[position] =
+            external_signer.sign_message(&transaction.message_data())?;
+        if !transaction.is_signed() {
+            return Err("Failed to sign transaction: not enough signers".to_string());
+        }
+        Ok(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escalation_starts_with_the_attempt_after_escalate_after() {
+        let config = Config {
+            fee_escalation_after: Some(2),
+            ..Config::new(Network::Devnet, 5)
+        };
+
+        let steps: Vec<u32> = (0..5)
+            .map(|attempt| config.for_attempt(attempt).fee_escalation_step)
+            .collect();
+        assert_eq!(steps, [0, 0, 1, 2, 3]);
+        assert_eq!(
+            Config::new(Network::Devnet, 5)
+                .for_attempt(4)
+                .fee_escalation_step,
+            0
+        );
+    }
 }
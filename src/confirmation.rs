@@ -0,0 +1,601 @@
+use std::{collections::HashMap, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature,
+    transaction::TransactionError,
+};
+use solana_transaction_status::{TransactionStatus, UiTransactionEncoding};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tracing::{error, field, info, instrument, Span};
+
+use crate::{config::Config, error::SendError, tx_error};
+
+#[cfg(feature = "geyser")]
+mod geyser;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ConfirmMethod {
+    /// `signatureSubscribe` notification for the signature, supported by every RPC node
+    Signature,
+    /// `blockSubscribe` to the blocks mentioning the sender, where the provider supports it
+    Block,
+    /// Yellowstone gRPC transaction stream of the sender (`geyser` feature)
+    Geyser,
+}
+
+/// Confirmation is where a transaction landed and how long confirming it took
+#[derive(Debug, Clone, Copy)]
+pub struct Confirmation {
+    pub slot: Slot,
+    pub elapsed: Duration,
+}
+
+/// check_confirm_transaction waits up to the confirmation timeout for the transaction to be
+/// confirmed, through a WebSocket notification or status polling, whichever comes first. Polling
+/// keeps going on its own if the WebSocket subscription fails. Cancelling the config stops waiting
+#[instrument(name = "confirmation", skip_all, fields(signature = %signature, slot = field::Empty))]
+pub async fn check_confirm_transaction(
+    rpc_client: &RpcClient,
+    config: &Config,
+    signature: &Signature,
+    last_valid_block_height: Option<u64>,
+) -> Result<Confirmation, SendError> {
+    let started = Instant::now();
+    let slot = match tokio::time::timeout(
+        config.confirm_timeout,
+        config.until_cancelled(wait_for_confirmation(
+            rpc_client,
+            config,
+            signature,
+            last_valid_block_height,
+        )),
+    )
+    .await
+    {
+        Ok(result) => result?,
+        Err(_) => lookup_history(rpc_client, &[*signature], config.confirm_commitment)
+            .await
+            .remove(signature)
+            .unwrap_or_else(|| {
+                Err(format!(
+                    "Transaction not confirmed within {:?}",
+                    config.confirm_timeout
+                )
+                .into())
+            })?,
+    };
+    Span::current().record("slot", slot);
+
+    Ok(Confirmation {
+        slot,
+        elapsed: started.elapsed(),
+    })
+}
+
+async fn wait_for_confirmation(
+    rpc_client: &RpcClient,
+    config: &Config,
+    signature: &Signature,
+    last_valid_block_height: Option<u64>,
+) -> Result<Slot, SendError> {
+    let notification = async {
+        match config.confirm_method {
+            ConfirmMethod::Signature => {
+                subscribe_signature(&config.ws_url, signature, config.confirm_commitment).await
+            }
+            ConfirmMethod::Block => {
+                subscribe_block(
+                    &config.ws_url,
+                    &config.sender(),
+                    signature,
+                    config.confirm_commitment,
+                )
+                .await
+            }
+            ConfirmMethod::Geyser => match &config.geyser_url {
+                #[cfg(feature = "geyser")]
+                Some(geyser_url) => {
+                    geyser::subscribe_transactions(
+                        geyser_url,
+                        &config.sender(),
+                        signature,
+                        config.confirm_commitment,
+                    )
+                    .await
+                }
+                #[cfg(not(feature = "geyser"))]
+                Some(_) => Err("Geyser confirmation requires the `geyser` feature".to_string()),
+                None => Err("Geyser confirmation requires --geyser-url".to_string()),
+            },
+        }
+    };
+    let polling = poll_signature_status(
+        rpc_client,
+        signature,
+        config.confirm_commitment,
+        config.confirm_poll_interval,
+        last_valid_block_height,
+    );
+    tokio::pin!(notification, polling);
+
+    tokio::select! {
+        result = &mut notification => match result {
+            Ok((slot, None)) => {
+                info!("[ WS ] - transaction confirmed");
+                Ok(slot)
+            }
+            Ok((_, Some(err))) => {
+                let program_ids = get_program_ids(rpc_client, signature).await;
+                Err(format!(
+                    "Transaction failed: {}",
+                    tx_error::explain(&err, &program_ids)
+                )
+                .into())
+            }
+            Err(e) => {
+                error!("WebSocket subscription failed, polling: {}", e);
+                polling.await
+            }
+        },
+        result = &mut polling => result,
+    }
+}
+
+/// subscribe_signature waits for the `signatureSubscribe` notification of the transaction at the
+/// commitment level, returning the slot and the error if it failed on-chain
+async fn subscribe_signature(
+    ws_url: &str,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Result<(Slot, Option<TransactionError>), String> {
+    let (mut ws_stream, _) = tokio_tungstenite::connect_async(ws_url)
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", ws_url, e))?;
+
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "signatureSubscribe",
+        "params": [signature.to_string(), { "commitment": commitment.commitment }],
+    });
+    ws_stream
+        .send(Message::Text(request.to_string()))
+        .await
+        .map_err(|e| format!("Failed to send signatureSubscribe: {}", e))?;
+
+    while let Some(message) = ws_stream.next().await {
+        let message = message.map_err(|e| format!("WebSocket error: {}", e))?;
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let Ok(response) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        if let Some(error) = response.get("error") {
+            return Err(format!("signatureSubscribe failed: {}", error));
+        }
+        if response["method"] == "signatureNotification" {
+            let result = &response["params"]["result"];
+            let slot = result["context"]["slot"].as_u64().unwrap_or_default();
+            let err = serde_json::from_value(result["value"]["err"].clone())
+                .map_err(|e| format!("Invalid signatureNotification: {}", e))?;
+            return Ok((slot, err));
+        }
+    }
+
+    Err("WebSocket closed before the signature notification".to_string())
+}
+
+/// subscribe_block watches the `blockSubscribe` stream of blocks mentioning the sender at the
+/// commitment level until one includes the transaction, returning the slot of the block and the
+/// error if it failed on-chain
+async fn subscribe_block(
+    ws_url: &str,
+    sender: &Pubkey,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Result<(Slot, Option<TransactionError>), String> {
+    if !commitment.is_at_least_confirmed() {
+        return Err("blockSubscribe supports confirmed and finalized commitment only".to_string());
+    }
+    let (mut ws_stream, _) = tokio_tungstenite::connect_async(ws_url)
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", ws_url, e))?;
+
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "blockSubscribe",
+        "params": [
+            { "mentionsAccountOrProgram": sender.to_string() },
+            {
+                "commitment": commitment.commitment,
+                "encoding": "base64",
+                "transactionDetails": "accounts",
+                "showRewards": false,
+                "maxSupportedTransactionVersion": 0,
+            },
+        ],
+    });
+    ws_stream
+        .send(Message::Text(request.to_string()))
+        .await
+        .map_err(|e| format!("Failed to send blockSubscribe: {}", e))?;
+
+    let signature = signature.to_string();
+    while let Some(message) = ws_stream.next().await {
+        let message = message.map_err(|e| format!("WebSocket error: {}", e))?;
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let Ok(response) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        if let Some(error) = response.get("error") {
+            return Err(format!("blockSubscribe failed: {}", error));
+        }
+        if response["method"] != "blockNotification" {
+            continue;
+        }
+
+        let value = &response["params"]["result"]["value"];
+        let Some(transactions) = value["block"]["transactions"].as_array() else {
+            continue;
+        };
+        let included = transactions.iter().find(|transaction| {
+            transaction["transaction"]["signatures"]
+                .as_array()
+                .is_some_and(|signatures| signatures.iter().any(|s| *s == signature))
+        });
+        if let Some(transaction) = included {
+            let slot = value["slot"].as_u64().unwrap_or_default();
+            let err = serde_json::from_value(transaction["meta"]["err"].clone())
+                .map_err(|e| format!("Invalid blockNotification: {}", e))?;
+            return Ok((slot, err));
+        }
+    }
+
+    Err("WebSocket closed before the block including the transaction".to_string())
+}
+
+/// poll_signature_status polls the signature status until the transaction reaches the commitment
+/// level. With the last valid block height of its blockhash known it stops once the chain passes
+/// that height, after which the transaction can never land
+async fn poll_signature_status(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+    poll_interval: Duration,
+    last_valid_block_height: Option<u64>,
+) -> Result<Slot, SendError> {
+    loop {
+        let statuses = rpc_client
+            .get_signature_statuses(&[*signature])
+            .await
+            .map_err(|e| format!("Failed to get signature statuses: {}", e))?;
+
+        if let Some(Some(status)) = statuses.value.first() {
+            if let Some(result) = final_result(rpc_client, signature, status, commitment).await {
+                return result;
+            }
+            info!(
+                "Transaction {:?}, waiting for {:?}...",
+                status.confirmation_status, commitment.commitment
+            );
+        }
+
+        if let Some(last_valid_block_height) = last_valid_block_height {
+            // the block height at the client commitment trails the tip, so the blockhash is
+            // certainly expired once it is passed
+            let block_height = rpc_client
+                .get_block_height()
+                .await
+                .map_err(|e| format!("Failed to get block height: {}", e))?;
+            if block_height > last_valid_block_height {
+                return lookup_history(rpc_client, &[*signature], commitment)
+                    .await
+                    .remove(signature)
+                    .unwrap_or(Err(SendError::BlockhashExpired {
+                        last_valid_block_height,
+                    }));
+            }
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// first_landed returns the first of the transactions that landed without error and the slot it
+/// landed in, searching the history past the recent status cache in a single request. None if the
+/// cluster doesn't know any of them or they failed
+pub async fn first_landed(
+    rpc_client: &RpcClient,
+    signatures: &[Signature],
+) -> Option<(Signature, Slot)> {
+    match rpc_client
+        .get_signature_statuses_with_history(signatures)
+        .await
+    {
+        Ok(statuses) => signatures
+            .iter()
+            .zip(statuses.value)
+            .find_map(|(signature, status)| {
+                status
+                    .filter(|status| status.err.is_none())
+                    .map(|status| (*signature, status.slot))
+            }),
+        Err(e) => {
+            error!(
+                "Failed to get signature statuses of {} transactions: {}",
+                signatures.len(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// wait_finalized follows a confirmed transaction until it's finalized, returning the slot it was
+/// finalized in. Once the cluster finalized past the confirmed slot without the transaction, its
+/// fork was abandoned and the transaction dropped. Cancelling the config stops following it
+pub async fn wait_finalized(
+    rpc_client: &RpcClient,
+    config: &Config,
+    signature: &Signature,
+    confirmed_slot: Slot,
+) -> Result<Slot, SendError> {
+    config
+        .until_cancelled(follow_finalization(
+            rpc_client,
+            config,
+            signature,
+            confirmed_slot,
+        ))
+        .await
+}
+
+async fn follow_finalization(
+    rpc_client: &RpcClient,
+    config: &Config,
+    signature: &Signature,
+    confirmed_slot: Slot,
+) -> Result<Slot, SendError> {
+    let finalized = CommitmentConfig::finalized();
+    let deadline = Instant::now() + config.confirm_timeout;
+    info!(
+        "[ FINALIZATION ] - waiting for {} to be finalized",
+        signature
+    );
+    loop {
+        // the finalized slot is read first, so the status can't be older than it
+        let finalized_slot = rpc_client
+            .get_slot_with_commitment(finalized)
+            .await
+            .map_err(|e| format!("Failed to get finalized slot: {}", e))?;
+        let statuses = rpc_client
+            .get_signature_statuses(&[*signature])
+            .await
+            .map_err(|e| format!("Failed to get signature statuses: {}", e))?;
+        let status = statuses.value.into_iter().next().flatten();
+
+        let landed_slot = match status {
+            Some(status) if status.satisfies_commitment(finalized) => {
+                if status.slot != confirmed_slot {
+                    info!(
+                        "[ FINALIZATION ] - {} moved from slot {} to {}",
+                        signature, confirmed_slot, status.slot
+                    );
+                }
+                info!("[ FINALIZATION ] - finalized in slot {}", status.slot);
+                return Ok(status.slot);
+            }
+            Some(status) => status.slot,
+            None => confirmed_slot,
+        };
+        // the cluster rooted past the slot without it, so that slot isn't on the finalized fork
+        if finalized_slot >= landed_slot {
+            return lookup_history(rpc_client, &[*signature], finalized)
+                .await
+                .remove(signature)
+                .unwrap_or(Err(SendError::DroppedInFork { slot: landed_slot }));
+        }
+        if Instant::now() >= deadline {
+            return Err(format!(
+                "Transaction not finalized within {:?}",
+                config.confirm_timeout
+            )
+            .into());
+        }
+        tokio::time::sleep(config.confirm_poll_interval).await;
+    }
+}
+
+/// Maximum number of signatures per `getSignatureStatuses` request
+const MAX_SIGNATURE_STATUSES: usize = 256;
+
+/// confirm_many waits for many transactions sharing a blockhash, polling their statuses in chunks
+/// of the RPC limit until each one reached the commitment level, failed or expired with the
+/// blockhash, or the confirmation timeout passed. `on_status` is called for each signature as soon
+/// as its status is final. Once the config is cancelled, the pending ones end as cancelled
+pub async fn confirm_many(
+    rpc_client: &RpcClient,
+    config: &Config,
+    signatures: &[Signature],
+    last_valid_block_height: u64,
+    mut on_status: impl FnMut(&Signature, &Result<Slot, SendError>),
+) -> HashMap<Signature, Result<Slot, SendError>> {
+    let mut results = HashMap::with_capacity(signatures.len());
+    let mut pending = signatures.to_vec();
+    let deadline = Instant::now() + config.confirm_timeout;
+
+    while !pending.is_empty() {
+        if config.cancel.is_cancelled() {
+            for signature in pending.drain(..) {
+                let result = Err(SendError::Cancelled);
+                on_status(&signature, &result);
+                results.insert(signature, result);
+            }
+            break;
+        }
+        let mut still_pending = Vec::with_capacity(pending.len());
+        for chunk in pending.chunks(MAX_SIGNATURE_STATUSES) {
+            let statuses = match rpc_client.get_signature_statuses(chunk).await {
+                Ok(statuses) => statuses.value,
+                Err(e) => {
+                    error!("Failed to get signature statuses: {}", e);
+                    still_pending.extend_from_slice(chunk);
+                    continue;
+                }
+            };
+
+            for (signature, status) in chunk.iter().zip(statuses) {
+                let result = match status {
+                    Some(status) => {
+                        final_result(rpc_client, signature, &status, config.confirm_commitment)
+                            .await
+                    }
+                    None => None,
+                };
+                match result {
+                    Some(result) => {
+                        on_status(signature, &result);
+                        results.insert(*signature, result);
+                    }
+                    None => still_pending.push(*signature),
+                }
+            }
+        }
+        pending = still_pending;
+        if pending.is_empty() {
+            break;
+        }
+
+        info!(
+            "{} of {} transactions confirmed, {} pending",
+            results.len(),
+            signatures.len(),
+            pending.len()
+        );
+        let expired = match rpc_client.get_block_height().await {
+            Ok(block_height) if block_height > last_valid_block_height => true,
+            _ if Instant::now() >= deadline => false,
+            Ok(_) => {
+                pause(config).await;
+                continue;
+            }
+            Err(e) => {
+                error!("Failed to get block height: {}", e);
+                pause(config).await;
+                continue;
+            }
+        };
+        let mut found = lookup_history(rpc_client, &pending, config.confirm_commitment).await;
+        for signature in pending.drain(..) {
+            let result = found.remove(&signature).unwrap_or_else(|| {
+                Err(match expired {
+                    true => SendError::BlockhashExpired {
+                        last_valid_block_height,
+                    },
+                    false => format!(
+                        "Transaction not confirmed within {:?}",
+                        config.confirm_timeout
+                    )
+                    .into(),
+                })
+            });
+            on_status(&signature, &result);
+            results.insert(signature, result);
+        }
+        break;
+    }
+
+    results
+}
+
+/// pause waits for the poll interval, or until the config is cancelled
+async fn pause(config: &Config) {
+    tokio::select! {
+        _ = config.cancel.cancelled() => {}
+        _ = tokio::time::sleep(config.confirm_poll_interval) => {}
+    }
+}
+
+/// final_result returns the result of a transaction whose status is final: failed on-chain or
+/// at the commitment level. None if it's still on its way
+async fn final_result(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+    status: &TransactionStatus,
+    commitment: CommitmentConfig,
+) -> Option<Result<Slot, SendError>> {
+    match &status.err {
+        Some(err) => {
+            let program_ids = get_program_ids(rpc_client, signature).await;
+            Some(Err(format!(
+                "Transaction failed: {}",
+                tx_error::explain(err, &program_ids)
+            )
+            .into()))
+        }
+        None if status.satisfies_commitment(commitment) => Some(Ok(status.slot)),
+        None => None,
+    }
+}
+
+/// lookup_history re-queries the statuses with `searchTransactionHistory`, which also finds
+/// transactions that already left the recent status cache of the node, before they are declared
+/// lost. Returns the result of those whose status is final
+async fn lookup_history(
+    rpc_client: &RpcClient,
+    signatures: &[Signature],
+    commitment: CommitmentConfig,
+) -> HashMap<Signature, Result<Slot, SendError>> {
+    let mut results = HashMap::new();
+    for chunk in signatures.chunks(MAX_SIGNATURE_STATUSES) {
+        let statuses = match rpc_client.get_signature_statuses_with_history(chunk).await {
+            Ok(statuses) => statuses.value,
+            Err(e) => {
+                error!("Failed to search the transaction history: {}", e);
+                continue;
+            }
+        };
+        for (signature, status) in chunk.iter().zip(statuses) {
+            let Some(status) = status else {
+                continue;
+            };
+            if let Some(result) = final_result(rpc_client, signature, &status, commitment).await {
+                info!("[ HISTORY ] - {} found in slot {}", signature, status.slot);
+                results.insert(*signature, result);
+            }
+        }
+    }
+    results
+}
+
+/// get_program_ids returns the program invoked by each instruction of a landed transaction, used
+/// to decode its custom program errors
+async fn get_program_ids(rpc_client: &RpcClient, signature: &Signature) -> Vec<Pubkey> {
+    let transaction = rpc_client
+        .get_transaction(signature, UiTransactionEncoding::Base64)
+        .await
+        .ok()
+        .and_then(|transaction| transaction.transaction.transaction.decode());
+
+    match transaction {
+        Some(transaction) => {
+            let account_keys = transaction.message.static_account_keys();
+            transaction
+                .message
+                .instructions()
+                .iter()
+                .map(|instruction| *instruction.program_id(account_keys))
+                .collect()
+        }
+        None => Vec::new(),
+    }
+}
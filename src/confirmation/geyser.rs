@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+use solana_sdk::{
+    clock::Slot,
+    commitment_config::{CommitmentConfig, CommitmentLevel as RpcCommitmentLevel},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::TransactionError,
+};
+use yellowstone_grpc_client::GeyserGrpcClient;
+use yellowstone_grpc_proto::prelude::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequestFilterTransactions,
+};
+
+/// Environment variable holding the `x-token` of the Geyser endpoint
+pub const GEYSER_TOKEN_ENV: &str = "GEYSER_X_TOKEN";
+
+/// subscribe_transactions watches the Yellowstone transaction stream of the sender at the
+/// commitment level until it carries the transaction, returning its slot and the error if it
+/// failed on-chain
+pub async fn subscribe_transactions(
+    endpoint: &str,
+    sender: &Pubkey,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Result<(Slot, Option<TransactionError>), String> {
+    let mut client = GeyserGrpcClient::connect(
+        endpoint.to_string(),
+        std::env::var(GEYSER_TOKEN_ENV).ok(),
+        None,
+    )
+    .map_err(|e| format!("Failed to connect to {}: {}", endpoint, e))?;
+
+    let transactions = HashMap::from([(
+        "sender".to_string(),
+        SubscribeRequestFilterTransactions {
+            vote: Some(false),
+            failed: None,
+            signature: None,
+            account_include: vec![sender.to_string()],
+            account_exclude: Vec::new(),
+            account_required: Vec::new(),
+        },
+    )]);
+    let commitment = match commitment.commitment {
+        RpcCommitmentLevel::Processed => CommitmentLevel::Processed,
+        RpcCommitmentLevel::Finalized => CommitmentLevel::Finalized,
+        _ => CommitmentLevel::Confirmed,
+    };
+    let stream = client
+        .subscribe_once(
+            HashMap::new(),
+            HashMap::new(),
+            transactions,
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            Some(commitment),
+            Vec::new(),
+        )
+        .await
+        .map_err(|e| format!("Failed to subscribe to {}: {}", endpoint, e))?;
+    tokio::pin!(stream);
+
+    let signature = signature.as_ref();
+    while let Some(update) = stream.next().await {
+        let update = update.map_err(|e| format!("Geyser stream error: {}", e))?;
+        let Some(UpdateOneof::Transaction(update)) = update.update_oneof else {
+            continue;
+        };
+        let Some(transaction) = update.transaction else {
+            continue;
+        };
+        if transaction.signature != signature {
+            continue;
+        }
+
+        let err = match transaction.meta.and_then(|meta| meta.err) {
+            Some(err) => Some(
+                bincode::deserialize(&err.err)
+                    .map_err(|e| format!("Invalid transaction error: {}", e))?,
+            ),
+            None => None,
+        };
+        return Ok((update.slot, err));
+    }
+
+    Err("Geyser stream closed before the transaction".to_string())
+}
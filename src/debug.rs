@@ -0,0 +1,169 @@
+use std::{net::SocketAddr, sync::atomic::Ordering};
+
+use serde::Serialize;
+use solana_connection_cache::connection_cache_stats::ConnectionCacheStats;
+use solana_sdk::clock::Slot;
+
+use crate::{
+    leader_tracker::{LeaderTracker, LeaderTrackerImpl},
+    quic_manager::{self, ConnectionPool},
+    supervisor::{self, TaskHealth},
+    tx_tracker::{TxState, TxStates},
+};
+
+/// Dump is the internal state printed by `debug dump` and on SIGUSR1, to find out why a
+/// transaction wasn't sent. The connections and the in-flight transactions belong to the process
+/// doing the sends, only the SIGUSR1 dump has them
+#[derive(Debug, Serialize)]
+pub struct Dump {
+    /// Latest slot seen by the leader tracker
+    pub slot: Slot,
+    /// Number of upcoming slots whose leader is known
+    pub cached_slots: usize,
+    /// Leaders of the slots the next send picks from
+    pub leader_window: Vec<WindowSlot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connections: Option<ConnectionStats>,
+    /// Transactions built or sent that haven't reached a final state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_flight: Option<Vec<InFlight>>,
+    /// Background tasks with their restarts, a task stuck restarting keeps the state stale
+    pub tasks: Vec<TaskHealth>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WindowSlot {
+    pub slot: Slot,
+    pub leader: String,
+    pub tpu_quic: Option<SocketAddr>,
+}
+
+/// ConnectionStats are the totals of every QUIC connection to the leaders, with the connections
+/// the sender workers keep open in their pool. A single send outside of the pool opens its own
+#[derive(Debug, Serialize)]
+pub struct ConnectionStats {
+    pub connections: u64,
+    pub reused: u64,
+    pub errors: u64,
+    pub packets: u64,
+    pub pooled: Vec<PooledConnection>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PooledConnection {
+    pub tpu_quic: SocketAddr,
+    /// `done`, or `pending` until the first send or the bench warm-up connects it
+    pub handshake: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InFlight {
+    pub signature: String,
+    /// `built` or `sent`
+    pub state: &'static str,
+    pub leader: Option<String>,
+    /// Slot the leader tracker was at when the transaction was sent
+    pub target_slot: Option<Slot>,
+}
+
+impl Dump {
+    /// capture takes the state of the leader tracker and the background tasks
+    pub fn capture(tracker: &LeaderTrackerImpl) -> Self {
+        Self {
+            slot: tracker.current_slot(),
+            cached_slots: tracker.cached_slots(),
+            leader_window: tracker
+                .leader_window()
+                .into_iter()
+                .map(|(slot, leader)| WindowSlot {
+                    slot,
+                    leader: leader.pubkey,
+                    tpu_quic: leader.tpu_quic,
+                })
+                .collect(),
+            connections: None,
+            in_flight: None,
+            tasks: supervisor::global().health(),
+        }
+    }
+
+    /// with_sends adds the QUIC connections and the in-flight transactions of the running sends
+    pub fn with_sends(
+        self,
+        states: &TxStates,
+        stats: &ConnectionCacheStats,
+        pool: &ConnectionPool,
+    ) -> Self {
+        let client = &stats.total_client_stats;
+        let mut in_flight: Vec<_> = states
+            .snapshot()
+            .into_iter()
+            .filter_map(|(signature, state)| {
+                let (state, leader, target_slot) = match state {
+                    TxState::Built => ("built", None, None),
+                    TxState::Sent { leader, slot, .. } => ("sent", Some(leader), slot),
+                    _ => return None,
+                };
+                Some(InFlight {
+                    signature: signature.to_string(),
+                    state,
+                    leader,
+                    target_slot,
+                })
+            })
+            .collect();
+        in_flight.sort_by(|a, b| a.signature.cmp(&b.signature));
+
+        Self {
+            connections: Some(ConnectionStats {
+                connections: client.total_connections.load(Ordering::Relaxed),
+                reused: client.connection_reuse.load(Ordering::Relaxed),
+                errors: client.connection_errors.load(Ordering::Relaxed),
+                packets: client.successful_packets.load(Ordering::Relaxed),
+                pooled: pool
+                    .handshakes()
+                    .into_iter()
+                    .map(|(tpu_quic, connected)| PooledConnection {
+                        tpu_quic,
+                        handshake: match connected {
+                            true => "done",
+                            false => "pending",
+                        },
+                    })
+                    .collect(),
+            }),
+            in_flight: Some(in_flight),
+            ..self
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize dump: {}", e))
+    }
+}
+
+/// dump_on_signal prints the dump to stderr every time the process receives SIGUSR1, e.g. from
+/// `kill -USR1 <pid>` during a long batch. It never returns
+#[cfg(unix)]
+pub async fn dump_on_signal(tracker: std::sync::Arc<LeaderTrackerImpl>, states: TxStates) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut signals = match signal(SignalKind::user_defined1()) {
+        Ok(signals) => signals,
+        Err(e) => {
+            tracing::error!("[ DEBUG ] - Failed to listen for SIGUSR1: {}", e);
+            return;
+        }
+    };
+    while signals.recv().await.is_some() {
+        let dump = Dump::capture(&tracker).with_sends(
+            &states,
+            &quic_manager::connection_stats(),
+            &quic_manager::connection_pool(),
+        );
+        match dump.to_json() {
+            Ok(json) => eprintln!("{}", json),
+            Err(e) => tracing::error!("[ DEBUG ] - {}", e),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use solana_sdk::transaction::TransactionError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SendError {
+    #[error("expected fee of {fee} lamports exceeds the limit of {max_fee} lamports")]
+    FeeTooHigh { fee: u64, max_fee: u64 },
+    #[error("insufficient funds: {required} lamports required, {available} available")]
+    InsufficientFunds { required: u64, available: u64 },
+    #[error("transaction is {size} bytes, exceeding the packet limit of {max} bytes")]
+    TransactionTooLarge { size: usize, max: usize },
+    #[error("simulation failed: {explanation}")]
+    SimulationFailed {
+        err: TransactionError,
+        explanation: String,
+    },
+    #[error("blockhash expired at block height {last_valid_block_height} before the transaction was confirmed, it can no longer land")]
+    BlockhashExpired { last_valid_block_height: u64 },
+    #[error("transaction confirmed in slot {slot} was dropped with its fork before finalization")]
+    DroppedInFork { slot: u64 },
+    #[error("deadline of {deadline:?} exceeded")]
+    DeadlineExceeded { deadline: Duration },
+    #[error("cancelled")]
+    Cancelled,
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<String> for SendError {
+    fn from(message: String) -> Self {
+        SendError::Other(message)
+    }
+}
@@ -0,0 +1,75 @@
+use serde::Deserialize;
+
+use crate::config::Network;
+
+/// Placeholder of the transaction signature in explorer URL templates
+pub const SIGNATURE_PLACEHOLDER: &str = "{signature}";
+/// Placeholder of the cluster name (`mainnet-beta`, `devnet`, `custom`) in explorer URL templates
+pub const CLUSTER_PLACEHOLDER: &str = "{cluster}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Explorer {
+    /// explorer.solana.com
+    Explorer,
+    /// solscan.io
+    Solscan,
+    /// solana.fm
+    Solanafm,
+    /// Helius XRAY
+    Xray,
+}
+
+impl Explorer {
+    /// url_template returns the transaction URL template of the explorer, with the cluster query
+    /// parameter it expects for the network. Only explorer.solana.com shows a local validator
+    pub fn url_template(self, network: &Network) -> String {
+        let (base_url, devnet, testnet) = match self {
+            Explorer::Explorer => (
+                "https://explorer.solana.com/tx/",
+                "?cluster=devnet",
+                "?cluster=testnet",
+            ),
+            Explorer::Solscan => (
+                "https://solscan.io/tx/",
+                "?cluster=devnet",
+                "?cluster=testnet",
+            ),
+            Explorer::Solanafm => (
+                "https://solana.fm/tx/",
+                "?cluster=devnet-solana",
+                "?cluster=testnet-solana",
+            ),
+            Explorer::Xray => (
+                "https://xray.helius.xyz/tx/",
+                "?network=devnet",
+                "?network=testnet",
+            ),
+        };
+
+        let cluster = match (network, self) {
+            (Network::Devnet, _) => devnet,
+            (Network::Testnet, _) => testnet,
+            (Network::Localnet, Explorer::Explorer) => {
+                "?cluster=custom&customUrl=http%3A%2F%2F127.0.0.1%3A8899"
+            }
+            _ => "",
+        };
+
+        format!("{}{}{}", base_url, SIGNATURE_PLACEHOLDER, cluster)
+    }
+}
+
+/// transaction_url fills the signature and the cluster of the network into a URL template
+pub fn transaction_url(template: &str, network: &Network, signature: &str) -> String {
+    let cluster = match network {
+        Network::Mainnet | Network::HeliosMainnet => "mainnet-beta",
+        Network::Devnet => "devnet",
+        Network::Testnet => "testnet",
+        Network::Localnet => "custom",
+    };
+    template
+        .replace(SIGNATURE_PLACEHOLDER, signature)
+        .replace(CLUSTER_PLACEHOLDER, cluster)
+}
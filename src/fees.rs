@@ -0,0 +1,321 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig,
+    rpc_request::RpcRequest,
+};
+use solana_sdk::{
+    fee::FeeStructure, hash::Hash, message::Message, pubkey::Pubkey, transaction::Transaction,
+};
+use tracing::{error, info};
+
+use crate::config::Config;
+
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum PriorityFeeSource {
+    /// Fixed compute unit price from the config
+    Static,
+    /// Standard `getRecentPrioritizationFees`, supported by every RPC node
+    Rpc,
+    /// Helius `getPriorityFeeEstimate`
+    Helius,
+    /// Triton `getRecentPrioritizationFees` with the `percentile` extension
+    Triton,
+}
+
+/// get_compute_unit_price returns the compute unit price (in micro-lamports) for the next
+/// transaction, falling back to the static config value if the estimation fails
+pub async fn get_compute_unit_price(rpc_client: &RpcClient, config: &Config) -> u64 {
+    if config.skip_compute_budget {
+        return 0;
+    }
+    escalate(
+        config,
+        estimate_compute_unit_price(rpc_client, config).await,
+    )
+}
+
+/// escalate raises the price by the multiplier once per escalation step, up to the configured cap
+fn escalate(config: &Config, price: u64) -> u64 {
+    if config.fee_escalation_step == 0 {
+        return price;
+    }
+    let factor = config
+        .fee_escalation_multiplier
+        .powi(config.fee_escalation_step as i32);
+    // a zero price can't be multiplied up, start from the smallest one
+    let mut escalated = (price.max(1) as f64 * factor).ceil().min(u64::MAX as f64) as u64;
+    if let Some(max_price) = config.max_compute_unit_price {
+        escalated = escalated.min(max_price.max(price));
+    }
+    info!(
+        "[ PRIORITY FEE ] - escalated to {} micro-lamports (step {})",
+        escalated, config.fee_escalation_step
+    );
+    escalated
+}
+
+async fn estimate_compute_unit_price(rpc_client: &RpcClient, config: &Config) -> u64 {
+    let accounts = if config.priority_fee_scope_accounts {
+        config.writable_accounts()
+    } else {
+        Vec::new()
+    };
+    let estimate = match config.priority_fee_source {
+        PriorityFeeSource::Static => return config.compute_unit_price,
+        PriorityFeeSource::Rpc => {
+            get_recent_prioritization_fees(rpc_client, config, &accounts).await
+        }
+        PriorityFeeSource::Helius => {
+            get_helius_priority_fee_estimate(rpc_client, config, &accounts).await
+        }
+        PriorityFeeSource::Triton => get_triton_priority_fee(rpc_client, config, &accounts).await,
+    };
+
+    match estimate {
+        Ok(price) => {
+            info!(
+                "[ PRIORITY FEE ] - {} micro-lamports ({:?}, p{})",
+                price, config.priority_fee_source, config.priority_fee_percentile
+            );
+            price
+        }
+        Err(e) => {
+            error!(
+                "Priority fee estimation failed, using static price {}: {}",
+                config.compute_unit_price, e
+            );
+            config.compute_unit_price
+        }
+    }
+}
+
+/// get_compute_unit_limit returns the compute unit limit for the next transaction. With
+/// simulation enabled it is the simulated consumption plus the configured margin, otherwise (or
+/// if the simulation fails) the static config value
+pub async fn get_compute_unit_limit(
+    rpc_client: &RpcClient,
+    config: &Config,
+    blockhash: Hash,
+) -> u32 {
+    if config.skip_compute_budget || !config.simulate_compute_units {
+        return config.compute_unit_limit;
+    }
+
+    match simulate_compute_units(rpc_client, config, blockhash).await {
+        Ok(units_consumed) => {
+            let limit = units_consumed
+                .saturating_add(units_consumed * config.compute_unit_margin as u64 / 100)
+                .min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32;
+            info!(
+                "[ COMPUTE UNITS ] - consumed {}, limit {} (+{}%)",
+                units_consumed, limit, config.compute_unit_margin
+            );
+            limit
+        }
+        Err(e) => {
+            error!(
+                "Compute unit simulation failed, using static limit {}: {}",
+                config.compute_unit_limit, e
+            );
+            config.compute_unit_limit
+        }
+    }
+}
+
+async fn simulate_compute_units(
+    rpc_client: &RpcClient,
+    config: &Config,
+    blockhash: Hash,
+) -> Result<u64, String> {
+    // simulate with the maximum limit so the measurement itself can't run out of compute units
+    let message = config.create_message(blockhash, MAX_COMPUTE_UNIT_LIMIT, 0)?;
+    let transaction = Transaction::new_unsigned(message);
+    let result = rpc_client
+        .simulate_transaction_with_config(
+            &transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+        .map_err(|e| format!("Error simulating transaction: {}", e))?
+        .value;
+
+    if let Some(err) = result.err {
+        return Err(format!("Simulation error: {}", err));
+    }
+    result
+        .units_consumed
+        .ok_or_else(|| "Simulation did not return units consumed".to_string())
+}
+
+/// expected_fee returns the fee in lamports the message will be charged: the base fee for each
+/// signature plus the priority fee for the requested compute units
+pub fn expected_fee(message: &Message, compute_unit_limit: u32, compute_unit_price: u64) -> u64 {
+    let base_fee = FeeStructure::default().lamports_per_signature
+        * message.header.num_required_signatures as u64;
+    // the compute unit price is in micro-lamports
+    let priority_fee =
+        (compute_unit_limit as u128 * compute_unit_price as u128).div_ceil(1_000_000) as u64;
+    base_fee + priority_fee
+}
+
+async fn get_recent_prioritization_fees(
+    rpc_client: &RpcClient,
+    config: &Config,
+    accounts: &[Pubkey],
+) -> Result<u64, String> {
+    let fees = rpc_client
+        .get_recent_prioritization_fees(accounts)
+        .await
+        .map_err(|e| format!("Error getting recent prioritization fees: {}", e))?;
+
+    percentile(
+        within_lookback(
+            config,
+            fees.into_iter()
+                .map(|fee| (fee.slot, fee.prioritization_fee))
+                .collect(),
+        ),
+        config.priority_fee_percentile,
+    )
+}
+
+async fn get_helius_priority_fee_estimate(
+    rpc_client: &RpcClient,
+    config: &Config,
+    accounts: &[Pubkey],
+) -> Result<u64, String> {
+    let account_keys: Vec<String> = accounts.iter().map(|key| key.to_string()).collect();
+    let mut options =
+        json!({ "priorityLevel": helius_priority_level(config.priority_fee_percentile) });
+    if let Some(lookback_slots) = config.priority_fee_lookback_slots {
+        options["lookbackSlots"] = json!(lookback_slots);
+    }
+    let response: Value = rpc_client
+        .send(
+            RpcRequest::Custom {
+                method: "getPriorityFeeEstimate",
+            },
+            json!([{
+                "accountKeys": account_keys,
+                "options": options,
+            }]),
+        )
+        .await
+        .map_err(|e| format!("Error getting Helius priority fee estimate: {}", e))?;
+
+    response["priorityFeeEstimate"]
+        .as_f64()
+        .map(|fee| fee.ceil() as u64)
+        .ok_or_else(|| format!("Unexpected getPriorityFeeEstimate response: {}", response))
+}
+
+/// helius_priority_level maps a percentile to the closest Helius priority level at or above it
+fn helius_priority_level(percentile: u8) -> &'static str {
+    match percentile {
+        0 => "Min",
+        1..=25 => "Low",
+        26..=50 => "Medium",
+        51..=75 => "High",
+        76..=95 => "VeryHigh",
+        _ => "UnsafeMax",
+    }
+}
+
+async fn get_triton_priority_fee(
+    rpc_client: &RpcClient,
+    config: &Config,
+    accounts: &[Pubkey],
+) -> Result<u64, String> {
+    let account_keys: Vec<String> = accounts.iter().map(|key| key.to_string()).collect();
+    // Triton computes the percentile of every slot itself, in basis points
+    let response: Vec<Value> = rpc_client
+        .send(
+            RpcRequest::GetRecentPrioritizationFees,
+            json!([account_keys, { "percentile": config.priority_fee_percentile as u32 * 100 }]),
+        )
+        .await
+        .map_err(|e| format!("Error getting Triton prioritization fees: {}", e))?;
+
+    percentile(
+        within_lookback(
+            config,
+            response
+                .iter()
+                .filter_map(|fee| Some((fee["slot"].as_u64()?, fee["prioritizationFee"].as_u64()?)))
+                .collect(),
+        ),
+        config.priority_fee_percentile,
+    )
+}
+
+/// within_lookback keeps the fees of the configured number of most recent slots
+fn within_lookback(config: &Config, fees: Vec<(u64, u64)>) -> Vec<u64> {
+    let newest_slot = fees.iter().map(|(slot, _)| *slot).max().unwrap_or_default();
+    let oldest_slot = config.priority_fee_lookback_slots.map_or(0, |lookback| {
+        newest_slot.saturating_sub(lookback.saturating_sub(1))
+    });
+    fees.into_iter()
+        .filter(|(slot, _)| *slot >= oldest_slot)
+        .map(|(_, fee)| fee)
+        .collect()
+}
+
+/// percentile returns the nearest-rank percentile of the fees
+fn percentile(mut fees: Vec<u64>, percentile: u8) -> Result<u64, String> {
+    if fees.is_empty() {
+        return Err("No prioritization fees returned".to_string());
+    }
+    fees.sort_unstable();
+    let rank = (fees.len() * percentile as usize).div_ceil(100).max(1);
+    Ok(fees[rank - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Network;
+
+    fn config(step: u32, max_compute_unit_price: Option<u64>) -> Config {
+        Config {
+            fee_escalation_after: Some(2),
+            fee_escalation_multiplier: 1.5,
+            fee_escalation_step: step,
+            max_compute_unit_price,
+            ..Config::new(Network::Devnet, 5)
+        }
+    }
+
+    #[test]
+    fn price_is_raised_once_per_step() {
+        assert_eq!(escalate(&config(0, None), 10_000), 10_000);
+        assert_eq!(escalate(&config(1, None), 10_000), 15_000);
+        assert_eq!(escalate(&config(2, None), 10_000), 22_500);
+        // a zero price escalates from the smallest one
+        assert_eq!(escalate(&config(1, None), 0), 2);
+    }
+
+    #[test]
+    fn escalated_price_is_capped() {
+        assert_eq!(escalate(&config(3, Some(20_000)), 10_000), 20_000);
+        // a cap below the estimate doesn't lower it
+        assert_eq!(escalate(&config(1, Some(5_000)), 10_000), 10_000);
+    }
+
+    #[test]
+    fn third_attempt_is_the_first_escalated_after_two() {
+        let config = config(0, Some(500_000));
+        let prices: Vec<u64> = (0..4)
+            .map(|attempt| escalate(&config.for_attempt(attempt), 10_000))
+            .collect();
+        assert_eq!(prices, [10_000, 10_000, 15_000, 22_500]);
+    }
+}
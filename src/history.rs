@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig, signature::Signature};
+use tokio::sync::broadcast;
+use tracing::error;
+
+use crate::{
+    outcome::FeeLookups,
+    tx_tracker::{TxEvent, TxState},
+};
+
+/// default_path returns `~/.quic/history.jsonl`
+pub fn default_path() -> PathBuf {
+    let home = std::env::var_os("HOME").unwrap_or_else(|| ".".into());
+    Path::new(&home).join(".quic").join("history.jsonl")
+}
+
+/// Record is the outcome of one submitted transaction, a line of the history file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// ID of the submission, missing in records written by older versions
+    #[serde(default)]
+    pub request_id: Option<String>,
+    pub signature: String,
+    /// Unix time of the outcome, in seconds
+    pub timestamp: u64,
+    /// Leaders the transaction was sent to, in order
+    pub leaders: Vec<String>,
+    /// `confirmed`, `failed` or `expired`
+    pub status: String,
+    pub slot: Option<Slot>,
+    pub error: Option<String>,
+    /// Fee charged in lamports
+    pub fee: Option<u64>,
+}
+
+/// History is the append-only JSONL store of submission outcomes
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn append(&self, record: &Record) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let mut line = serde_json::to_string(record)
+            .map_err(|e| format!("Failed to serialize history record: {}", e))?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+
+    /// read returns every record, oldest first
+    pub fn read(&self) -> Result<Vec<Record>, String> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read {}: {}", self.path.display(), e)),
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("{}: line {}: {}", self.path.display(), index + 1, e))
+            })
+            .collect()
+    }
+
+    /// record appends the outcome of every transaction reaching a final state until the tracker
+    /// is dropped, with the leaders it was sent to and the fee it was charged
+    pub async fn record(
+        self,
+        rpc_client: Arc<RpcClient>,
+        commitment: CommitmentConfig,
+        mut events: broadcast::Receiver<TxEvent>,
+    ) {
+        let (fees, mut resolved) = FeeLookups::new(rpc_client, commitment, "HISTORY");
+        let events = async move {
+            let mut leaders: HashMap<Signature, Vec<String>> = HashMap::new();
+            loop {
+                let (request_id, signature, state) = match events.recv().await {
+                    Ok(TxEvent::Transition {
+                        request_id,
+                        signature,
+                        state,
+                    }) => (request_id, signature, state),
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        error!("[ HISTORY ] - {} events skipped", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                let (status, slot, error) = match state {
+                    TxState::Sent { leader, .. } => {
+                        leaders.entry(signature).or_default().push(leader);
+                        continue;
+                    }
+                    TxState::Built => continue,
+                    TxState::Confirmed { slot } => ("confirmed", Some(slot), None),
+                    TxState::Failed { error } => ("failed", None, Some(error)),
+                    TxState::Expired => ("expired", None, None),
+                };
+                let record = Record {
+                    request_id: Some(request_id.to_string()),
+                    signature: signature.to_string(),
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|elapsed| elapsed.as_secs())
+                        .unwrap_or_default(),
+                    leaders: leaders.remove(&signature).unwrap_or_default(),
+                    status: status.to_string(),
+                    slot,
+                    error,
+                    fee: None,
+                };
+                fees.lookup(slot.map(|_| signature), record);
+            }
+        };
+        let writer = async {
+            while let Some((mut record, fee)) = resolved.recv().await {
+                record.fee = fee;
+                if let Err(e) = self.append(&record) {
+                    error!("[ HISTORY ] - {}", e);
+                }
+            }
+        };
+        tokio::join!(events, writer);
+    }
+}
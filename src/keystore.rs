@@ -0,0 +1,126 @@
+use std::fs;
+
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::{read_keypair_file, Keypair};
+
+use crate::signer;
+
+/// Environment variable holding the passphrase of an encrypted keypair file
+pub const PASSPHRASE_ENV: &str = "SOLANA_KEYPAIR_PASSPHRASE";
+
+const KDF: &str = "argon2id";
+const CIPHER: &str = "aes-256-gcm";
+
+/// EncryptedKeypair is a keypair file encrypted with AES-256-GCM under a key derived from a
+/// passphrase with Argon2id
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptedKeypair {
+    pub kdf: String,
+    pub cipher: String,
+    /// Base64 salt of the key derivation
+    pub salt: String,
+    /// Base64 nonce of the cipher
+    pub nonce: String,
+    /// Base64 encrypted keypair bytes with the authentication tag
+    pub ciphertext: String,
+}
+
+impl EncryptedKeypair {
+    pub fn encrypt(keypair: &Keypair, passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt)?);
+        let ciphertext = cipher
+            .encrypt(&nonce, keypair.to_bytes().as_ref())
+            .map_err(|e| format!("Failed to encrypt keypair: {}", e))?;
+
+        Ok(Self {
+            kdf: KDF.to_string(),
+            cipher: CIPHER.to_string(),
+            salt: STANDARD.encode(salt),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair, String> {
+        if self.kdf != KDF || self.cipher != CIPHER {
+            return Err(format!(
+                "Unsupported keypair encryption {}/{}",
+                self.kdf, self.cipher
+            ));
+        }
+        let decode = |field: &str, value: &str| {
+            STANDARD
+                .decode(value)
+                .map_err(|e| format!("Invalid {} in encrypted keypair: {}", field, e))
+        };
+        let salt = decode("salt", &self.salt)?;
+        let nonce = decode("nonce", &self.nonce)?;
+        let ciphertext = decode("ciphertext", &self.ciphertext)?;
+        if nonce.len() != 12 {
+            return Err("Invalid nonce in encrypted keypair".to_string());
+        }
+
+        let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt)?);
+        let bytes = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| "Failed to decrypt keypair: wrong passphrase".to_string())?;
+        Keypair::from_bytes(&bytes).map_err(|e| format!("Invalid keypair: {}", e))
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, String> {
+    let mut key = Key::<Aes256Gcm>::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key: {}", e))?;
+    Ok(key)
+}
+
+/// passphrase returns the passphrase from `SOLANA_KEYPAIR_PASSPHRASE`, prompting for it without
+/// echo if the variable isn't set
+pub fn passphrase(prompt: &str) -> Result<String, String> {
+    match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => rpassword::prompt_password(prompt)
+            .map_err(|e| format!("Failed to read passphrase: {}", e)),
+    }
+}
+
+/// read_keypair reads a plaintext or an encrypted keypair file, asking for the passphrase of an
+/// encrypted one
+pub fn read_keypair(path: &str) -> Result<Keypair, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read keypair {}: {}", path, e))?;
+    match serde_json::from_str::<EncryptedKeypair>(&content) {
+        Ok(encrypted) => encrypted.decrypt(&passphrase(&format!("Passphrase for {}: ", path))?),
+        Err(_) => signer::keypair_from_str(&content)
+            .map_err(|e| format!("Failed to read keypair {}: {}", path, e)),
+    }
+}
+
+/// encrypt_keypair_file writes an encrypted copy of a plaintext keypair file
+pub fn encrypt_keypair_file(input: &str, output: &str) -> Result<(), String> {
+    let keypair =
+        read_keypair_file(input).map_err(|e| format!("Failed to read keypair {}: {}", input, e))?;
+    let passphrase = passphrase("New passphrase: ")?;
+    if std::env::var(PASSPHRASE_ENV).is_err()
+        && rpassword::prompt_password("Confirm passphrase: ")
+            .map_err(|e| format!("Failed to read passphrase: {}", e))?
+            != passphrase
+    {
+        return Err("Passphrases don't match".to_string());
+    }
+
+    let content = serde_json::to_string_pretty(&EncryptedKeypair::encrypt(&keypair, &passphrase)?)
+        .map_err(|e| format!("Failed to serialize {}: {}", output, e))?;
+    fs::write(output, content).map_err(|e| format!("Failed to write {}: {}", output, e))
+}
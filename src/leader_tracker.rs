@@ -1,8 +1,9 @@
 use std::{
     collections::HashMap,
+    ops::Range,
     sync::{
         atomic::{AtomicU64, Ordering},
-        Arc,
+        Arc, PoisonError, RwLock,
     },
     time::Duration,
 };
@@ -10,107 +11,318 @@ use std::{
 use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
 use indexmap::IndexMap;
-use solana_client::{nonblocking::rpc_client::RpcClient, rpc_response::RpcContactInfo};
-use solana_sdk::clock::Slot;
-use tokio::time::sleep;
+use solana_client::rpc_response::RpcContactInfo;
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig};
+use tokio::time::{sleep, sleep_until, Instant};
 use tokio_tungstenite::tungstenite::protocol::Message;
-use tracing::{error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    chaos,
+    metrics::{self, Metrics},
+    recording,
+    rpc_api::RpcApi,
+    supervisor,
+    webhook::{Alert, Webhook},
+};
 
 pub trait LeaderTracker: Send + Sync {
     /// get_leaders returns the next slot leaders in order
-    fn get_leaders(&self) -> Vec<RpcContactInfo>;
+    fn get_leaders(&self) -> Arc<[RpcContactInfo]>;
+
+    /// current_slot returns the latest slot seen by the tracker, 0 if none yet
+    fn current_slot(&self) -> Slot;
 }
 
 const NUM_LEADERS_PER_SLOT: usize = 4;
 
+/// Slots the tracker may fall behind the RPC node before an alert, unless configured
+pub const DEFAULT_MAX_SLOT_LAG: u64 = 20;
+const SLOT_LAG_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Time without a slot update over the WebSocket after which the slot is polled over RPC
+const SLOT_STALE_AFTER: Duration = Duration::from_secs(2);
+/// Interval of the slot polls over RPC while the WebSocket is silent, about a slot
+const SLOT_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
 #[derive(Clone)]
 pub struct LeaderTrackerImpl {
-    rpc_client: Arc<RpcClient>,
+    rpc_client: Arc<dyn RpcApi>,
     cur_slot: Arc<AtomicU64>,
     cur_leaders: Arc<DashMap<Slot, RpcContactInfo>>,
+    /// Immutable snapshot of the next leaders, replaced on every slot and schedule update so
+    /// get_leaders only clones a pointer
+    upcoming: Arc<RwLock<Arc<[RpcContactInfo]>>>,
+    /// Time the last slot update arrived over the WebSocket
+    slot_received: Arc<RwLock<Instant>>,
     num_leaders: usize,
     leader_offset: i64,
+    /// Stops the background tasks of the tracker
+    cancel: CancellationToken,
 }
 
 impl LeaderTrackerImpl {
     pub async fn new(
-        rpc_client: Arc<RpcClient>,
+        rpc_client: Arc<dyn RpcApi>,
         num_leaders: usize,
         leader_offset: i64,
         ws_url: String,
     ) -> Self {
-        let cur_slot = Arc::new(AtomicU64::new(0));
+        Self::cancellable(
+            rpc_client,
+            num_leaders,
+            leader_offset,
+            ws_url,
+            CancellationToken::new(),
+        )
+        .await
+    }
 
-        let initial_slot = rpc_client.get_slot().await.unwrap_or(0);
-        cur_slot.store(initial_slot, Ordering::Relaxed);
+    /// cancellable returns a tracker like `new` whose slot listener and polls stop once `cancel`
+    /// is cancelled, e.g. on shutdown
+    pub async fn cancellable(
+        rpc_client: Arc<dyn RpcApi>,
+        num_leaders: usize,
+        leader_offset: i64,
+        ws_url: String,
+        cancel: CancellationToken,
+    ) -> Self {
+        let leader_tracker = Self::driven(rpc_client, num_leaders, leader_offset, cancel).await;
+        leader_tracker.start_websocket_listener(ws_url);
+        leader_tracker
+    }
+
+    /// driven returns a tracker polling the leader schedule like `cancellable`, but moved from
+    /// slot to slot only by `set_slot` instead of the WebSocket, e.g. by a simulated slot clock
+    pub async fn driven(
+        rpc_client: Arc<dyn RpcApi>,
+        num_leaders: usize,
+        leader_offset: i64,
+        cancel: CancellationToken,
+    ) -> Self {
+        let initial_slot = rpc_client
+            .get_slot(CommitmentConfig::processed())
+            .await
+            .unwrap_or(0);
 
         let leader_tracker = Self {
             rpc_client,
-            cur_slot,
+            cur_slot: Arc::new(AtomicU64::new(initial_slot)),
             cur_leaders: Arc::new(DashMap::new()),
+            upcoming: Arc::new(RwLock::new(Arc::from([]))),
+            slot_received: Arc::new(RwLock::new(Instant::now())),
             num_leaders,
             leader_offset,
+            cancel,
         };
-        leader_tracker.start_websocket_listener(ws_url);
         leader_tracker.poll_slot_leaders();
         leader_tracker
     }
 
-    /// Start WebSocket listener for slot updates
+    /// local returns a tracker always targeting the TPU QUIC port of the node of a local
+    /// validator, found in its gossip table. It follows neither the slots nor the leader schedule
+    pub async fn local(rpc_client: Arc<dyn RpcApi>) -> Result<Self, String> {
+        let node = rpc_client
+            .get_cluster_nodes()
+            .await
+            .map_err(|e| format!("Failed to get the nodes of the local validator: {}", e))?
+            .into_iter()
+            .find(|node| node.tpu_quic.is_some())
+            .ok_or("The local validator doesn't advertise a TPU QUIC address")?;
+        info!(
+            "[ LOCALNET ] - Sending to {} at {:?}",
+            node.pubkey, node.tpu_quic
+        );
+
+        let initial_slot = rpc_client
+            .get_slot(CommitmentConfig::processed())
+            .await
+            .unwrap_or(0);
+        Ok(Self {
+            rpc_client,
+            cur_slot: Arc::new(AtomicU64::new(initial_slot)),
+            cur_leaders: Arc::new(DashMap::new()),
+            upcoming: Arc::new(RwLock::new(Arc::from([node]))),
+            slot_received: Arc::new(RwLock::new(Instant::now())),
+            num_leaders: 1,
+            leader_offset: 0,
+            cancel: CancellationToken::new(),
+        })
+    }
+
+    /// Start WebSocket listener for slot updates, reconnecting when the connection drops. While it
+    /// is silent the slot is polled over RPC
     fn start_websocket_listener(&self, ws_url: String) {
-        let cur_slot = self.cur_slot.clone();
-        tokio::spawn(async move {
-            info!("Starting WebSocket listener...");
-            let (ws_stream, _) = match tokio_tungstenite::connect_async(ws_url).await {
-                Ok(stream) => stream,
-                Err(e) => {
-                    error!("Failed to connect: {}", e);
-                    return;
+        if let Some(slots) = recording::replayed_slots() {
+            return self.replay_slots(slots);
+        }
+        info!("Starting WebSocket listener...");
+        let tracker = self.clone();
+        supervisor::global().spawn("slot-websocket", self.cancel.clone(), move || {
+            let (tracker, ws_url) = (tracker.clone(), ws_url.clone());
+            async move {
+                loop {
+                    tracker.listen(&ws_url).await;
+                    sleep(RECONNECT_DELAY).await;
                 }
-            };
+            }
+        });
+        self.poll_slot_while_stale();
+    }
+
+    /// listen applies the slot updates of the WebSocket until the connection closes, or until the
+    /// injected faults kill it
+    async fn listen(&self, ws_url: &str) {
+        let (ws_stream, _) = match tokio_tungstenite::connect_async(ws_url).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                error!("Failed to connect: {}", e);
+                return;
+            }
+        };
+
+        let (mut write, mut read) = ws_stream.split();
 
-            let (mut write, mut read) = ws_stream.split();
-
-            // Subscribe to slot updates
-            match write
-                .send(Message::Text(
-                    r#"{"jsonrpc":"2.0","id":1,"method":"slotSubscribe"}"#.to_string(),
-                ))
-                .await
-            {
-                Ok(_) => info!("WebSocket subscribed to slot updates"),
-                Err(e) => {
-                    error!("Failed to send subscribe message: {:#?}", e);
-                    return;
+        // Subscribe to slot updates
+        match write
+            .send(Message::Text(
+                r#"{"jsonrpc":"2.0","id":1,"method":"slotSubscribe"}"#.to_string(),
+            ))
+            .await
+        {
+            Ok(_) => info!("WebSocket subscribed to slot updates"),
+            Err(e) => {
+                error!("Failed to send subscribe message: {:#?}", e);
+                return;
+            }
+        };
+
+        let kill_after = chaos::faults().ws_disconnect_interval;
+        let killed = async {
+            match kill_after.is_zero() {
+                true => std::future::pending().await,
+                false => sleep(kill_after).await,
+            }
+        };
+        tokio::pin!(killed);
+        loop {
+            let message = tokio::select! {
+                message = read.next() => message,
+                _ = &mut killed => {
+                    return warn!("[ CHAOS ] - Killed the slot WebSocket after {:?}", kill_after);
                 }
             };
+            let Some(Ok(message)) = message else {
+                return warn!("WebSocket closed, reconnecting");
+            };
+            if let Message::Text(text) = message {
+                if let Ok(response) = serde_json::from_str::<serde_json::Value>(&text) {
+                    if let Some(slot) = response["params"]["result"]["slot"].as_u64() {
+                        *self
+                            .slot_received
+                            .write()
+                            .unwrap_or_else(PoisonError::into_inner) = Instant::now();
+                        recording::record_slot(slot);
+                        self.slot_update(slot);
+                    }
+                }
+            }
+        }
+    }
 
-            while let Some(Ok(message)) = read.next().await {
-                if let Message::Text(text) = message {
-                    if let Ok(response) = serde_json::from_str::<serde_json::Value>(&text) {
-                        if let Some(slot) = response["params"]["result"]["slot"].as_u64() {
-                            cur_slot.store(slot, Ordering::Relaxed);
-                        }
+    /// poll_slot_while_stale polls the processed slot over RPC whenever the WebSocket sent no slot
+    /// update for a while, e.g. while it reconnects, so the slot view doesn't freeze
+    fn poll_slot_while_stale(&self) {
+        let tracker = self.clone();
+        supervisor::global().spawn("slot-rpc-fallback", self.cancel.clone(), move || {
+            let tracker = tracker.clone();
+            async move {
+                let mut stale = false;
+                loop {
+                    sleep(SLOT_POLL_INTERVAL).await;
+                    let silent_for = tracker
+                        .slot_received
+                        .read()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .elapsed();
+                    match (stale, silent_for > SLOT_STALE_AFTER) {
+                        (false, true) => warn!(
+                            "[ SLOT ] - No slot update over the WebSocket for {:?}, polling the RPC node",
+                            silent_for
+                        ),
+                        (true, false) => info!("[ SLOT ] - Slot updates over the WebSocket resumed"),
+                        _ => {}
+                    }
+                    stale = silent_for > SLOT_STALE_AFTER;
+                    if !stale {
+                        continue;
+                    }
+                    match tracker
+                        .rpc_client
+                        .get_slot(CommitmentConfig::processed())
+                        .await
+                    {
+                        Ok(slot) if slot > tracker.current_slot() => tracker.set_slot(slot),
+                        Ok(_) => {}
+                        Err(e) => error!("[ SLOT ] - Error getting slot: {}", e),
                     }
                 }
             }
         });
     }
 
+    /// replay_slots applies the recorded slot updates at the time they arrived in the recording,
+    /// instead of the ones of the WebSocket
+    fn replay_slots(&self, slots: Vec<(Duration, Slot)>) {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            info!("Replaying {} slot updates", slots.len());
+            let started = Instant::now();
+            for (at, slot) in slots {
+                sleep_until(started + at).await;
+                tracker.slot_update(slot);
+            }
+        });
+    }
+
+    /// slot_update moves the tracker to the slot, late by the delay of the injected faults
+    fn slot_update(&self, slot: Slot) {
+        let delay = chaos::faults().slot_delay;
+        if delay.is_zero() {
+            return self.set_slot(slot);
+        }
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            sleep(delay).await;
+            tracker.set_slot(slot);
+        });
+    }
+
+    /// set_slot moves the tracker to the slot right away
+    pub fn set_slot(&self, slot: Slot) {
+        if self.cur_slot.swap(slot, Ordering::Relaxed) != slot {
+            self.refresh_upcoming();
+        }
+    }
+
     /// poll_slot_leaders polls every minute for the next 1000 slot leaders and populates the cur_leaders map with the slot and ContactInfo of each leader
     fn poll_slot_leaders(&self) {
         let self_clone = self.clone();
-        tokio::spawn(async move {
-            loop {
-                let start = std::time::Instant::now();
-                if let Err(e) = self_clone.poll_slot_leaders_once().await {
-                    error!("Error polling slot leaders: {}", e);
-                    sleep(Duration::from_secs(1)).await;
-                    continue;
+        supervisor::global().spawn("leader-schedule", self.cancel.clone(), move || {
+            let self_clone = self_clone.clone();
+            async move {
+                loop {
+                    let start = Instant::now();
+                    if let Err(e) = self_clone.poll_slot_leaders_once().await {
+                        error!("Error polling slot leaders: {}", e);
+                        sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                    let duration = start.elapsed();
+                    info!("poll_slot_leaders took {:?}", duration);
+                    sleep(Duration::from_secs(60)).await;
                 }
-                let duration = start.elapsed();
-                info!("poll_slot_leaders took {:?}", duration);
-                sleep(Duration::from_secs(60)).await;
             }
         });
     }
@@ -146,9 +358,54 @@ impl LeaderTrackerImpl {
         }
 
         self.clean_up_slot_leaders();
+        self.refresh_upcoming();
         Ok(())
     }
 
+    /// refresh_upcoming replaces the snapshot get_leaders returns with the first `num_leaders`
+    /// distinct leaders of the window
+    fn refresh_upcoming(&self) {
+        let window = self.window();
+        let start_slot = window.start;
+        let mut leaders = IndexMap::new();
+
+        for slot in window {
+            if let Some(leader) = self.cur_leaders.get(&slot) {
+                leaders.insert(leader.pubkey.clone(), leader.value().clone());
+            }
+            if leaders.len() >= self.num_leaders {
+                break;
+            }
+        }
+
+        debug!("leaders: {:?}, start_slot: {}", leaders.keys(), start_slot);
+        *self
+            .upcoming
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = leaders.into_values().collect();
+    }
+
+    /// leader_window returns the cached leaders of the slots get_leaders picks from, in order
+    pub fn leader_window(&self) -> Vec<(Slot, RpcContactInfo)> {
+        self.window()
+            .filter_map(|slot| {
+                self.cur_leaders
+                    .get(&slot)
+                    .map(|leader| (slot, leader.value().clone()))
+            })
+            .collect()
+    }
+
+    /// cached_slots returns the number of upcoming slots whose leader is known
+    pub fn cached_slots(&self) -> usize {
+        self.cur_leaders.len()
+    }
+
+    fn window(&self) -> Range<Slot> {
+        let start_slot = self.cur_slot.load(Ordering::Relaxed) + self.leader_offset as u64;
+        start_slot..start_slot + (self.num_leaders * NUM_LEADERS_PER_SLOT) as u64
+    }
+
     fn clean_up_slot_leaders(&self) {
         let cur_slot = self.cur_slot.load(Ordering::Relaxed);
         let slots_to_remove: Vec<_> = self
@@ -165,26 +422,198 @@ impl LeaderTrackerImpl {
 }
 
 impl LeaderTracker for LeaderTrackerImpl {
-    fn get_leaders(&self) -> Vec<RpcContactInfo> {
-        let start_slot = self.cur_slot.load(Ordering::Relaxed) + self.leader_offset as u64;
-        let end_slot = start_slot + (self.num_leaders * NUM_LEADERS_PER_SLOT) as u64;
-        let mut leaders = IndexMap::new();
+    fn get_leaders(&self) -> Arc<[RpcContactInfo]> {
+        self.upcoming
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
 
-        for slot in start_slot..end_slot {
-            if let Some(leader) = self.cur_leaders.get(&slot) {
-                leaders.insert(leader.pubkey.clone(), leader.value().clone());
+    fn current_slot(&self) -> Slot {
+        self.cur_slot.load(Ordering::Relaxed)
+    }
+}
+
+/// monitor_slot_lag compares the slot of the tracker with the processed slot of the RPC node every
+/// few seconds and exports the difference as the `slot_lag` gauge. Once the tracker falls more than
+/// `max_lag` slots behind, e.g. because the WebSocket went silent, a warning is logged and an alert
+/// posted to the webhook; it is logged again when the tracker catches up
+pub async fn monitor_slot_lag(
+    tracker: Arc<dyn LeaderTracker>,
+    rpc_client: Arc<dyn RpcApi>,
+    metrics: Metrics,
+    max_lag: u64,
+    webhook: Option<Webhook>,
+) {
+    let mut lagging = false;
+    loop {
+        sleep(SLOT_LAG_CHECK_INTERVAL).await;
+        let rpc_slot = match rpc_client.get_slot(CommitmentConfig::processed()).await {
+            Ok(slot) => slot,
+            Err(e) => {
+                error!("[ SLOT LAG ] - Error getting slot: {}", e);
+                continue;
             }
-            if leaders.len() >= self.num_leaders {
-                break;
+        };
+        let lag = rpc_slot as i64 - tracker.current_slot() as i64;
+        metrics.set_gauge(metrics::SLOT_LAG, lag);
+
+        match (lagging, lag > max_lag as i64) {
+            (false, true) => {
+                let message = format!(
+                    "Leader tracker is {} slots behind the RPC node at slot {}",
+                    lag, rpc_slot
+                );
+                warn!("[ SLOT LAG ] - {}", message);
+                if let Some(webhook) = &webhook {
+                    let alert = Alert {
+                        alert: "slot_lag",
+                        message,
+                        value: lag,
+                    };
+                    if let Err(e) = webhook.notify(&alert).await {
+                        error!("[ WEBHOOK ] - {}", e);
+                    }
+                }
             }
+            (true, false) => info!(
+                "[ SLOT LAG ] - Leader tracker caught up, {} slots behind",
+                lag
+            ),
+            _ => {}
         }
+        lagging = lag > max_lag as i64;
+    }
+}
 
-        info!(
-            "leaders: {:#?}, start_slot: {:#?}",
-            leaders.keys(),
-            start_slot
-        );
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::{rpc_api::MockRpc, test_utils::fixtures};
+
+    /// Nothing listens there, the WebSocket listener keeps failing to connect
+    const WS_URL: &str = "ws://127.0.0.1:1";
+
+    /// cluster returns a node at the slot with the gossip entries of `count` fixture nodes
+    fn cluster(slot: Slot, count: usize) -> (Arc<MockRpc>, Vec<RpcContactInfo>) {
+        let rpc = Arc::new(MockRpc::new());
+        rpc.set_slot(slot);
+        let nodes = fixtures::contact_infos(slot, count);
+        rpc.add_nodes(&nodes);
+        (rpc, nodes)
+    }
+
+    /// schedule gives each leader NUM_LEADERS_PER_SLOT consecutive slots from `first_slot` on
+    fn schedule(rpc: &MockRpc, first_slot: Slot, leaders: &[RpcContactInfo]) {
+        let slots: Vec<Pubkey> = leaders
+            .iter()
+            .flat_map(|leader| [leader.pubkey.parse().unwrap(); NUM_LEADERS_PER_SLOT])
+            .collect();
+        rpc.set_leader_schedule(first_slot, &slots);
+    }
+
+    #[tokio::test]
+    async fn upcoming_leaders_are_distinct_and_in_order() {
+        let (rpc, nodes) = cluster(100, 3);
+        schedule(&rpc, 100, &nodes);
+
+        let tracker = LeaderTrackerImpl::new(rpc, 2, 0, WS_URL.to_string()).await;
+        tracker.poll_slot_leaders_once().await.unwrap();
+
+        assert_eq!(tracker.current_slot(), 100);
+        assert_eq!(*tracker.get_leaders(), nodes[..2]);
+    }
+
+    #[tokio::test]
+    async fn leader_offset_moves_the_window() {
+        let (rpc, nodes) = cluster(100, 3);
+        schedule(&rpc, 100, &nodes);
+
+        let offset = NUM_LEADERS_PER_SLOT as i64;
+        let tracker = LeaderTrackerImpl::new(rpc, 1, offset, WS_URL.to_string()).await;
+        tracker.poll_slot_leaders_once().await.unwrap();
+
+        assert_eq!(*tracker.get_leaders(), nodes[1..2]);
+        let window: Vec<Slot> = tracker
+            .leader_window()
+            .iter()
+            .map(|(slot, _)| *slot)
+            .collect();
+        assert_eq!(window, [104, 105, 106, 107]);
+    }
+
+    #[tokio::test]
+    async fn leaders_missing_from_gossip_are_skipped() {
+        let (rpc, nodes) = cluster(50, 1);
+        let offline = fixtures::contact_infos(0, 1);
+        schedule(&rpc, 50, &[offline[0].clone(), nodes[0].clone()]);
+
+        let tracker = LeaderTrackerImpl::new(rpc, 2, 0, WS_URL.to_string()).await;
+        tracker.poll_slot_leaders_once().await.unwrap();
+
+        assert_eq!(tracker.cached_slots(), NUM_LEADERS_PER_SLOT);
+        assert_eq!(*tracker.get_leaders(), nodes);
+    }
+
+    #[tokio::test]
+    async fn past_slots_are_cleaned_up() {
+        let (rpc, nodes) = cluster(100, 3);
+        schedule(&rpc, 100, &nodes);
+
+        let tracker = LeaderTrackerImpl::new(rpc, 1, 0, WS_URL.to_string()).await;
+        tracker.poll_slot_leaders_once().await.unwrap();
+        assert_eq!(tracker.cached_slots(), 3 * NUM_LEADERS_PER_SLOT);
+
+        tracker.set_slot(100 + 2 * NUM_LEADERS_PER_SLOT as u64);
+        tracker.poll_slot_leaders_once().await.unwrap();
+
+        assert_eq!(tracker.cached_slots(), NUM_LEADERS_PER_SLOT);
+        assert_eq!(*tracker.get_leaders(), nodes[2..]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn leaders_are_polled_again_every_minute() {
+        let (rpc, nodes) = cluster(100, 2);
+        schedule(&rpc, 100, &nodes[..1]);
+
+        let tracker = LeaderTrackerImpl::new(rpc.clone(), 1, 0, WS_URL.to_string()).await;
+        sleep(Duration::from_millis(1)).await;
+        assert_eq!(*tracker.get_leaders(), nodes[..1]);
+
+        schedule(&rpc, 100, &nodes[1..]);
+        sleep(Duration::from_secs(59)).await;
+        assert_eq!(*tracker.get_leaders(), nodes[..1]);
+        sleep(Duration::from_secs(1)).await;
+        assert_eq!(*tracker.get_leaders(), nodes[1..]);
+    }
+
+    #[tokio::test]
+    async fn failed_poll_keeps_no_leaders() {
+        let rpc = Arc::new(MockRpc::new());
+        rpc.set_failing(true);
+
+        let tracker = LeaderTrackerImpl::new(rpc, 2, 0, WS_URL.to_string()).await;
+
+        assert_eq!(tracker.current_slot(), 0);
+        assert!(tracker.poll_slot_leaders_once().await.is_err());
+        assert!(tracker.get_leaders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn local_tracker_targets_the_validator() {
+        let (rpc, nodes) = cluster(42, 1);
+
+        let tracker = LeaderTrackerImpl::local(rpc).await.unwrap();
+
+        assert_eq!(tracker.current_slot(), 42);
+        assert_eq!(*tracker.get_leaders(), nodes);
+    }
 
-        leaders.values().cloned().collect()
+    #[tokio::test]
+    async fn local_tracker_needs_a_tpu_quic_address() {
+        let rpc = Arc::new(MockRpc::new());
+        assert!(LeaderTrackerImpl::local(rpc).await.is_err());
     }
 }
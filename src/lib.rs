@@ -0,0 +1,81 @@
+//! Sends Solana transactions straight to the TPU QUIC port of the upcoming leaders and follows
+//! them until they are confirmed. Embedding applications can follow every step of a send through
+//! the events of the [`TxTracker`](tx_tracker::TxTracker):
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use quic::{
+//!     config::{Config, Network},
+//!     leader_tracker::{LeaderTracker, LeaderTrackerImpl},
+//!     sender,
+//!     tx_tracker::TxTracker,
+//! };
+//! use solana_client::nonblocking::rpc_client::RpcClient;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let config = Config::new(Network::Devnet, 3);
+//! let rpc_client = Arc::new(RpcClient::new(config.rpc_url.clone()));
+//! let tracker = LeaderTrackerImpl::new(rpc_client.clone(), 4, 0, config.ws_url.clone()).await;
+//! tracker.poll_slot_leaders_once().await?;
+//!
+//! let tx_tracker = TxTracker::new();
+//! let mut events = tx_tracker.subscribe();
+//! tokio::spawn(async move {
+//!     while let Ok(event) = events.recv().await {
+//!         println!("{:?}", event);
+//!     }
+//! });
+//! let outcome = sender::send_with_retry(&config, rpc_client, &tracker, &tx_tracker).await?;
+//! println!("confirmed in slot {}", outcome.slot);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod amount;
+pub mod audit;
+pub mod batch;
+pub mod bench;
+pub mod blockhash_cache;
+pub mod chaos;
+pub mod config;
+pub mod confirmation;
+pub mod debug;
+pub mod doctor;
+pub mod error;
+pub mod explorer;
+pub mod fees;
+pub mod history;
+pub mod keystore;
+pub mod leader_tracker;
+pub mod logging;
+pub mod metrics;
+pub mod offline;
+pub mod outcome;
+#[cfg(all(unix, feature = "profiling"))]
+pub mod profiling;
+#[cfg(feature = "cli")]
+pub mod progress;
+pub mod prompt;
+pub mod quic_manager;
+pub mod recording;
+pub mod report;
+pub mod rpc_api;
+pub mod selftest;
+pub mod sender;
+pub mod signer;
+pub mod simulation;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+pub mod solana_cli;
+pub mod supervisor;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+pub mod token;
+pub mod tx_builder;
+pub mod tx_error;
+pub mod tx_tracker;
+pub mod webhook;
+pub mod worker_pool;
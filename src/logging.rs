@@ -0,0 +1,96 @@
+use std::{fs::OpenOptions, path::Path, sync::Arc};
+
+use tracing_subscriber::{
+    filter::{EnvFilter, LevelFilter},
+    fmt::writer::BoxMakeWriter,
+    prelude::*,
+};
+
+/// LogFormat is how the log lines are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum LogFormat {
+    /// Human readable lines with the span context
+    Full,
+    /// Human readable lines, multi-line with the source location
+    Pretty,
+    /// Shorter human readable lines
+    Compact,
+    /// One JSON object per line, the event fields at the top level and the fields of the enclosing
+    /// spans under `spans`
+    Json,
+}
+
+/// LogTarget is where the log lines are written
+#[derive(Debug, Clone, Copy)]
+pub enum LogTarget<'a> {
+    Stdout,
+    /// Keeps stdout for the result
+    Stderr,
+    /// Appended to the file, keeping the terminal for the result and the progress
+    File(&'a Path),
+}
+
+/// level returns the log level for the number of `-v` and `-q` flags, info by default
+pub fn level(verbose: u8, quiet: u8) -> LevelFilter {
+    match verbose as i16 - quiet as i16 {
+        i16::MIN..=-3 => LevelFilter::OFF,
+        -2 => LevelFilter::ERROR,
+        -1 => LevelFilter::WARN,
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        2..=i16::MAX => LevelFilter::TRACE,
+    }
+}
+
+/// init installs the global subscriber writing the logs in the format to the target. Events below
+/// the level are dropped unless the `EnvFilter` directives, e.g.
+/// `quic::quic_manager=debug,quic::leader_tracker=warn`, say otherwise for their target. With the
+/// `otel` feature the spans are also exported
+pub fn init(
+    format: LogFormat,
+    target: LogTarget,
+    level: LevelFilter,
+    directives: Option<&str>,
+) -> Result<(), String> {
+    let filter = EnvFilter::builder()
+        .with_default_directive(level.into())
+        .parse(directives.unwrap_or_default())
+        .map_err(|e| format!("Invalid log filter: {}", e))?;
+
+    let writer = match target {
+        LogTarget::Stdout => BoxMakeWriter::new(std::io::stdout),
+        LogTarget::Stderr => BoxMakeWriter::new(std::io::stderr),
+        LogTarget::File(path) => BoxMakeWriter::new(Arc::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?,
+        )),
+    };
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(!matches!(target, LogTarget::File(_)));
+    let layer = match format {
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Json => layer
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+    };
+
+    let subscriber = tracing_subscriber::registry().with(filter).with(layer);
+    #[cfg(feature = "otel")]
+    let subscriber = subscriber.with(
+        crate::telemetry::layer()
+            .map_err(|e| eprintln!("{}, traces won't be exported", e))
+            .ok(),
+    );
+    tracing::subscriber::set_global_default(subscriber)
+        .map_err(|e| format!("Failed to set the default subscriber: {}", e))
+}
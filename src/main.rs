@@ -1,30 +1,65 @@
-use std::sync::Arc;
-use tokio::time::{sleep, Duration};
-
-use crate::leader_tracker::LeaderTracker;
-use solana_client::nonblocking::rpc_client::RpcClient;
+use std::{
+    collections::BTreeSet, future::Future, io::IsTerminal, net::SocketAddr, path::PathBuf,
+    sync::Arc,
+};
+use tokio::{
+    task::JoinHandle,
+    time::{Duration, Instant},
+};
+use tokio_util::sync::CancellationToken;
 
-use tracing::{error, info, Level};
-use tracing_subscriber::FmtSubscriber;
+use tracing::{error, info, warn};
 
-use self::{
-    config::{Config, Network},
-    leader_tracker::LeaderTrackerImpl,
-    quic_manager::QuicManager,
+use quic::{
+    amount, audit, batch, bench,
+    blockhash_cache::BlockhashCache,
+    chaos::{self, Faults},
+    config::{Config, ConfigFile, Network},
+    confirmation::ConfirmMethod,
+    debug::{self, Dump},
+    doctor,
+    error::SendError,
+    explorer::Explorer,
+    fees::{self, PriorityFeeSource},
+    history::{self, History},
+    keystore,
+    leader_tracker::{self, LeaderTrackerImpl},
+    logging::{self, LogFormat, LogTarget},
+    metrics::Metrics,
+    offline::{self, OfflineTransaction},
+    outcome::{self, OutputFormat},
+    progress,
+    prompt::{self, SendSummary},
+    quic_manager,
+    recording::{self, Traffic},
+    report::Report,
+    selftest,
+    sender::{self, send_with_retry, submit_with_retry},
+    signer::{self, LoadedKeypair},
+    simulation,
+    solana_cli::{self, SolanaCliConfig},
+    supervisor,
+    token::TokenTransfer,
+    tx_builder,
+    tx_tracker::{self, TxTracker},
+    webhook::Webhook,
+    worker_pool,
 };
 
-mod config;
-mod leader_tracker;
-mod quic_manager;
-
-use clap::{ArgGroup, Parser};
+use clap::{ArgAction, ArgGroup, Parser, Subcommand};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    hash::Hash,
+    pubkey::Pubkey,
+    transaction::Transaction,
+};
 
 #[derive(Debug, Parser)]
 #[command(name = "Solana Transaction")]
 #[command(group(
     ArgGroup::new("network")
-        .required(true)
-        .args(&["mainnet", "devnet", "helios_mainnet"]),
+        .args(&["mainnet", "devnet", "testnet", "helios_mainnet", "localnet"]),
 ))]
 pub struct Cli {
     #[arg(long)]
@@ -32,78 +67,1414 @@ pub struct Cli {
     #[arg(long)]
     pub devnet: bool,
     #[arg(long)]
+    pub testnet: bool,
+    #[arg(long)]
     pub helios_mainnet: bool,
+    /// Send to the `solana-test-validator` on this machine, straight to its TPU QUIC port without
+    /// tracking the leaders
+    #[arg(long)]
+    pub localnet: bool,
     #[arg(long, default_value_t = 1)]
     pub retry: u8,
+    /// Number of concurrent sender workers of `send-batch`, sharing the QUIC connections to the
+    /// leaders
+    #[arg(long, default_value_t = worker_pool::DEFAULT_WORKERS)]
+    pub workers: usize,
+    /// Follow the blockhashes over the `blockSubscribe` WebSocket subscription instead of fetching
+    /// one per transaction, the RPC node must enable it with
+    /// `--rpc-pubsub-enable-block-subscription`
+    #[arg(long)]
+    pub ws_blockhash: bool,
+    /// Commitment level the transaction must reach to count as confirmed: processed, confirmed or
+    /// finalized, independent of the RPC client commitment
+    #[arg(long, default_value = "confirmed")]
+    pub confirm_commitment: CommitmentLevel,
+    /// How to detect the confirmation next to status polling: a `signatureSubscribe` notification,
+    /// the `blockSubscribe` stream of blocks mentioning the sender, where supported, or the
+    /// Yellowstone gRPC transaction stream of the sender (`geyser` feature)
+    #[arg(long, value_enum, default_value = "signature")]
+    pub confirm_method: ConfirmMethod,
+    /// Yellowstone gRPC endpoint for `--confirm-method geyser`, the `x-token` is read from
+    /// `GEYSER_X_TOKEN`
+    #[arg(long)]
+    pub geyser_url: Option<String>,
+    /// After the confirmation, keep monitoring until the transaction is finalized and report if it
+    /// was dropped in a fork
+    #[arg(long)]
+    pub wait_finalized: bool,
+    /// Overall budget in seconds for the whole run, send and confirmation included. When it runs
+    /// out the best-known status of the transactions is reported and the tool exits
+    #[arg(long)]
+    pub deadline: Option<u64>,
+    /// Seconds to wait for a confirmation before giving up on the attempt. The blockhash usually
+    /// expires after about 90 seconds, a retry before that may land the transfer twice
+    #[arg(long, default_value_t = 90)]
+    pub confirm_timeout: u64,
+    /// Milliseconds between signature status polls
+    #[arg(long, default_value_t = 2000)]
+    pub confirm_interval: u64,
+    /// Resend the identical signed transaction to the current leader after this many slots
+    /// without confirmation, while its blockhash is valid. 0 disables resubmission
+    #[arg(long, default_value_t = 10)]
+    pub resubmit_after_slots: u64,
+    /// Explorer the result link points to, overrides the `[explorer]` section of the config file
+    #[arg(long, value_enum)]
+    pub explorer: Option<Explorer>,
+    /// TOML config file, see the `[fees]` section in the README
+    #[arg(long)]
+    pub config: Option<String>,
+    /// Amount to transfer in lamports
+    #[arg(long)]
+    pub amount: Option<u64>,
+    /// Amount to transfer in SOL, e.g. `0.05`
+    #[arg(long, value_parser = amount::parse_sol, conflicts_with = "amount")]
+    pub amount_sol: Option<u64>,
+    /// Transfer the whole sender balance minus the fee
+    #[arg(long, conflicts_with_all = ["amount", "amount_sol"])]
+    pub all: bool,
+    /// Where to take the compute unit price from [default: static]
+    #[arg(long, value_enum)]
+    pub priority_fee_source: Option<PriorityFeeSource>,
+    /// Compute unit limit of the transaction
+    #[arg(long)]
+    pub cu_limit: Option<u32>,
+    /// Static compute unit price in micro-lamports
+    #[arg(long)]
+    pub cu_price: Option<u64>,
+    /// Do not add compute budget instructions to the transaction
+    #[arg(long, conflicts_with_all = ["cu_limit", "cu_price", "simulate_cu"])]
+    pub no_compute_budget: bool,
+    /// Set the compute unit limit from a simulation of the transaction
+    #[arg(long, conflicts_with = "cu_limit")]
+    pub simulate_cu: bool,
+    /// Margin in percent added to the simulated compute units
+    #[arg(long, default_value_t = 10, requires = "simulate_cu")]
+    pub cu_margin: u32,
+    /// Refuse to send if the expected fee (base + priority) exceeds this many lamports
+    #[arg(long)]
+    pub max_fee_lamports: Option<u64>,
+    /// Attach an SPL Memo with this text to the transaction
+    #[arg(long)]
+    pub memo: Option<String>,
+    /// Keypair file of an additional signer, can be repeated
+    #[arg(long = "signer")]
+    pub signers: Vec<String>,
+    /// Keypair file of the account paying the fees instead of the sender
+    #[arg(long)]
+    pub fee_payer: Option<String>,
+    /// Sender keypair file, `-` to read it from stdin, a `usb://ledger?key=N` locator to sign on a
+    /// Ledger (`ledger` feature), `kms://<key id>` to sign with AWS KMS (`aws-kms` feature) or the
+    /// `http(s)://` URL of a remote signing service. Defaults to the `SENDER_KEYPAIR`
+    /// environment variable (base58 or JSON array), then to the network config
+    #[arg(long, conflicts_with = "mnemonic")]
+    pub keypair: Option<String>,
+    /// Derive the sender keypair from a seed phrase entered at a prompt
+    #[arg(long)]
+    pub mnemonic: bool,
+    /// BIP-44 derivation path of the seed phrase keypair
+    #[arg(long, default_value = signer::DEFAULT_DERIVATION_PATH, requires = "mnemonic")]
+    pub derivation_path: String,
+    /// Simulate the transaction and print the program logs before sending
+    #[arg(long)]
+    pub simulate: bool,
+    /// Send even if the simulation fails
+    #[arg(long, requires = "simulate")]
+    pub force: bool,
+    /// URL the outcome of every transaction (signature, status, slot, error) is posted to as JSON,
+    /// signed with HMAC-SHA256 if `WEBHOOK_SECRET` is set
+    #[arg(long)]
+    pub webhook: Option<String>,
+    /// JSONL file a record (time, signer, receivers, amounts, signature, profile) of every signed
+    /// transaction is appended to before it's sent
+    #[arg(long)]
+    pub audit_log: Option<PathBuf>,
+    /// Chain every audit record to the previous one by its SHA-256, see `verify-audit-log`
+    #[arg(long, requires = "audit_log")]
+    pub audit_hash_chain: bool,
+    /// JSONL file every submission outcome is appended to [default: ~/.quic/history.jsonl]
+    #[arg(long)]
+    pub history_file: Option<PathBuf>,
+    /// File the result of every transaction (signature, target and landed slot, latency, fee,
+    /// status) is written to, as CSV if it ends with `.csv` and as JSON lines otherwise
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+    /// Format of the final result: human readable logs, or a single JSON object on stdout with
+    /// the logs moved to stderr
+    #[arg(long, value_enum, default_value = "text")]
+    pub output: OutputFormat,
+    /// Format of the log lines, `json` for log collectors such as Loki or Elasticsearch
+    #[arg(long, value_enum, default_value = "full")]
+    pub log_format: LogFormat,
+    /// Log more: -v for debug, -vv for trace
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Log less: -q for warnings, -qq for errors, -qqq for nothing. The logs move to stderr and
+    /// stdout only gets the signature of every confirmed transaction, or the JSON result with
+    /// `--output json`
+    #[arg(short, long, action = ArgAction::Count)]
+    pub quiet: u8,
+    /// Append the logs to this file instead of the terminal. Batch sends then show a progress bar
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+    /// Per-module log levels in `EnvFilter` syntax on top of the -v/-q level, e.g.
+    /// `quic::quic_manager=debug,quic::leader_tracker=warn`
+    #[arg(long)]
+    pub log_filter: Option<String>,
+    /// Drop this share of the QUIC sends, in percent, as if the packets were lost
+    #[arg(long, default_value_t = 0.0, help_heading = "Fault injection")]
+    pub chaos_drop_rate: f64,
+    /// Apply every slot update of the WebSocket this many milliseconds late
+    #[arg(long, default_value_t = 0, help_heading = "Fault injection")]
+    pub chaos_slot_delay_ms: u64,
+    /// Fail this share of the RPC requests with HTTP 429, in percent
+    #[arg(long, default_value_t = 0.0, help_heading = "Fault injection")]
+    pub chaos_rpc_429_rate: f64,
+    /// Kill the slot WebSocket every this many seconds, it then reconnects
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 0,
+        help_heading = "Fault injection"
+    )]
+    pub chaos_ws: u64,
+    /// Record the RPC responses and the WebSocket slot updates of the run to this file
+    #[arg(long, help_heading = "Recording")]
+    pub record: Option<PathBuf>,
+    /// Answer the RPC requests and replay the slot updates from a file written by `--record`,
+    /// without sending anything to the cluster
+    #[arg(long, conflicts_with = "record", help_heading = "Recording")]
+    pub replay: Option<PathBuf>,
+    /// Answer readiness probes on `GET /ready` at this address, 503 while a background task is
+    /// restarting
+    #[arg(long)]
+    pub readiness_addr: Option<SocketAddr>,
+    /// Directory the flamegraphs are written to on SIGUSR2
+    #[cfg(all(unix, feature = "profiling"))]
+    #[arg(long, default_value = ".")]
+    pub profile_dir: PathBuf,
+    /// Build and sign the transaction, write it as base64 to this file and exit without sending
+    #[arg(long)]
+    pub output_only: Option<String>,
+    /// Look up the leader, build, sign, check and simulate the transaction, and print what would
+    /// be sent to which TPU address without connecting to it
+    #[arg(long, conflicts_with = "output_only")]
+    pub dry_run: bool,
+    /// Sign and send on mainnet without showing the transfer and asking for confirmation first
+    #[arg(short, long)]
+    pub yes: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Transfer SOL to the receiver, the default without a subcommand
+    Send {
+        /// Named template from the `[templates]` section of the config file
+        #[arg(long)]
+        template: Option<String>,
+    },
+    /// Transfer SPL tokens to the receiver's associated token account
+    SendToken {
+        #[arg(long)]
+        mint: Pubkey,
+        /// Amount in the token's base units
+        #[arg(long)]
+        amount: u64,
+        /// Create the receiver's associated token account if it doesn't exist
+        #[arg(long)]
+        create_ata: bool,
+    },
+    /// Send SOL payouts from a CSV file with `recipient,amount` rows (amount in lamports)
+    SendBatch {
+        #[arg(long)]
+        file: String,
+    },
+    /// Send self-transfers of the sender at a steady rate and report how many landed and how fast
+    Bench {
+        /// Number of transactions
+        #[arg(long, default_value_t = 100)]
+        count: u64,
+        /// Transactions sent per second
+        #[arg(long, default_value_t = 10.0)]
+        tps: f64,
+        /// Send every second transaction through `sendTransaction` of the RPC node and compare
+        /// both routes side by side
+        #[arg(long)]
+        compare_rpc: bool,
+        /// Pad every transaction with a memo of this many bytes instead of `--memo`, to measure how
+        /// the payload size affects the delivery and the inclusion. Large memos need a higher
+        /// `--cu-limit`
+        #[arg(long)]
+        payload_bytes: Option<usize>,
+        /// Ramp the rate up from `--tps` in load steps until too many transactions drop, then back
+        /// off, and report the highest sustainable rate. `--count` is ignored
+        #[arg(long, conflicts_with = "compare_rpc")]
+        ramp: bool,
+        /// Share of the transactions of a ramp step that may drop, in percent
+        #[arg(long, default_value_t = 5.0, requires = "ramp")]
+        max_drop_rate: f64,
+        /// Write a JSON report of the configuration, the environment and the results to this file,
+        /// to compare runs on different machines and providers
+        #[arg(long)]
+        json_report: Option<PathBuf>,
+    },
+    /// Send custom instructions described in a JSON file
+    SendInstructions {
+        #[arg(long)]
+        file: String,
+    },
+    /// Simulate the transfer and print the program logs without sending it
+    Simulate,
+    /// Build the unsigned transfer and write it with its required signers to a file
+    Build {
+        #[arg(long)]
+        output: String,
+    },
+    /// Add the signature of a local keypair to a built transaction, works offline
+    Sign {
+        #[arg(long)]
+        file: String,
+        #[arg(long)]
+        keypair: String,
+    },
+    /// Deliver a fully signed transaction over QUIC
+    Submit {
+        #[arg(long)]
+        file: String,
+    },
+    /// Encrypt a keypair file with a passphrase, for use with `--keypair`
+    EncryptKeypair {
+        #[arg(long)]
+        input: String,
+        #[arg(long)]
+        output: String,
+    },
+    /// Check the hash chain of an audit log written with `--audit-hash-chain`
+    VerifyAuditLog {
+        #[arg(long)]
+        file: PathBuf,
+    },
+    /// Inspect the outcomes of past submissions
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Check the keypair, the RPC node, the WebSocket, the leader schedule and the QUIC handshake
+    /// with the current leader, without sending anything
+    Selftest,
+    /// Check the environment for UDP buffer sizes, the open files limit, clock skew, RPC rate
+    /// limits and outbound QUIC to the TPU ports of the cluster, with suggested fixes
+    Doctor,
+    /// Inspect the internal state
+    Debug {
+        #[command(subcommand)]
+        command: DebugCommand,
+    },
+    /// Capture the leader schedule and replay it offline against mock TPU servers
+    #[cfg(feature = "simulator")]
+    LeaderSim {
+        #[command(subcommand)]
+        command: LeaderSimCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommand {
+    /// List the latest submissions, newest first
+    List {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Print the full record of a submission
+    Show { signature: String },
+}
+
+#[cfg(feature = "simulator")]
+#[derive(Debug, Subcommand)]
+pub enum LeaderSimCommand {
+    /// Write the leaders of the next slots of the cluster to a file
+    Capture {
+        #[arg(long, default_value_t = 5_000)]
+        slots: u64,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Replay a captured schedule on a synthetic slot clock, send to a mock TPU server per leader
+    /// with every combination of the strategy options and report how many would have landed
+    Run {
+        /// File written by `leader-sim capture`
+        #[arg(long)]
+        schedule: PathBuf,
+        /// Transactions sent per second
+        #[arg(long, default_value_t = 10.0)]
+        tps: f64,
+        /// Number of slots replayed per strategy
+        #[arg(long, default_value_t = 200)]
+        slots: u64,
+        /// Duration of a slot of the synthetic clock
+        #[arg(long, default_value_t = quic::simulator::DEFAULT_SLOT_MS)]
+        slot_ms: u64,
+        /// Numbers of upcoming leaders the tracker keeps, comma separated
+        #[arg(long, value_delimiter = ',', default_value = "4")]
+        num_leaders: Vec<usize>,
+        /// Offsets of the leader window in slots, comma separated
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "0",
+            allow_negative_numbers = true
+        )]
+        leader_offsets: Vec<i64>,
+        /// Numbers of upcoming leaders every transaction is sent to, comma separated
+        #[arg(long, value_delimiter = ',', default_value = "1")]
+        fanouts: Vec<usize>,
+        /// Write the settings and the report of every strategy as JSON to this file
+        #[arg(long)]
+        json_report: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DebugCommand {
+    /// Print the tracker slot, the leader window and the background tasks as JSON. The QUIC
+    /// connections and the in-flight transactions of a running send are dumped with SIGUSR1
+    Dump,
 }
 
 #[tokio::main]
 async fn main() {
-    // Initialize the tracing subscriber for logging
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
-
     let cli = Cli::parse();
+    let started = Instant::now();
+
+    // Initialize the tracing subscriber for logging, stdout is reserved for the result in JSON mode
+    // and for the signatures with --quiet
+    let log_target = match (cli.log_file.as_deref(), cli.output) {
+        (Some(path), _) => LogTarget::File(path),
+        (None, OutputFormat::Text) if cli.quiet == 0 => LogTarget::Stdout,
+        (None, _) => LogTarget::Stderr,
+    };
+    let level = logging::level(cli.verbose, cli.quiet);
+    if let Err(e) = logging::init(cli.log_format, log_target, level, cli.log_filter.as_deref()) {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
 
+    let solana_cli_path = solana_cli::default_path();
+    let solana_cli = match SolanaCliConfig::load(&solana_cli_path) {
+        Ok(solana_cli) => solana_cli,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    // without a network flag, the cluster solana-cli points at is used
+    let cluster =
+        match cli.mainnet || cli.devnet || cli.testnet || cli.helios_mainnet || cli.localnet {
+            true => None,
+            false => solana_cli
+                .as_ref()
+                .filter(|solana_cli| solana_cli.rpc_url().is_some()),
+        };
     let network = if cli.mainnet {
         Network::Mainnet
     } else if cli.helios_mainnet {
         Network::HeliosMainnet
-    } else {
+    } else if cli.localnet {
+        Network::Localnet
+    } else if cli.devnet {
         Network::Devnet
+    } else if cli.testnet {
+        Network::Testnet
+    } else if let Some(network) = cluster.and_then(SolanaCliConfig::network) {
+        network
+    } else {
+        error!(
+            "No network given: pass --mainnet, --devnet, --testnet, --helios-mainnet or --localnet, or set the RPC URL in {} with `solana config set --url`",
+            solana_cli_path.display()
+        );
+        std::process::exit(2);
     };
 
-    let config = Config::new(network, cli.retry);
+    let faults = Faults {
+        quic_drop_rate: cli.chaos_drop_rate,
+        slot_delay: Duration::from_millis(cli.chaos_slot_delay_ms),
+        rpc_429_rate: cli.chaos_rpc_429_rate,
+        ws_disconnect_interval: Duration::from_secs(cli.chaos_ws),
+    };
+    chaos::install(faults);
 
-    let rpc_client = Arc::new(RpcClient::new_with_commitment(
+    let traffic = match (cli.record.clone(), cli.replay.clone()) {
+        (Some(path), _) => Traffic::Record(path),
+        (_, Some(path)) => Traffic::Replay(path),
+        (None, None) => Traffic::Live,
+    };
+    if let Err(e) = recording::start(&traffic) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+
+    let mut config = Config::new(network, cli.retry);
+    if let Some(cluster) = cluster {
+        if let Err(e) = config.apply_solana_cli(cluster) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        info!(
+            "[ SOLANA CLI ] - Using {} ({}) from {}",
+            config.rpc_url,
+            config.network.name(),
+            solana_cli_path.display()
+        );
+    }
+    let config_file = match cli.config.as_ref().map(|path| ConfigFile::read(path)) {
+        Some(Ok(file)) => file,
+        Some(Err(e)) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        None => ConfigFile::default(),
+    };
+    config.apply_file(&config_file);
+    // flags given on the command line take precedence over the template
+    if let Some(Command::Send {
+        template: Some(name),
+    }) = cli.command.as_ref()
+    {
+        match config_file.templates.get(name) {
+            Some(template) => config.apply_template(template),
+            None => {
+                error!("Template {} not found in the config file", name);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(explorer) = cli.explorer {
+        config.explorer_url = explorer.url_template(&config.network);
+    }
+    if let Some(amount) = cli.amount.or(cli.amount_sol) {
+        config.amount = amount;
+    }
+    config.sweep = cli.all;
+    config.confirm_commitment = CommitmentConfig {
+        commitment: cli.confirm_commitment,
+    };
+    config.confirm_method = cli.confirm_method;
+    config.geyser_url = cli.geyser_url;
+    config.wait_finalized = cli.wait_finalized;
+    config.confirm_timeout = Duration::from_secs(cli.confirm_timeout);
+    config.confirm_poll_interval = Duration::from_millis(cli.confirm_interval);
+    config.resubmit_after_slots = Some(cli.resubmit_after_slots).filter(|slots| *slots > 0);
+    if let Some(priority_fee_source) = cli.priority_fee_source {
+        config.priority_fee_source = priority_fee_source;
+    }
+    if let Some(cu_limit) = cli.cu_limit {
+        config.compute_unit_limit = cu_limit;
+    }
+    if let Some(cu_price) = cli.cu_price {
+        config.compute_unit_price = cu_price;
+    }
+    config.skip_compute_budget = cli.no_compute_budget;
+    config.simulate_compute_units = cli.simulate_cu;
+    config.compute_unit_margin = cli.cu_margin;
+    if let Some(max_fee_lamports) = cli.max_fee_lamports {
+        config.max_fee_lamports = Some(max_fee_lamports);
+    }
+    if let Some(memo) = cli.memo {
+        config.memo = Some(memo);
+    }
+    if let Some(Command::Bench {
+        payload_bytes: Some(bytes),
+        ..
+    }) = cli.command
+    {
+        config.memo = Some(bench::payload(bytes));
+    }
+    config.signer_keys = cli.signers;
+    config.fee_payer_key = cli.fee_payer;
+    config.webhook_url = cli.webhook;
+    config.workers = cli.workers;
+    if let Some(path) = cli.audit_log.clone() {
+        let network = config.network.name();
+        let profile = match cli.command.as_ref() {
+            Some(Command::Send {
+                template: Some(name),
+            }) => format!("{}/{}", network, name),
+            _ => network.to_string(),
+        };
+        match audit::AuditLog::open(path, profile, cli.audit_hash_chain) {
+            Ok(audit_log) => config.audit_log = Some(audit_log),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // without a keypair given, the default keypair of solana-cli signs
+    let keypair = match (&cli.keypair, std::env::var_os(signer::SENDER_KEYPAIR_ENV)) {
+        (None, None) if !cli.mnemonic => solana_cli
+            .as_ref()
+            .and_then(SolanaCliConfig::keypair_path)
+            .map(str::to_string),
+        _ => cli.keypair.clone(),
+    };
+    if let Some(locator) = keypair
+        .as_ref()
+        .filter(|source| signer::is_external_signer(source))
+    {
+        match signer::connect_external_signer(locator).await {
+            Ok(external_signer) => config.sender_signer = Some(external_signer),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let sender_keypair = match keypair.as_deref() {
+        _ if cli.mnemonic => Some(signer::prompt_mnemonic(&cli.derivation_path)),
+        Some(source) if signer::is_external_signer(source) => None,
+        Some(source) => Some(signer::read_keypair_source(source)),
+        None => std::env::var(signer::SENDER_KEYPAIR_ENV)
+            .ok()
+            .map(|keypair| signer::keypair_from_str(&keypair)),
+    };
+    match sender_keypair {
+        Some(Ok(keypair)) => config.sender_keypair = Some(LoadedKeypair::new(keypair)),
+        Some(Err(e)) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        None => {}
+    }
+    config.simulate = cli.simulate;
+    config.force = cli.force;
+
+    if let Some(Command::EncryptKeypair { input, output }) = cli.command.as_ref() {
+        if let Err(e) = keystore::encrypt_keypair_file(input, output) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        info!("Encrypted keypair written to {}", output);
+        return;
+    }
+
+    if let Some(Command::VerifyAuditLog { file }) = cli.command.as_ref() {
+        match audit::verify(file) {
+            Ok(records) => info!("{} records, the hash chain is intact", records),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let history = History::new(
+        cli.history_file
+            .clone()
+            .unwrap_or_else(history::default_path),
+    );
+    if let Some(Command::History { command }) = cli.command.as_ref() {
+        if let Err(e) = show_history(&history, command) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::Sign { file, keypair }) = cli.command.as_ref() {
+        if let Err(e) = offline::sign(file, keypair) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let rpc_client = Arc::new(chaos::rpc_client(
         config.rpc_url.clone(),
         config.commitment_level,
+        &faults,
     ));
+
+    #[cfg(feature = "simulator")]
+    if let Some(Command::LeaderSim { command }) = cli.command.as_ref() {
+        if let Err(e) = leader_sim(command, rpc_client.as_ref()).await {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::Selftest) = cli.command {
+        let report = selftest::run(&config, rpc_client).await;
+        match cli.output {
+            OutputFormat::Json => match serde_json::to_string(&report) {
+                Ok(json) => println!("{}", json),
+                Err(e) => error!("Failed to serialize the self-test report: {}", e),
+            },
+            OutputFormat::Text => info!("{}", report),
+        }
+        if !report.passed() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::Doctor) = cli.command {
+        let report = doctor::diagnose(rpc_client).await;
+        match cli.output {
+            OutputFormat::Json => match serde_json::to_string(&report) {
+                Ok(json) => println!("{}", json),
+                Err(e) => error!("Failed to serialize the doctor report: {}", e),
+            },
+            OutputFormat::Text => info!("{}", report),
+        }
+        if !report.healthy() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(&Command::SendToken {
+        mint,
+        amount,
+        create_ata,
+    }) = cli.command.as_ref()
+    {
+        let mut token_transfer = match TokenTransfer::new(&rpc_client, mint, amount).await {
+            Ok(token_transfer) => token_transfer,
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = token_transfer
+            .check_destination(&rpc_client, &config.setup_receiver(), create_ata)
+            .await
+        {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        config.token_transfer = Some(token_transfer);
+    }
+    if let Some(Command::SendInstructions { file }) = cli.command.as_ref() {
+        match tx_builder::read_instructions(file) {
+            Ok(instructions) => config.instructions = Some(instructions),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
     info!("CONFIG {:#?}", config);
 
-    let tracker =
-        Arc::new(LeaderTrackerImpl::new(rpc_client.clone(), 4, 0, config.ws_url.clone()).await);
-    tracker.poll_slot_leaders_once().await.unwrap();
-
-    let mut attempts = 0;
-    while attempts < config.retry {
-        let leaders = tracker.get_leaders();
-
-        if let Some(leader) = leaders.last() {
-            info!("LEADER: {:#?}", leader);
-            // берем первого лидера из списка с учетом смещения
-            if let Some(tpu_quic) = &leader.tpu_quic {
-                let manager = QuicManager::new(rpc_client.clone(), *tpu_quic).await;
-                info!("QUIC: {:#?}", tpu_quic);
-
-                match manager.send_transaction(&config).await {
-                    Ok(signature) => {
-                        info!("Transaction sent. Confirmation...");
-                        match manager.check_confirm_transaction(&signature).await {
-                            Ok(_) => {
-                                info!("Transaction confirmed successfully.");
-                                let full_url = config.generate_url(&signature.to_string());
-                                info!("{}", full_url);
-                                break;
-                            }
-                            Err(e) => error!("Error confirming transaction: {:#?}", e),
-                        }
+    // catch oversized transactions before any leader tracking and QUIC traffic
+    if !matches!(
+        cli.command,
+        Some(Command::SendBatch { .. } | Command::Submit { .. } | Command::Debug { .. })
+    ) {
+        let size_check = config
+            .create_message(
+                Hash::default(),
+                config.compute_unit_limit,
+                config.compute_unit_price,
+            )
+            .map_err(SendError::from)
+            .and_then(|message| config.check_size(&message));
+        if let Err(e) = size_check {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(Command::Simulate) = cli.command {
+        let simulation = match tx_builder::build_transaction(&rpc_client, &config, None).await {
+            Ok((transaction, _)) => {
+                simulation::simulate_transaction(rpc_client.as_ref(), &transaction, false).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = simulation {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Command::Build { output }) = cli.command.as_ref() {
+        let built = match tx_builder::build_message(&rpc_client, &config, None).await {
+            Ok((message, recent_blockhash)) => {
+                let transaction = Transaction::new_unsigned(message);
+                offline::log_missing_signers(&transaction);
+                OfflineTransaction::new(&transaction)
+                    .and_then(|built| built.write(output))
+                    .map(|_| recent_blockhash.last_valid_block_height)
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match built {
+            Ok(last_valid_block_height) => info!(
+                "Transaction written to {}, sign and submit it before block height {}",
+                output, last_valid_block_height
+            ),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(path) = cli.output_only.as_ref() {
+        let written = match tx_builder::build_transaction(&rpc_client, &config, None)
+            .await
+            .map(|(transaction, _)| transaction)
+        {
+            Ok(transaction) if config.simulate => {
+                simulation::simulate_transaction(rpc_client.as_ref(), &transaction, config.force)
+                    .await
+                    .map(|_| transaction)
+            }
+            result => result,
+        }
+        .map_err(|e| e.to_string())
+        .and_then(|transaction| {
+            offline::write_wire_transaction(&transaction, path)?;
+            Ok(transaction.signatures[0])
+        });
+        match written {
+            Ok(signature) => {
+                info!("Transaction written to {}", path);
+                println!("{}", signature);
+            }
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let sends = matches!(
+        cli.command,
+        None | Some(
+            Command::Send { .. }
+                | Command::SendToken { .. }
+                | Command::SendBatch { .. }
+                | Command::SendInstructions { .. }
+                | Command::Bench { .. }
+        )
+    );
+    if sends && config.network.is_mainnet() && !cli.yes && !cli.dry_run {
+        confirm_mainnet_send(cli.command.as_ref(), &config, &rpc_client).await;
+    }
+
+    tokio::spawn(cancel_on_interrupt(config.cancel.clone()));
+    if cli.ws_blockhash {
+        config.blockhash_cache = Some(BlockhashCache::subscribe(
+            config.ws_url.clone(),
+            config.commitment_level,
+            config.cancel.clone(),
+        ));
+    }
+    let localnet = matches!(config.network, Network::Localnet);
+    let tracker = if localnet {
+        match LeaderTrackerImpl::local(rpc_client.clone()).await {
+            Ok(tracker) => Arc::new(tracker),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let tracker = LeaderTrackerImpl::cancellable(
+            rpc_client.clone(),
+            4,
+            0,
+            config.ws_url.clone(),
+            config.cancel.clone(),
+        )
+        .await;
+        tracker.poll_slot_leaders_once().await.unwrap();
+        Arc::new(tracker)
+    };
+
+    if cli.dry_run {
+        if !matches!(
+            cli.command,
+            None | Some(
+                Command::Send { .. } | Command::SendToken { .. } | Command::SendInstructions { .. }
+            )
+        ) {
+            error!("--dry-run only applies to sending a single transaction");
+            std::process::exit(2);
+        }
+        match sender::dry_run(&config, rpc_client, tracker.as_ref()).await {
+            Ok(dry_run) => match cli.output {
+                OutputFormat::Json => match serde_json::to_string(&dry_run) {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => error!("Failed to serialize the dry run: {}", e),
+                },
+                OutputFormat::Text => info!("{}", dry_run),
+            },
+            Err(e) => {
+                error!("Dry run failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let tx_tracker = TxTracker::new();
+    let request_id = tx_tracker.request_id();
+    if let Some(Command::Debug {
+        command: DebugCommand::Dump,
+    }) = cli.command
+    {
+        let dump = Dump::capture(&tracker);
+        match dump.to_json() {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    tokio::spawn(tx_tracker::log_events(tx_tracker.subscribe()));
+    let metrics = match Metrics::from_config(&config_file.metrics) {
+        Ok(metrics) => metrics,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut listeners = vec![
+        tokio::spawn(history.record(
+            rpc_client.clone(),
+            config.confirm_commitment,
+            tx_tracker.subscribe(),
+        )),
+        tokio::spawn(metrics.clone().record(tx_tracker.subscribe())),
+    ];
+    if let Some(url) = config.webhook_url.clone() {
+        listeners.push(tokio::spawn(Webhook::new(url).run(tx_tracker.subscribe())));
+    }
+    if let Some(addr) = cli.readiness_addr {
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => {
+                tokio::spawn(supervisor::global().clone().serve_readiness(listener));
+            }
+            Err(e) => {
+                error!("Failed to listen for readiness probes on {}: {}", addr, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    #[cfg(unix)]
+    tokio::spawn(debug::dump_on_signal(tracker.clone(), tx_tracker.states()));
+    #[cfg(all(unix, feature = "profiling"))]
+    tokio::spawn(quic::profiling::flamegraph_on_signal(
+        cli.profile_dir.clone(),
+    ));
+    tokio::spawn(
+        metrics
+            .clone()
+            .export_connection_stats_periodically(quic_manager::connection_stats()),
+    );
+    // the local tracker doesn't follow the slots
+    if !localnet {
+        tokio::spawn(leader_tracker::monitor_slot_lag(
+            tracker.clone(),
+            rpc_client.clone(),
+            metrics.clone(),
+            config_file
+                .metrics
+                .max_slot_lag
+                .unwrap_or(leader_tracker::DEFAULT_MAX_SLOT_LAG),
+            config.webhook_url.clone().map(Webhook::new),
+        ));
+    }
+    if matches!(
+        cli.command,
+        Some(Command::SendBatch { .. } | Command::Bench { .. })
+    ) && cli.log_file.is_some()
+        && cli.quiet == 0
+        && std::io::stderr().is_terminal()
+    {
+        listeners.push(tokio::spawn(progress::run(
+            progress::progress_bar(),
+            tx_tracker.subscribe(),
+        )));
+    }
+    if cli.quiet > 0 && cli.output == OutputFormat::Text {
+        listeners.push(tokio::spawn(progress::print_signatures(
+            tx_tracker.subscribe(),
+        )));
+    }
+    if let Some(path) = cli.report.as_ref() {
+        match Report::create(path) {
+            Ok(report) => listeners.push(tokio::spawn(report.record(
+                rpc_client.clone(),
+                config.confirm_commitment,
+                tx_tracker.subscribe(),
+            ))),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // the budget covers the whole run, including the leader tracker setup above
+    let deadline = cli
+        .deadline
+        .map(|deadline| (started + Duration::from_secs(deadline), deadline));
+    let outcome = match cli.command {
+        Some(Command::SendBatch { file }) => {
+            let sent = within_deadline(
+                deadline,
+                &tx_tracker,
+                batch::send_batch(&config, rpc_client, tracker.as_ref(), &tx_tracker, &file),
+            )
+            .await;
+            flush_listeners(tx_tracker, listeners).await;
+            metrics.export_connection_stats(&quic_manager::connection_stats());
+            metrics.log_summary();
+            match sent {
+                Ok(Ok(0)) => return,
+                Ok(Ok(_)) => std::process::exit(1),
+                Ok(Err(e)) => {
+                    error!("Error sending batch: {}", e);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Command::Bench {
+            tps,
+            ramp: true,
+            max_drop_rate,
+            json_report,
+            ..
+        }) => {
+            let report = within_deadline(
+                deadline,
+                &tx_tracker,
+                bench::ramp(
+                    &config,
+                    rpc_client,
+                    tracker.clone(),
+                    &tx_tracker,
+                    tps,
+                    max_drop_rate,
+                ),
+            )
+            .await;
+            flush_listeners(tx_tracker, listeners).await;
+            metrics.export_connection_stats(&quic_manager::connection_stats());
+            metrics.log_summary();
+            match report {
+                Ok(Ok(report)) => {
+                    bench::log_ramp(&report);
+                    let Some(path) = json_report else {
+                        return;
+                    };
+                    let settings = bench::Settings::new(&config, tps, false);
+                    match bench::JsonReport::ramp(settings, &report).write(&path) {
+                        Ok(()) => return info!("[ BENCH ] - Report written to {}", path.display()),
+                        Err(e) => error!("{}", e),
                     }
-                    Err(e) => error!("Error sending transaction: {:#?}", e),
                 }
-            } else {
-                error!("No QUIC address available for the current leader.");
+                Ok(Err(e)) => error!("Error running the bench: {}", e),
+                Err(e) => error!("{}", e),
             }
-        } else {
-            error!("No current leader available. Searching...");
+            std::process::exit(1);
+        }
+        Some(Command::Bench {
+            count,
+            tps,
+            compare_rpc,
+            json_report,
+            ..
+        }) => {
+            let report = within_deadline(
+                deadline,
+                &tx_tracker,
+                bench::bench(
+                    &config,
+                    rpc_client,
+                    tracker.clone(),
+                    &tx_tracker,
+                    count,
+                    tps,
+                    compare_rpc,
+                ),
+            )
+            .await;
+            flush_listeners(tx_tracker, listeners).await;
+            metrics.export_connection_stats(&quic_manager::connection_stats());
+            metrics.log_summary();
+            match report {
+                Ok(Ok(reports)) => {
+                    bench::log_reports(&reports);
+                    let Some(path) = json_report else {
+                        return;
+                    };
+                    let settings = bench::Settings::new(&config, tps, compare_rpc);
+                    match bench::JsonReport::bench(settings, &reports).write(&path) {
+                        Ok(()) => return info!("[ BENCH ] - Report written to {}", path.display()),
+                        Err(e) => error!("{}", e),
+                    }
+                }
+                Ok(Err(e)) => error!("Error running the bench: {}", e),
+                Err(e) => error!("{}", e),
+            }
+            std::process::exit(1);
+        }
+        Some(Command::Submit { file }) => {
+            let transaction = match OfflineTransaction::read(&file).and_then(|f| f.transaction()) {
+                Ok(transaction) => transaction,
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let missing = offline::missing_signers(&transaction);
+            if !missing.is_empty() {
+                error!("Transaction is missing signatures: {:?}", missing);
+                std::process::exit(1);
+            }
+            within_deadline(
+                deadline,
+                &tx_tracker,
+                submit_with_retry(
+                    &config,
+                    rpc_client,
+                    tracker.as_ref(),
+                    &tx_tracker,
+                    &transaction,
+                ),
+            )
+            .await
+            .and_then(|outcome| outcome)
+        }
+        None | Some(Command::Send { .. }) if !config.sweep => {
+            if let Err(e) =
+                amount::check_rent_exemption(&rpc_client, &config.setup_receiver(), config.amount)
+                    .await
+            {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+            within_deadline(
+                deadline,
+                &tx_tracker,
+                send_with_retry(&config, rpc_client, tracker.as_ref(), &tx_tracker),
+            )
+            .await
+            .and_then(|outcome| outcome)
+        }
+        _ => within_deadline(
+            deadline,
+            &tx_tracker,
+            send_with_retry(&config, rpc_client, tracker.as_ref(), &tx_tracker),
+        )
+        .await
+        .and_then(|outcome| outcome),
+    };
+
+    flush_listeners(tx_tracker, listeners).await;
+    metrics.export_connection_stats(&quic_manager::connection_stats());
+    metrics.log_summary();
+    match outcome {
+        Ok(outcome) => {
+            outcome.log(&config);
+            match cli.output {
+                OutputFormat::Json => {
+                    println!("{}", outcome.to_json(&config, started.elapsed()))
+                }
+                OutputFormat::Text => {}
+            }
+        }
+        Err(e) => {
+            error!(%request_id, "Error sending transaction: {}", e);
+            if cli.output == OutputFormat::Json {
+                println!(
+                    "{}",
+                    outcome::failure_json(request_id, &e, started.elapsed())
+                );
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+/// within_deadline runs the send until the deadline, if any. When it passes, the best-known state
+/// of every tracked transaction is reported and the send is abandoned
+async fn within_deadline<T>(
+    deadline: Option<(Instant, u64)>,
+    tx_tracker: &TxTracker,
+    send: impl Future<Output = T>,
+) -> Result<T, SendError> {
+    let Some((deadline, seconds)) = deadline else {
+        return Ok(send.await);
+    };
+    match tokio::time::timeout_at(deadline, send).await {
+        Ok(result) => Ok(result),
+        Err(_) => {
+            for (signature, state) in tx_tracker.snapshot() {
+                info!("[ DEADLINE ] - {} {:?}", signature, state);
+            }
+            Err(SendError::DeadlineExceeded {
+                deadline: Duration::from_secs(seconds),
+            })
+        }
+    }
+}
+
+/// confirm_mainnet_send shows what is about to be signed and exits unless it's confirmed at the
+/// terminal. Without a terminal, e.g. in scripts, `--yes` is required
+async fn confirm_mainnet_send(command: Option<&Command>, config: &Config, rpc_client: &RpcClient) {
+    if !std::io::stdin().is_terminal() {
+        error!(
+            "Refusing to send on {} without a terminal to confirm it, pass --yes to skip the confirmation",
+            config.network.name()
+        );
+        std::process::exit(1);
+    }
+    let confirmed = match send_summary(command, config, rpc_client).await {
+        Ok(summary) => prompt::confirm(
+            &summary,
+            &mut std::io::stdin().lock(),
+            &mut std::io::stderr(),
+        ),
+        Err(e) => Err(e),
+    };
+    match confirmed {
+        Ok(true) => {}
+        Ok(false) => {
+            info!("Not confirmed, nothing was signed or sent");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
         }
-        attempts += 1;
-        sleep(Duration::from_secs(1)).await;
     }
+}
+
+/// send_summary describes the receiver, the amount and the expected fee of the send
+async fn send_summary(
+    command: Option<&Command>,
+    config: &Config,
+    rpc_client: &RpcClient,
+) -> Result<SendSummary, String> {
+    let sol = |lamports| format!("{} SOL", amount::format_sol(lamports));
+    let (receiver, amount) = match command {
+        Some(Command::SendBatch { file }) => {
+            let payouts = batch::read_payouts(file)?;
+            let total = payouts
+                .iter()
+                .fold(0u64, |total, payout| total.saturating_add(payout.amount));
+            (
+                format!("{} payouts from {}", payouts.len(), file),
+                sol(total),
+            )
+        }
+        Some(Command::Bench { count, ramp, .. }) => (
+            "the sender itself".to_string(),
+            match ramp {
+                true => {
+                    "self-transfers until the rate saturates, only the fees are spent".to_string()
+                }
+                false => format!("{} self-transfers, only the fees are spent", count),
+            },
+        ),
+        _ => match (&config.token_transfer, &config.instructions) {
+            (Some(token_transfer), _) => (
+                config.setup_receiver().to_string(),
+                format!(
+                    "{} of mint {}",
+                    amount::format_units(token_transfer.amount, token_transfer.decimals),
+                    token_transfer.mint
+                ),
+            ),
+            (_, Some(instructions)) => {
+                let programs: BTreeSet<String> = instructions
+                    .iter()
+                    .map(|instruction| instruction.program_id.to_string())
+                    .collect();
+                (
+                    format!(
+                        "programs {}",
+                        programs.into_iter().collect::<Vec<_>>().join(", ")
+                    ),
+                    format!("set by {} custom instructions", instructions.len()),
+                )
+            }
+            _ if config.sweep => (
+                config.setup_receiver().to_string(),
+                "the whole balance minus the fee".to_string(),
+            ),
+            _ => (config.setup_receiver().to_string(), sol(config.amount)),
+        },
+    };
+
+    let compute_unit_price = fees::get_compute_unit_price(rpc_client, config).await;
+    let message = config.create_message(
+        Hash::default(),
+        config.compute_unit_limit,
+        compute_unit_price,
+    )?;
+    Ok(SendSummary {
+        network: config.network.name(),
+        sender: config.try_sender()?,
+        receiver,
+        amount,
+        fee: fees::expected_fee(&message, config.compute_unit_limit, compute_unit_price),
+    })
+}
 
-    if attempts >= config.retry {
-        info!("Maximum number of attempts reached, stopping the application.");
+/// cancel_on_interrupt cancels the send on the first Ctrl-C, so the outcome of the tracked
+/// transactions is still recorded and reported. A second one exits right away
+async fn cancel_on_interrupt(cancel: CancellationToken) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    warn!("[ SHUTDOWN ] - Interrupted, cancelling. Press Ctrl-C again to exit right away");
+    cancel.cancel();
+    if tokio::signal::ctrl_c().await.is_ok() {
+        std::process::exit(130);
+    }
+}
+
+/// flush_listeners closes the tracker and waits for its listeners to record and deliver the
+/// pending outcomes and for the pending spans to be exported before the process exits
+async fn flush_listeners(tx_tracker: TxTracker, listeners: Vec<JoinHandle<()>>) {
+    drop(tx_tracker);
+    let flushed = tokio::time::timeout(
+        Duration::from_secs(30),
+        futures_util::future::join_all(listeners),
+    )
+    .await;
+    if flushed.is_err() {
+        error!("Timed out recording and delivering the transaction outcomes");
+    }
+    #[cfg(feature = "otel")]
+    tokio::task::spawn_blocking(quic::telemetry::shutdown)
+        .await
+        .ok();
+}
+
+/// leader_sim captures a leader schedule or replays it with every combination of the strategies
+#[cfg(feature = "simulator")]
+async fn leader_sim(command: &LeaderSimCommand, rpc_client: &RpcClient) -> Result<(), String> {
+    use quic::simulator::{self, CapturedSchedule, Settings, Strategy};
+
+    let (schedule, settings, strategies, json_report) = match command {
+        LeaderSimCommand::Capture { slots, output } => {
+            let schedule = CapturedSchedule::capture(rpc_client, *slots).await?;
+            schedule.write(output)?;
+            info!(
+                "[ SIMULATION ] - {} slot leaders from slot {} written to {}",
+                schedule.leaders.len(),
+                schedule.first_slot,
+                output.display()
+            );
+            return Ok(());
+        }
+        LeaderSimCommand::Run {
+            schedule,
+            tps,
+            slots,
+            slot_ms,
+            num_leaders,
+            leader_offsets,
+            fanouts,
+            json_report,
+        } => {
+            let strategies: Vec<Strategy> = num_leaders
+                .iter()
+                .flat_map(|&num_leaders| {
+                    leader_offsets.iter().flat_map(move |&leader_offset| {
+                        fanouts.iter().map(move |&fanout| Strategy {
+                            num_leaders,
+                            leader_offset,
+                            fanout,
+                        })
+                    })
+                })
+                .collect();
+            let settings = Settings {
+                tps: *tps,
+                slots: *slots,
+                slot_ms: *slot_ms,
+            };
+            (
+                CapturedSchedule::read(schedule)?,
+                settings,
+                strategies,
+                json_report,
+            )
+        }
+    };
+
+    let mut reports = Vec::new();
+    for strategy in strategies {
+        info!(
+            "[ SIMULATION ] - Replaying {} slots with {}",
+            settings.slots, strategy
+        );
+        reports.push(simulator::simulate(&schedule, strategy, settings).await?);
+    }
+    simulator::log_reports(&reports);
+    if let Some(path) = json_report {
+        let json = serde_json::to_string_pretty(&serde_json::json!({
+            "settings": settings,
+            "strategies": reports,
+        }))
+        .map_err(|e| format!("Failed to serialize the simulation report: {}", e))?;
+        std::fs::write(path, json)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// show_history prints the history records asked for
+fn show_history(history: &History, command: &HistoryCommand) -> Result<(), String> {
+    let records = history.read()?;
+    match command {
+        HistoryCommand::List { limit } => {
+            for record in records.iter().rev().take(*limit) {
+                println!(
+                    "{}  {}  {:<9}  slot {:<10}  fee {:<8}  {}",
+                    record.timestamp,
+                    record.signature,
+                    record.status,
+                    record
+                        .slot
+                        .map_or_else(|| "-".to_string(), |slot| slot.to_string()),
+                    record
+                        .fee
+                        .map_or_else(|| "-".to_string(), |fee| fee.to_string()),
+                    record.leaders.join(",")
+                );
+            }
+        }
+        HistoryCommand::Show { signature } => {
+            let record = records
+                .iter()
+                .rev()
+                .find(|record| record.signature == *signature)
+                .ok_or_else(|| format!("{} not found in the history", signature))?;
+            let record = serde_json::to_string_pretty(record)
+                .map_err(|e| format!("Failed to serialize history record: {}", e))?;
+            println!("{}", record);
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,502 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::UdpSocket,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hdrhistogram::Histogram;
+use solana_connection_cache::connection_cache_stats::{
+    ConnectionCacheStats, CONNECTION_STAT_SUBMISSION_INTERVAL,
+};
+use solana_sdk::signature::Signature;
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+use crate::{
+    config::{MetricsSection, MetricsSink},
+    tx_tracker::{TxEvent, TxState},
+};
+
+/// Transactions handed to a leader, resubmissions included
+pub const SENDS: &str = "sends";
+/// Send attempts after the first one
+pub const RETRIES: &str = "retries";
+pub const CONFIRMED: &str = "confirmed";
+/// Failures delivering the transaction to the leader
+pub const SEND_FAILURES: &str = "failures.send";
+/// Transactions that failed on-chain or couldn't be confirmed
+pub const CONFIRM_FAILURES: &str = "failures.confirm";
+/// Transactions whose blockhash expired before they landed
+pub const EXPIRED: &str = "failures.expired";
+/// Time the RPC node took to hand out a blockhash
+pub const BLOCKHASH_LATENCY: &str = "latency.blockhash";
+/// Time from signing a transaction to handing it to the first leader
+pub const SEND_LATENCY: &str = "latency.send";
+/// Time delivering a transaction to a leader over QUIC took, the handshake included
+pub const QUIC_SEND_LATENCY: &str = "latency.quic_send";
+/// Time from handing a transaction to the first leader to its confirmation
+pub const CONFIRM_LATENCY: &str = "latency.confirm";
+/// Transactions delivered to a leader, with a `leader` tag
+pub const LEADER_DELIVERED: &str = "leader.delivered";
+/// Failures delivering to a leader, with a `leader` tag
+pub const LEADER_FAILED: &str = "leader.failed";
+/// QUIC connections opened to the leaders
+pub const QUIC_CONNECTIONS: &str = "quic.connections";
+/// Sends over an already open QUIC connection
+pub const QUIC_CONNECTION_REUSE: &str = "quic.connection_reuse";
+pub const QUIC_CONNECTION_ERRORS: &str = "quic.connection_errors";
+/// Reconnections resuming the session with 0-RTT, accepted or rejected by the leader
+pub const QUIC_ZERO_RTT_ACCEPTS: &str = "quic.zero_rtt_accepts";
+pub const QUIC_ZERO_RTT_REJECTS: &str = "quic.zero_rtt_rejects";
+/// Packets the leaders received
+pub const QUIC_PACKETS: &str = "quic.packets";
+/// Losses the QUIC congestion controller reacted to, the transport doesn't expose retransmits
+pub const QUIC_CONGESTION_EVENTS: &str = "quic.congestion_events";
+/// Sends held back by the stream limit of the leader
+pub const QUIC_STREAMS_BLOCKED: &str = "quic.streams_blocked";
+/// Sends held back by the flow control of the leader
+pub const QUIC_DATA_BLOCKED: &str = "quic.data_blocked";
+/// Time spent opening QUIC connections, in milliseconds
+pub const QUIC_HANDSHAKE_TIME: &str = "quic.handshake_ms";
+/// Average time opening a QUIC connection took over an export interval
+pub const QUIC_HANDSHAKE_LATENCY: &str = "latency.quic_handshake";
+
+/// Slots the leader tracker is behind the RPC node, negative if ahead
+pub const SLOT_LAG: &str = "slot_lag";
+
+/// Number of leader identities tracked on their own, the next ones are counted as OTHER_LEADER
+pub const MAX_LEADER_LABELS: usize = 64;
+pub const OTHER_LEADER: &str = "other";
+
+/// LeaderStats is the delivery record of a leader identity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LeaderStats {
+    pub delivered: u64,
+    pub failed: u64,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    counters: BTreeMap<&'static str, u64>,
+    gauges: BTreeMap<&'static str, i64>,
+    /// Deliveries per leader identity, at most MAX_LEADER_LABELS of them plus OTHER_LEADER
+    leaders: BTreeMap<String, LeaderStats>,
+    /// Latencies in microseconds
+    latencies: BTreeMap<&'static str, Histogram<u64>>,
+}
+
+const DEFAULT_STATSD_HOST: &str = "127.0.0.1:8125";
+const DEFAULT_PREFIX: &str = "quic";
+
+/// Statsd pushes the metrics to a StatsD agent over UDP, with DogStatsD tags if any
+#[derive(Debug)]
+pub struct Statsd {
+    socket: UdpSocket,
+    prefix: String,
+    /// `|#tag,tag` suffix, empty without tags since plain StatsD doesn't know them
+    tags: String,
+}
+
+impl Statsd {
+    pub fn connect(host: &str, prefix: &str, tags: &[String]) -> Result<Self, String> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .and_then(|socket| socket.connect(host).map(|_| socket))
+            .map_err(|e| format!("Failed to connect to the StatsD agent {}: {}", host, e))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|e| format!("Failed to set up the StatsD socket: {}", e))?;
+        Ok(Self {
+            socket,
+            prefix: prefix.to_string(),
+            tags: match tags.is_empty() {
+                true => String::new(),
+                false => format!("|#{}", tags.join(",")),
+            },
+        })
+    }
+
+    pub fn count(&self, name: &str, value: u64) {
+        self.send(name, &value.to_string(), "c");
+    }
+
+    /// count_tagged counts with an additional DogStatsD tag, e.g. `leader:<identity>`
+    pub fn count_tagged(&self, name: &str, value: u64, tag: &str) {
+        let tags = match self.tags.is_empty() {
+            true => format!("|#{}", tag),
+            false => format!("{},{}", self.tags, tag),
+        };
+        self.send_line(format!("{}.{}:{}|c{}", self.prefix, name, value, tags));
+    }
+
+    pub fn gauge(&self, name: &str, value: i64) {
+        self.send(name, &value.to_string(), "g");
+    }
+
+    pub fn timing(&self, name: &str, latency: Duration) {
+        self.send(name, &latency.as_millis().to_string(), "ms");
+    }
+
+    fn send(&self, name: &str, value: &str, kind: &str) {
+        self.send_line(format!(
+            "{}.{}:{}|{}{}",
+            self.prefix, name, value, kind, self.tags
+        ));
+    }
+
+    fn send_line(&self, line: String) {
+        // metrics are best effort, a lost datagram must not disturb the send
+        let _ = self.socket.send(line.as_bytes());
+    }
+}
+
+/// Metrics is the registry of the run's counters and latencies, shared by every clone. Every
+/// update is also pushed to the StatsD agent if there is one
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    registry: Arc<Mutex<Registry>>,
+    statsd: Option<Arc<Statsd>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// from_config returns the metrics pushed to the sink of the `[metrics]` section
+    pub fn from_config(section: &MetricsSection) -> Result<Self, String> {
+        let statsd = match section.sink {
+            MetricsSink::None => None,
+            MetricsSink::Statsd => Some(Arc::new(Statsd::connect(
+                section
+                    .statsd_host
+                    .as_deref()
+                    .unwrap_or(DEFAULT_STATSD_HOST),
+                section.prefix.as_deref().unwrap_or(DEFAULT_PREFIX),
+                &section.tags,
+            )?)),
+        };
+        Ok(Self {
+            statsd,
+            ..Self::default()
+        })
+    }
+
+    pub fn incr(&self, name: &'static str) {
+        self.add(name, 1);
+    }
+
+    pub fn add(&self, name: &'static str, value: u64) {
+        *self.lock().counters.entry(name).or_default() += value;
+        if let Some(statsd) = &self.statsd {
+            statsd.count(name, value);
+        }
+    }
+
+    pub fn set_gauge(&self, name: &'static str, value: i64) {
+        self.lock().gauges.insert(name, value);
+        if let Some(statsd) = &self.statsd {
+            statsd.gauge(name, value);
+        }
+    }
+
+    pub fn gauge(&self, name: &'static str) -> Option<i64> {
+        self.lock().gauges.get(name).copied()
+    }
+
+    pub fn observe(&self, name: &'static str, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        self.lock()
+            .latencies
+            .entry(name)
+            .or_insert_with(|| Histogram::new(3).expect("3 significant figures are supported"))
+            .saturating_record(micros);
+        if let Some(statsd) = &self.statsd {
+            statsd.timing(name, latency);
+        }
+    }
+
+    /// leader_delivered counts a transaction delivered to the leader
+    pub fn leader_delivered(&self, leader: &str) {
+        let label = self.update_leader(leader, |stats| stats.delivered += 1);
+        if let Some(statsd) = &self.statsd {
+            statsd.count_tagged(LEADER_DELIVERED, 1, &format!("leader:{}", label));
+        }
+    }
+
+    /// leader_failed counts a failure delivering a transaction to the leader
+    pub fn leader_failed(&self, leader: &str) {
+        let label = self.update_leader(leader, |stats| stats.failed += 1);
+        if let Some(statsd) = &self.statsd {
+            statsd.count_tagged(LEADER_FAILED, 1, &format!("leader:{}", label));
+        }
+    }
+
+    /// update_leader updates the stats of the leader, or of OTHER_LEADER once MAX_LEADER_LABELS
+    /// identities are tracked, and returns the label they are kept under
+    fn update_leader(&self, leader: &str, update: impl FnOnce(&mut LeaderStats)) -> String {
+        let mut registry = self.lock();
+        let label = match registry.leaders.contains_key(leader)
+            || registry.leaders.len() < MAX_LEADER_LABELS
+        {
+            true => leader,
+            false => OTHER_LEADER,
+        };
+        update(registry.leaders.entry(label.to_string()).or_default());
+        label.to_string()
+    }
+
+    /// leader_stats returns the delivery record of every leader identity targeted so far
+    pub fn leader_stats(&self) -> Vec<(String, LeaderStats)> {
+        self.lock()
+            .leaders
+            .iter()
+            .map(|(leader, stats)| (leader.clone(), *stats))
+            .collect()
+    }
+
+    pub fn counter(&self, name: &'static str) -> u64 {
+        self.lock().counters.get(name).copied().unwrap_or_default()
+    }
+
+    /// latency_percentile returns the percentile of the latencies observed under the name, to 3
+    /// significant figures, None if there are none
+    pub fn latency_percentile(&self, name: &'static str, percentile: f64) -> Option<Duration> {
+        let registry = self.lock();
+        let histogram = registry.latencies.get(name).filter(|h| !h.is_empty())?;
+        Some(Duration::from_micros(
+            histogram.value_at_percentile(percentile),
+        ))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Registry> {
+        // a panicked holder can't leave the counters inconsistent
+        self.registry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// export_connection_stats adds what the QUIC connections counted since the last export to the
+    /// counters
+    pub fn export_connection_stats(&self, stats: &ConnectionCacheStats) {
+        let client = &stats.total_client_stats;
+        // the connection statistics only grow, the counters hold what was exported so far
+        let export = |name, total| self.catch_up(name, total);
+        let connections = export(
+            QUIC_CONNECTIONS,
+            client.total_connections.load(Ordering::Relaxed),
+        );
+        export(
+            QUIC_CONNECTION_REUSE,
+            client.connection_reuse.load(Ordering::Relaxed),
+        );
+        export(
+            QUIC_CONNECTION_ERRORS,
+            client.connection_errors.load(Ordering::Relaxed),
+        );
+        export(
+            QUIC_ZERO_RTT_ACCEPTS,
+            client.zero_rtt_accepts.load(Ordering::Relaxed),
+        );
+        export(
+            QUIC_ZERO_RTT_REJECTS,
+            client.zero_rtt_rejects.load(Ordering::Relaxed),
+        );
+        export(
+            QUIC_PACKETS,
+            client.successful_packets.load(Ordering::Relaxed),
+        );
+        let handshake_ms = export(
+            QUIC_HANDSHAKE_TIME,
+            client.make_connection_ms.load(Ordering::Relaxed),
+        );
+        if let Some(average_ms) = handshake_ms.checked_div(connections) {
+            self.observe(QUIC_HANDSHAKE_LATENCY, Duration::from_millis(average_ms));
+        }
+
+        // these are reset by every read
+        for (name, delta) in [
+            (
+                QUIC_CONGESTION_EVENTS,
+                client.congestion_events.load_and_reset(),
+            ),
+            (
+                QUIC_STREAMS_BLOCKED,
+                client.streams_blocked_uni.load_and_reset(),
+            ),
+            (QUIC_DATA_BLOCKED, client.data_blocked.load_and_reset()),
+        ] {
+            if delta > 0 {
+                self.add(name, delta);
+            }
+        }
+    }
+
+    /// catch_up raises the counter to the total and returns by how much. The counter is read and
+    /// written under one lock, so exports running at the same time can't both add the same delta
+    fn catch_up(&self, name: &'static str, total: u64) -> u64 {
+        let delta = {
+            let mut registry = self.lock();
+            let counter = registry.counters.entry(name).or_default();
+            let delta = total.saturating_sub(*counter);
+            *counter += delta;
+            delta
+        };
+        match &self.statsd {
+            Some(statsd) if delta > 0 => statsd.count(name, delta),
+            _ => {}
+        }
+        delta
+    }
+
+    /// export_connection_stats_periodically exports the QUIC connection statistics every 2 seconds,
+    /// it never returns
+    pub async fn export_connection_stats_periodically(self, stats: Arc<ConnectionCacheStats>) {
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(CONNECTION_STAT_SUBMISSION_INTERVAL));
+        loop {
+            interval.tick().await;
+            self.export_connection_stats(&stats);
+        }
+    }
+
+    /// record derives the metrics from the tracker events until it's dropped
+    pub async fn record(self, mut events: broadcast::Receiver<TxEvent>) {
+        let mut built_at: HashMap<Signature, Instant> = HashMap::new();
+        let mut sent_at: HashMap<Signature, Instant> = HashMap::new();
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    error!("[ METRICS ] - {} events skipped", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            match event {
+                TxEvent::BlockhashFetched { latency, .. } => {
+                    self.observe(BLOCKHASH_LATENCY, latency)
+                }
+                TxEvent::Retry { .. } => self.incr(RETRIES),
+                TxEvent::SendFailed { leader, .. } => {
+                    self.incr(SEND_FAILURES);
+                    self.leader_failed(&leader);
+                }
+                TxEvent::Transition {
+                    signature, state, ..
+                } => match state {
+                    TxState::Built => {
+                        built_at.insert(signature, Instant::now());
+                    }
+                    TxState::Sent {
+                        leader, latency, ..
+                    } => {
+                        self.incr(SENDS);
+                        self.leader_delivered(&leader);
+                        self.observe(QUIC_SEND_LATENCY, latency);
+                        if let Some(built_at) = built_at.remove(&signature) {
+                            self.observe(SEND_LATENCY, built_at.elapsed());
+                        }
+                        sent_at.entry(signature).or_insert_with(Instant::now);
+                    }
+                    TxState::Confirmed { .. } => {
+                        self.incr(CONFIRMED);
+                        if let Some(sent_at) = sent_at.remove(&signature) {
+                            self.observe(CONFIRM_LATENCY, sent_at.elapsed());
+                        }
+                    }
+                    TxState::Failed { .. } => self.incr(CONFIRM_FAILURES),
+                    TxState::Expired => self.incr(EXPIRED),
+                },
+            }
+        }
+    }
+
+    /// log_summary prints the end-of-run statistics
+    pub fn log_summary(&self) {
+        let latency = |name| match (
+            self.latency_percentile(name, 50.0),
+            self.latency_percentile(name, 90.0),
+            self.latency_percentile(name, 99.0),
+        ) {
+            (Some(p50), Some(p90), Some(p99)) => {
+                format!("p50 {:?}, p90 {:?}, p99 {:?}", p50, p90, p99)
+            }
+            _ => "none".to_string(),
+        };
+        let mut leaders = self.leader_stats();
+        let targeted = leaders.len();
+        // the leaders dropping the most deliveries first
+        leaders.retain(|(_, stats)| stats.failed > 0);
+        leaders.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.failed));
+        let failing = match leaders.is_empty() {
+            true => "none".to_string(),
+            false => leaders
+                .iter()
+                .take(5)
+                .map(|(leader, stats)| {
+                    format!(
+                        "{} ({} of {} failed)",
+                        leader,
+                        stats.failed,
+                        stats.failed + stats.delivered
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        info!(
+            "[ STATS\n\tSENDS: {} to {} leaders, {} retries\n\tCONFIRMED: {}\n\tFAILURES: {} send, {} confirmation, {} expired\n\tFAILING LEADERS: {}\n\tSLOT LAG: {}\n\tQUIC: {} connections ({} errors, {} reused), {} packets, {} congestion events\n\tBLOCKHASH LATENCY: {}\n\tSEND LATENCY: {}\n\tQUIC HANDSHAKE LATENCY: {}\n\tQUIC SEND LATENCY: {}\n\tCONFIRM LATENCY: {}\n]",
+            self.counter(SENDS),
+            targeted,
+            self.counter(RETRIES),
+            self.counter(CONFIRMED),
+            self.counter(SEND_FAILURES),
+            self.counter(CONFIRM_FAILURES),
+            self.counter(EXPIRED),
+            failing,
+            self.gauge(SLOT_LAG)
+                .map_or("none".to_string(), |lag| lag.to_string()),
+            self.counter(QUIC_CONNECTIONS),
+            self.counter(QUIC_CONNECTION_ERRORS),
+            self.counter(QUIC_CONNECTION_REUSE),
+            self.counter(QUIC_PACKETS),
+            self.counter(QUIC_CONGESTION_EVENTS),
+            latency(BLOCKHASH_LATENCY),
+            latency(SEND_LATENCY),
+            latency(QUIC_HANDSHAKE_LATENCY),
+            latency(QUIC_SEND_LATENCY),
+            latency(CONFIRM_LATENCY)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_exports_count_the_connections_once() {
+        let metrics = Metrics::new();
+        let stats = ConnectionCacheStats::default();
+        stats
+            .total_client_stats
+            .total_connections
+            .store(1_000, Ordering::Relaxed);
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        metrics.export_connection_stats(&stats);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(metrics.counter(QUIC_CONNECTIONS), 1_000);
+    }
+}
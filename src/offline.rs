@@ -0,0 +1,104 @@
+use std::fs;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Signature},
+    signer::Signer,
+    transaction::Transaction,
+};
+use tracing::info;
+
+/// OfflineTransaction is the file passed between the `build`, `sign` and `submit` steps
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OfflineTransaction {
+    /// Transaction in wire format encoded as base64, missing signatures are zeroed
+    pub transaction: String,
+    /// Signers required by the message, in signature order
+    pub signers: Vec<String>,
+}
+
+impl OfflineTransaction {
+    pub fn new(transaction: &Transaction) -> Result<Self, String> {
+        let serialized = bincode::serialize(transaction)
+            .map_err(|e| format!("Failed to serialize transaction: {}", e))?;
+        let num_signers = transaction.message.header.num_required_signatures as usize;
+
+        Ok(Self {
+            transaction: STANDARD.encode(serialized),
+            signers: transaction.message.account_keys[..num_signers]
+                .iter()
+                .map(|signer| signer.to_string())
+                .collect(),
+        })
+    }
+
+    pub fn transaction(&self) -> Result<Transaction, String> {
+        let serialized = STANDARD
+            .decode(&self.transaction)
+            .map_err(|e| format!("Invalid base64 transaction: {}", e))?;
+        bincode::deserialize(&serialized).map_err(|e| format!("Invalid transaction: {}", e))
+    }
+
+    pub fn read(path: &str) -> Result<Self, String> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path, e))
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize {}: {}", path, e))?;
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+/// write_wire_transaction writes the signed transaction in wire format encoded as base64, ready to
+/// be handed to any other delivery system
+pub fn write_wire_transaction(transaction: &Transaction, path: &str) -> Result<(), String> {
+    let serialized = bincode::serialize(transaction)
+        .map_err(|e| format!("Failed to serialize transaction: {}", e))?;
+    fs::write(path, STANDARD.encode(serialized))
+        .map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// missing_signers returns the required signers that haven't signed the transaction yet
+pub fn missing_signers(transaction: &Transaction) -> Vec<Pubkey> {
+    transaction
+        .message
+        .account_keys
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(signer, _)| *signer)
+        .collect()
+}
+
+/// sign adds the signature of a local keypair to the transaction in the file, without any network
+/// access so it can run on an air-gapped machine
+pub fn sign(path: &str, keypair_path: &str) -> Result<(), String> {
+    let offline_transaction = OfflineTransaction::read(path)?;
+    let mut transaction = offline_transaction.transaction()?;
+    let keypair = read_keypair_file(keypair_path)
+        .map_err(|e| format!("Failed to read keypair {}: {}", keypair_path, e))?;
+
+    let blockhash = transaction.message.recent_blockhash;
+    transaction
+        .try_partial_sign(&[&keypair], blockhash)
+        .map_err(|e| format!("Failed to sign with {}: {}", keypair.pubkey(), e))?;
+    OfflineTransaction::new(&transaction)?.write(path)?;
+
+    info!("[ SIGNED ] - {} signed {}", keypair.pubkey(), path);
+    log_missing_signers(&transaction);
+    Ok(())
+}
+
+pub fn log_missing_signers(transaction: &Transaction) {
+    let missing = missing_signers(transaction);
+    if missing.is_empty() {
+        info!("All signatures present, ready to submit");
+    } else {
+        info!("Missing signatures: {:?}", missing);
+    }
+}
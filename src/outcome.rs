@@ -0,0 +1,326 @@
+use std::{fmt, net::SocketAddr, sync::Arc, time::Duration};
+
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::{
+    borsh1::try_from_slice_unchecked,
+    clock::Slot,
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    compute_budget::{self, ComputeBudgetInstruction},
+    fee::FeeStructure,
+    signature::Signature,
+};
+use solana_transaction_status::UiTransactionEncoding;
+use tokio::{sync::mpsc, time::sleep};
+use tracing::{error, info, warn};
+
+use crate::{config::Config, confirmation::Confirmation, error::SendError, tx_tracker::RequestId};
+
+/// OutputFormat is how the final result is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OutputFormat {
+    /// Human readable summary in the logs
+    Text,
+    /// A single JSON object on stdout, logs go to stderr
+    Json,
+}
+
+/// DeliveryPath is how the transaction reached the cluster
+#[derive(Debug, Clone)]
+pub enum DeliveryPath {
+    /// Sent directly to the TPU QUIC port of a leader
+    Quic {
+        leader: String,
+        tpu_quic: SocketAddr,
+    },
+}
+
+impl fmt::Display for DeliveryPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryPath::Quic { leader, tpu_quic } => {
+                write!(f, "QUIC to leader {} at {}", leader, tpu_quic)
+            }
+        }
+    }
+}
+
+/// PaidFee is what a landed transaction was actually charged, read from its meta, next to the
+/// compute budget it requested
+#[derive(Debug, Clone)]
+pub struct PaidFee {
+    /// Total fee in lamports
+    pub fee: u64,
+    /// Part of the fee above the base fee of the signatures, in lamports
+    pub priority_fee: u64,
+    /// Requested compute unit price in micro-lamports
+    pub compute_unit_price: Option<u64>,
+    pub compute_unit_limit: Option<u32>,
+    pub compute_units_consumed: Option<u64>,
+}
+
+impl PaidFee {
+    fn describe_fee(&self) -> String {
+        format!(
+            "{} lamports ({} base + {} priority at {} micro-lamports/CU)",
+            self.fee,
+            self.fee - self.priority_fee,
+            self.priority_fee,
+            self.compute_unit_price.unwrap_or_default()
+        )
+    }
+
+    fn describe_compute_units(&self) -> String {
+        let unknown = || "unknown".to_string();
+        format!(
+            "{} consumed of {} requested",
+            self.compute_units_consumed
+                .map_or_else(unknown, |units| units.to_string()),
+            self.compute_unit_limit
+                .map_or_else(unknown, |limit| limit.to_string())
+        )
+    }
+}
+
+/// Number of `getTransaction` requests for the fee of a transaction before giving up
+const FEE_LOOKUP_ATTEMPTS: u32 = 5;
+/// Interval between the fee lookups, about a slot
+const FEE_LOOKUP_INTERVAL: Duration = Duration::from_millis(400);
+
+/// FeeLookups looks up the fees charged to landed transactions on tasks of their own, so a listener
+/// of the tracker never waits on the RPC node and falls behind the events. Items come out of the
+/// receiver once the fee of their transaction is known, or known to be unavailable
+pub struct FeeLookups<T> {
+    rpc_client: Arc<RpcClient>,
+    commitment: CommitmentConfig,
+    /// Tag of the listener in the logs
+    tag: &'static str,
+    resolved: mpsc::UnboundedSender<(T, Option<u64>)>,
+}
+
+impl<T: Send + 'static> FeeLookups<T> {
+    /// new returns the lookups and the receiver of the resolved items. The receiver ends once the
+    /// lookups are dropped and the pending ones resolved
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        commitment: CommitmentConfig,
+        tag: &'static str,
+    ) -> (Self, mpsc::UnboundedReceiver<(T, Option<u64>)>) {
+        let (resolved, receiver) = mpsc::unbounded_channel();
+        let lookups = Self {
+            rpc_client,
+            commitment,
+            tag,
+            resolved,
+        };
+        (lookups, receiver)
+    }
+
+    /// lookup passes the item on with the fee paid by the landed transaction, right away without
+    /// a fee if there's no transaction to look up
+    pub fn lookup(&self, landed: Option<Signature>, item: T) {
+        let Some(signature) = landed else {
+            let _ = self.resolved.send((item, None));
+            return;
+        };
+        let rpc_client = self.rpc_client.clone();
+        let commitment = self.commitment;
+        let tag = self.tag;
+        let resolved = self.resolved.clone();
+        tokio::spawn(async move {
+            let fee = match fetch_paid_fee(&rpc_client, &signature, commitment).await {
+                Ok(paid_fee) => Some(paid_fee.fee),
+                Err(e) => {
+                    warn!("[ {} ] - {}, recorded without the fee", tag, e);
+                    None
+                }
+            };
+            let _ = resolved.send((item, fee));
+        });
+    }
+}
+
+/// fetch_paid_fee reads the fee charged to a landed transaction and the compute budget it
+/// requested. The transaction is looked up at the commitment it was confirmed at, retrying for a
+/// few slots while the node doesn't serve it yet
+pub async fn fetch_paid_fee(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Result<PaidFee, String> {
+    // getTransaction doesn't serve processed transactions
+    let commitment = match commitment.commitment {
+        CommitmentLevel::Finalized => CommitmentConfig::finalized(),
+        _ => CommitmentConfig::confirmed(),
+    };
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(commitment),
+        max_supported_transaction_version: Some(0),
+    };
+    let mut attempt = 1;
+    let transaction = loop {
+        match rpc_client
+            .get_transaction_with_config(signature, config)
+            .await
+        {
+            Ok(transaction) => break transaction.transaction,
+            Err(_) if attempt < FEE_LOOKUP_ATTEMPTS => {
+                attempt += 1;
+                sleep(FEE_LOOKUP_INTERVAL).await;
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Failed to get transaction {} after {} attempts: {}",
+                    signature, attempt, e
+                ))
+            }
+        }
+    };
+    let meta = transaction
+        .meta
+        .ok_or_else(|| format!("Transaction {} has no meta", signature))?;
+    let decoded = transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| format!("Failed to decode transaction {}", signature))?;
+
+    let mut compute_unit_price = None;
+    let mut compute_unit_limit = None;
+    let account_keys = decoded.message.static_account_keys();
+    for instruction in decoded.message.instructions() {
+        if *instruction.program_id(account_keys) != compute_budget::id() {
+            continue;
+        }
+        match try_from_slice_unchecked(&instruction.data) {
+            Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => {
+                compute_unit_price = Some(price)
+            }
+            Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => {
+                compute_unit_limit = Some(limit)
+            }
+            _ => {}
+        }
+    }
+
+    let base_fee = FeeStructure::default().lamports_per_signature
+        * decoded.message.header().num_required_signatures as u64;
+    Ok(PaidFee {
+        fee: meta.fee,
+        priority_fee: meta.fee.saturating_sub(base_fee),
+        compute_unit_price,
+        compute_unit_limit,
+        compute_units_consumed: meta.compute_units_consumed.into(),
+    })
+}
+
+/// SendOutcome describes a confirmed transaction
+#[derive(Debug, Clone)]
+pub struct SendOutcome {
+    pub request_id: RequestId,
+    pub signature: Signature,
+    /// Slot the transaction landed in
+    pub slot: Slot,
+    /// Slot the transaction was finalized in, with `--wait-finalized`
+    pub finalized_slot: Option<Slot>,
+    /// Time from sending the transaction to its confirmation
+    pub confirmation_latency: Duration,
+    /// What the transaction was charged, if its meta could be fetched
+    pub paid_fee: Option<PaidFee>,
+    pub delivery_path: DeliveryPath,
+    /// Number of send attempts it took
+    pub attempts: u8,
+}
+
+impl SendOutcome {
+    /// fetch completes the outcome of a transaction confirmed at the commitment with the fee it
+    /// was charged
+    pub async fn fetch(
+        rpc_client: &RpcClient,
+        commitment: CommitmentConfig,
+        request_id: RequestId,
+        signature: Signature,
+        confirmation: Confirmation,
+        delivery_path: DeliveryPath,
+        attempts: u8,
+    ) -> Self {
+        let paid_fee = match fetch_paid_fee(rpc_client, &signature, commitment).await {
+            Ok(paid_fee) => Some(paid_fee),
+            Err(e) => {
+                error!("{}, fee unknown", e);
+                None
+            }
+        };
+
+        Self {
+            request_id,
+            signature,
+            slot: confirmation.slot,
+            finalized_slot: None,
+            confirmation_latency: confirmation.elapsed,
+            paid_fee,
+            delivery_path,
+            attempts,
+        }
+    }
+
+    /// log prints the outcome summary with the explorer link
+    pub fn log(&self, config: &Config) {
+        info!(
+            "[ OUTCOME\n\tREQUEST: {}\n\tSIGNATURE: {}\n\tSLOT: {}\n\tFINALIZED: {}\n\tCONFIRMATION LATENCY: {:?}\n\tFEE: {}\n\tCOMPUTE UNITS: {}\n\tDELIVERY: {}\n\tATTEMPTS: {}\n]",
+            self.request_id,
+            self.signature,
+            self.slot,
+            self.finalized_slot
+                .map_or_else(|| "not awaited".to_string(), |slot| format!("slot {}", slot)),
+            self.confirmation_latency,
+            self.paid_fee
+                .as_ref()
+                .map_or_else(|| "unknown".to_string(), PaidFee::describe_fee),
+            self.paid_fee
+                .as_ref()
+                .map_or_else(|| "unknown".to_string(), PaidFee::describe_compute_units),
+            self.delivery_path,
+            self.attempts
+        );
+        info!("{}", config.generate_url(&self.signature.to_string()));
+    }
+
+    /// to_json returns the outcome as the final JSON object of `--output json`
+    pub fn to_json(&self, config: &Config, total: Duration) -> Value {
+        json!({
+            "request_id": self.request_id.to_string(),
+            "signature": self.signature.to_string(),
+            "status": if self.finalized_slot.is_some() { "finalized" } else { "confirmed" },
+            "slot": self.slot,
+            "finalized_slot": self.finalized_slot,
+            "fee": self.paid_fee.as_ref().map(|paid_fee| paid_fee.fee),
+            "priority_fee": self.paid_fee.as_ref().map(|paid_fee| paid_fee.priority_fee),
+            "compute_unit_price": self.paid_fee.as_ref().and_then(|paid_fee| paid_fee.compute_unit_price),
+            "compute_unit_limit": self.paid_fee.as_ref().and_then(|paid_fee| paid_fee.compute_unit_limit),
+            "compute_units_consumed": self.paid_fee.as_ref().and_then(|paid_fee| paid_fee.compute_units_consumed),
+            "explorer_url": config.transaction_url(&self.signature.to_string()),
+            "delivery": self.delivery_path.to_string(),
+            "attempts": self.attempts,
+            "timings": {
+                "confirmation_ms": self.confirmation_latency.as_millis(),
+                "total_ms": total.as_millis(),
+            },
+        })
+    }
+}
+
+/// failure_json returns a failed send as the final JSON object of `--output json`
+pub fn failure_json(request_id: RequestId, error: &SendError, total: Duration) -> Value {
+    json!({
+        "request_id": request_id.to_string(),
+        "signature": null,
+        "status": "failed",
+        "error": error.to_string(),
+        "timings": {
+            "total_ms": total.as_millis(),
+        },
+    })
+}
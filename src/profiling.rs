@@ -0,0 +1,80 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use pprof::{ProfilerGuard, ProfilerGuardBuilder};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info};
+
+/// Stack samples taken per second
+const FREQUENCY: i32 = 99;
+/// Libraries the sampler doesn't unwind through, it may crash in them
+const BLOCKLIST: &[&str] = &["libc", "libgcc", "pthread", "vdso"];
+
+/// flamegraph_on_signal samples the stacks of the process from now on and, every time it receives
+/// SIGUSR2, e.g. from `kill -USR2 <pid>`, writes a flamegraph of the samples since the previous
+/// one to `flamegraph-<unix time>.svg` in the directory. It never returns
+pub async fn flamegraph_on_signal(dir: PathBuf) {
+    let mut signals = match signal(SignalKind::user_defined2()) {
+        Ok(signals) => signals,
+        Err(e) => {
+            error!("[ PROFILING ] - Failed to listen for SIGUSR2: {}", e);
+            return;
+        }
+    };
+    let mut guard = match start() {
+        Ok(guard) => guard,
+        Err(e) => {
+            error!("[ PROFILING ] - {}", e);
+            return;
+        }
+    };
+    info!(
+        "[ PROFILING ] - Sampling at {} Hz, SIGUSR2 writes a flamegraph",
+        FREQUENCY
+    );
+
+    while signals.recv().await.is_some() {
+        match write_flamegraph(&guard, &dir) {
+            Ok(path) => info!("[ PROFILING ] - Flamegraph written to {}", path.display()),
+            Err(e) => error!("[ PROFILING ] - {}", e),
+        }
+        // the samples are kept until the profiler stops, the next flamegraph starts afresh
+        drop(guard);
+        guard = match start() {
+            Ok(guard) => guard,
+            Err(e) => {
+                error!("[ PROFILING ] - {}", e);
+                return;
+            }
+        };
+    }
+}
+
+fn start() -> Result<ProfilerGuard<'static>, String> {
+    ProfilerGuardBuilder::default()
+        .frequency(FREQUENCY)
+        .blocklist(BLOCKLIST)
+        .build()
+        .map_err(|e| format!("Failed to start the profiler: {}", e))
+}
+
+fn write_flamegraph(guard: &ProfilerGuard<'static>, dir: &Path) -> Result<PathBuf, String> {
+    let report = guard
+        .report()
+        .build()
+        .map_err(|e| format!("Failed to build the profile: {}", e))?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("flamegraph-{}.svg", timestamp));
+    let file =
+        File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    report
+        .flamegraph(file)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::sync::broadcast;
+use tracing::error;
+
+use crate::tx_tracker::{TxEvent, TxState};
+
+const TEMPLATE: &str = "{spinner} [{elapsed_precise}] {wide_bar} {pos}/{len} {msg} ETA {eta}";
+
+/// progress_bar returns the bar drawn on stderr, its length grows with the transactions built
+pub fn progress_bar() -> ProgressBar {
+    let bar = ProgressBar::new(0);
+    bar.set_style(ProgressStyle::with_template(TEMPLATE).expect("valid progress bar template"));
+    bar.enable_steady_tick(Duration::from_millis(200));
+    bar
+}
+
+/// run moves the bar forward as the tracked transactions reach a final state, with the counts of
+/// confirmed, failed and pending ones, until the tracker is dropped
+pub async fn run(bar: ProgressBar, mut events: broadcast::Receiver<TxEvent>) {
+    let (mut built, mut confirmed, mut failed) = (0u64, 0u64, 0u64);
+    loop {
+        let state = match events.recv().await {
+            Ok(TxEvent::Transition { state, .. }) => state,
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        match state {
+            TxState::Built => {
+                built += 1;
+                bar.inc_length(1);
+            }
+            TxState::Sent { .. } => continue,
+            TxState::Confirmed { .. } => {
+                confirmed += 1;
+                bar.inc(1);
+            }
+            TxState::Failed { .. } | TxState::Expired => {
+                failed += 1;
+                bar.inc(1);
+            }
+        }
+        bar.set_message(format!(
+            "confirmed {}, failed {}, pending {}",
+            confirmed,
+            failed,
+            built.saturating_sub(confirmed + failed)
+        ));
+    }
+    bar.finish();
+}
+
+/// print_signatures prints the signature of every transaction confirmed, one per line on stdout,
+/// until the tracker is dropped
+pub async fn print_signatures(mut events: broadcast::Receiver<TxEvent>) {
+    loop {
+        match events.recv().await {
+            Ok(TxEvent::Transition {
+                signature,
+                state: TxState::Confirmed { .. },
+                ..
+            }) => println!("{}", signature),
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                error!("[ PROGRESS ] - {} events skipped", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    fmt,
+    io::{BufRead, Write},
+};
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::amount;
+
+/// SendSummary is what a send is about to sign, shown for confirmation before mainnet sends
+#[derive(Debug, Clone)]
+pub struct SendSummary {
+    pub network: &'static str,
+    pub sender: Pubkey,
+    /// Receiver of the transfer, or a description of the receivers
+    pub receiver: String,
+    /// Amount transferred, with its unit
+    pub amount: String,
+    /// Expected fee of a transaction in lamports, base and priority fee
+    pub fee: u64,
+}
+
+impl fmt::Display for SendSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[ {} SEND\n\tFROM: {}\n\tTO: {}\n\tAMOUNT: {}\n\tFEE: {} SOL per transaction\n]",
+            self.network.to_uppercase(),
+            self.sender,
+            self.receiver,
+            self.amount,
+            amount::format_sol(self.fee)
+        )
+    }
+}
+
+/// confirm shows the summary on `output` and asks whether to sign and send, only `y` or `yes`
+/// answered on `input` proceeds
+pub fn confirm(
+    summary: &SendSummary,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<bool, String> {
+    write!(output, "{}\nSign and send? [y/N] ", summary)
+        .and_then(|_| output.flush())
+        .map_err(|e| format!("Failed to show the confirmation prompt: {}", e))?;
+    let mut answer = String::new();
+    input
+        .read_line(&mut answer)
+        .map_err(|e| format!("Failed to read the confirmation: {}", e))?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> SendSummary {
+        SendSummary {
+            network: "mainnet",
+            sender: Pubkey::new_from_array([1; 32]),
+            receiver: Pubkey::new_from_array([2; 32]).to_string(),
+            amount: format!("{} SOL", amount::format_sol(50_000_000)),
+            fee: 5_500,
+        }
+    }
+
+    #[test]
+    fn only_yes_confirms() {
+        for (answer, confirmed) in [
+            ("y\n", true),
+            ("YES\n", true),
+            ("\n", false),
+            ("n\n", false),
+            ("yep\n", false),
+            ("", false),
+        ] {
+            let mut output = Vec::new();
+            let result = confirm(&summary(), &mut answer.as_bytes(), &mut output).unwrap();
+            assert_eq!(result, confirmed, "answer {:?}", answer);
+        }
+    }
+
+    #[test]
+    fn prompt_shows_receiver_amount_and_fee() {
+        let mut output = Vec::new();
+        confirm(&summary(), &mut "n\n".as_bytes(), &mut output).unwrap();
+
+        let prompt = String::from_utf8(output).unwrap();
+        assert!(prompt.starts_with("[ MAINNET SEND"));
+        assert!(prompt.contains(&Pubkey::new_from_array([2; 32]).to_string()));
+        assert!(prompt.contains("AMOUNT: 0.050000000 SOL"));
+        assert!(prompt.contains("FEE: 0.000005500 SOL per transaction"));
+        assert!(prompt.ends_with("Sign and send? [y/N] "));
+    }
+}
@@ -1,50 +1,210 @@
-use solana_client::{
-    nonblocking::{
-        quic_client::{QuicLazyInitializedEndpoint, QuicTpuConnection},
-        rpc_client::RpcClient,
-        tpu_connection::TpuConnection,
-    },
-    tpu_connection::ClientStats,
+use dashmap::DashMap;
+use solana_client::nonblocking::{
+    quic_client::{QuicLazyInitializedEndpoint, QuicTpuConnection},
+    rpc_client::RpcClient,
+    tpu_connection::TpuConnection,
 };
 use solana_connection_cache::connection_cache_stats::ConnectionCacheStats;
-use solana_sdk::signature::Signature;
-use std::{net::SocketAddr, sync::Arc};
-use tracing::{error, info};
+use solana_sdk::{
+    clock::{Slot, DEFAULT_MS_PER_SLOT},
+    commitment_config::CommitmentConfig,
+    signature::Signature,
+    transaction::Transaction,
+};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, LazyLock,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{error, field, info, info_span, instrument, Instrument, Span};
+
+use crate::{
+    chaos,
+    config::Config,
+    error::SendError,
+    leader_tracker::LeaderTracker,
+    outcome::DeliveryPath,
+    recording,
+    rpc_api::RpcApi,
+    simulation,
+    tx_builder::{self, PresignedTransaction},
+    tx_tracker::TxTracker,
+};
+
+/// Statistics of every QUIC connection of the process
+static CONNECTION_STATS: LazyLock<Arc<ConnectionCacheStats>> = LazyLock::new(Default::default);
+
+/// QUIC endpoint of every connection of the process, one UDP socket and one client certificate.
+/// It binds on the first connection
+static ENDPOINT: LazyLock<Arc<QuicLazyInitializedEndpoint>> = LazyLock::new(Default::default);
+
+/// Connections to the leaders shared by the sender workers of the process
+static CONNECTION_POOL: LazyLock<ConnectionPool> = LazyLock::new(Default::default);
 
-use crate::config::Config;
+/// connection_stats returns the statistics the QUIC connections to the leaders add up to
+pub fn connection_stats() -> Arc<ConnectionCacheStats> {
+    CONNECTION_STATS.clone()
+}
+
+/// connection_pool returns the pool the sender workers of the process share, listed in the debug
+/// dump
+pub fn connection_pool() -> ConnectionPool {
+    CONNECTION_POOL.clone()
+}
+
+/// Connection to a leader and whether its handshake is done
+type Connection = (Arc<QuicTpuConnection>, Arc<AtomicBool>);
+
+/// ConnectionPool keeps one QUIC connection per leader TPU address, shared by every sender holding
+/// a clone, so the sends to a leader share its handshake
+#[derive(Clone, Default)]
+pub struct ConnectionPool {
+    connections: Arc<DashMap<SocketAddr, Connection>>,
+}
+
+impl ConnectionPool {
+    /// manager returns a manager sending over the pooled connection to the address, connecting
+    /// it on first use
+    pub fn manager(&self, rpc_client: Arc<RpcClient>, socket_addr: SocketAddr) -> QuicManager {
+        let (connection, connected) = self
+            .connections
+            .entry(socket_addr)
+            .or_insert_with(|| (connect(socket_addr), Arc::default()))
+            .clone();
+        QuicManager::with_connection(rpc_client, socket_addr, connection, connected)
+    }
+
+    /// handshakes returns the pooled leader addresses with whether the handshake of their
+    /// connection is done, ordered by address
+    pub fn handshakes(&self) -> Vec<(SocketAddr, bool)> {
+        let mut handshakes: Vec<_> = self
+            .connections
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().1.load(Ordering::Relaxed)))
+            .collect();
+        handshakes.sort();
+        handshakes
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+}
+
+fn connect(socket_addr: SocketAddr) -> Arc<QuicTpuConnection> {
+    Arc::new(QuicTpuConnection::new(
+        ENDPOINT.clone(),
+        socket_addr,
+        connection_stats(),
+    ))
+}
 
 pub struct QuicManager {
     pub connection: Arc<QuicTpuConnection>,
-    pub stats: Arc<ClientStats>,
     pub rpc_client: Arc<RpcClient>,
+    /// Slot seen by the leader tracker, the RPC node must have caught up with it when handing out
+    /// the blockhash
+    pub tracker_slot: Option<Slot>,
+    /// Identity of the targeted leader, if known
+    pub leader: Option<String>,
+    pub tpu_quic: SocketAddr,
+    /// Whether the handshake of the connection is done, shared with the pooled copies
+    connected: Arc<AtomicBool>,
 }
 
 impl QuicManager {
     pub async fn new(rpc_client: Arc<RpcClient>, socket_addr: SocketAddr) -> Self {
-        let endpoint = Arc::new(QuicLazyInitializedEndpoint::default());
-        let stats = Arc::new(ClientStats::default());
-        let connection_stats = Arc::new(ConnectionCacheStats::default());
-
-        let quic_tpu_connection = QuicTpuConnection::new(endpoint, socket_addr, connection_stats);
+        Self::with_connection(
+            rpc_client,
+            socket_addr,
+            connect(socket_addr),
+            Arc::default(),
+        )
+    }
 
+    fn with_connection(
+        rpc_client: Arc<RpcClient>,
+        socket_addr: SocketAddr,
+        connection: Arc<QuicTpuConnection>,
+        connected: Arc<AtomicBool>,
+    ) -> Self {
         QuicManager {
-            connection: Arc::new(quic_tpu_connection),
-            stats,
+            connection,
             rpc_client,
+            tracker_slot: None,
+            leader: None,
+            tpu_quic: socket_addr,
+            connected,
         }
     }
 
-    pub async fn send_transaction(&self, config: &Config) -> Result<Signature, String> {
+    /// for_current_leader connects to the TPU QUIC address of the upcoming leader
+    pub async fn for_current_leader(
+        rpc_client: Arc<RpcClient>,
+        tracker: &impl LeaderTracker,
+    ) -> Result<Self, String> {
+        Self::for_current_leader_in(rpc_client, tracker, None).await
+    }
+
+    /// for_current_leader_in is `for_current_leader` reusing the connection to the leader from the
+    /// pool, if any
+    #[instrument(name = "leader_lookup", skip_all)]
+    pub async fn for_current_leader_in(
+        rpc_client: Arc<RpcClient>,
+        tracker: &impl LeaderTracker,
+        pool: Option<&ConnectionPool>,
+    ) -> Result<Self, String> {
+        let leaders = tracker.get_leaders();
+        // берем первого лидера из списка с учетом смещения
+        let leader = leaders
+            .last()
+            .ok_or("No current leader available. Searching...")?;
+        info!("LEADER: {:#?}", leader);
+
+        let tpu_quic = leader
+            .tpu_quic
+            .ok_or("No QUIC address available for the current leader.")?;
+        info!("QUIC: {:#?}", tpu_quic);
+
+        let mut manager = match pool {
+            Some(pool) => pool.manager(rpc_client, tpu_quic),
+            None => Self::new(rpc_client, tpu_quic).await,
+        };
+        manager.tracker_slot = Some(tracker.current_slot()).filter(|slot| *slot > 0);
+        manager.leader = Some(leader.pubkey.clone());
+        Ok(manager)
+    }
+
+    /// send_transaction builds, signs and sends the transaction described by the config, returning
+    /// it with the last block height its blockhash is valid for
+    pub async fn send_transaction(
+        &self,
+        config: &Config,
+        tx_tracker: &TxTracker,
+    ) -> Result<(Transaction, u64), SendError> {
         let max_attempts = 1; // Увеличение числа попыток
         for attempt in 0..max_attempts {
-            let blockhash = self
-                .rpc_client
-                .get_latest_blockhash()
-                .await
-                .map_err(|e| format!("Failed to get blockhash: {}", e))?;
-            info!("[ BLOCKHASH ] - {:#?}", blockhash);
+            let (transaction, recent_blockhash) =
+                tx_builder::build_transaction(&self.rpc_client, config, self.tracker_slot).await?;
+            tx_tracker.blockhash_fetched(&recent_blockhash);
+            let last_valid_block_height = recent_blockhash.last_valid_block_height;
+            tx_tracker.built(transaction.signatures[0]);
 
-            let transaction = config.create_transaction(blockhash);
+            if config.simulate {
+                simulation::simulate_transaction(
+                    self.rpc_client.as_ref(),
+                    &transaction,
+                    config.force,
+                )
+                .await?;
+            }
 
             info!(
             "[ TRANSACTION\n\tSENDER: {:?}\n\tRECEIVER: {:?}\n\tBLOCKHASH: {:?}\n\tSIGNATURE: {:?}\n]",
@@ -54,37 +214,11 @@ impl QuicManager {
             transaction.signatures
         );
 
-            let serialized_tx = bincode::serialize(&transaction).unwrap();
-
-            let send_result = tokio::time::timeout(
-                std::time::Duration::from_secs(60), // Увеличение таймаута до 60 секунд
-                self.connection.send_data(&serialized_tx),
-            )
-            .await;
-
-            match send_result {
-                Ok(Ok(_)) => {
-                    if let Some(signature) = transaction.signatures.first() {
-                        return Ok(*signature);
-                    } else {
-                        return Err("No signature found in the transaction".to_string());
-                    }
-                }
-                Ok(Err(e)) => {
-                    error!(
-                        "Attempt {}: Failed to send transaction via QUIC: {:#?}",
-                        attempt + 1,
-                        e
-                    );
-                    if attempt + 1 < max_attempts {
-                        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                    }
-                }
-                Err(_) => {
-                    error!(
-                        "Attempt {}: Timed out while sending transaction via QUIC",
-                        attempt + 1
-                    );
+            config.audit(&transaction)?;
+            match self.send_signed_transaction(&transaction, tx_tracker).await {
+                Ok(_) => return Ok((transaction, last_valid_block_height)),
+                Err(e) => {
+                    error!("Attempt {}: {}", attempt + 1, e);
                     if attempt + 1 < max_attempts {
                         tokio::time::sleep(std::time::Duration::from_secs(2)).await;
                     }
@@ -92,38 +226,173 @@ impl QuicManager {
             }
         }
 
-        Err("Failed to send transaction via QUIC after multiple attempts".to_string())
+        Err(
+            "Failed to send transaction via QUIC after multiple attempts"
+                .to_string()
+                .into(),
+        )
+    }
+
+    pub fn delivery_path(&self) -> DeliveryPath {
+        DeliveryPath::Quic {
+            leader: self.leader.clone().unwrap_or_else(|| "unknown".to_string()),
+            tpu_quic: self.tpu_quic,
+        }
     }
 
-    pub async fn check_confirm_transaction(&self, signature: &Signature) -> Result<bool, String> {
-        let transaction_with_meta = self
-            .rpc_client
-            .get_transaction(
+    /// handshake opens the QUIC connection to the leader unless it's already open. The client
+    /// connects lazily, an empty buffer connects it without sending a packet, like the connection
+    /// cache warmer of the validators does
+    pub async fn handshake(&self) -> solana_sdk::transport::Result<()> {
+        if self.connected.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.connection
+            .send_data(&[])
+            .instrument(info_span!("quic_handshake"))
+            .await?;
+        self.connected.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// send_signed_transaction delivers an already signed transaction to the leader
+    #[instrument(
+        name = "send",
+        skip_all,
+        fields(signature = field::Empty, leader = self.leader.as_deref(), tpu_quic = %self.tpu_quic)
+    )]
+    pub async fn send_signed_transaction(
+        &self,
+        transaction: &Transaction,
+        tx_tracker: &TxTracker,
+    ) -> Result<Signature, SendError> {
+        let signature = *transaction
+            .signatures
+            .first()
+            .ok_or_else(|| "No signature found in the transaction".to_string())?;
+        Span::current().record("signature", field::display(signature));
+        let serialized_tx = tx_tracker.serialized(transaction)?;
+        self.send_serialized(signature, &serialized_tx, tx_tracker)
+            .await
+    }
+
+    /// send_presigned_transaction delivers a transaction signed and serialized ahead of time, the
+    /// send is the write of its bytes
+    #[instrument(
+        name = "send",
+        skip_all,
+        fields(signature = %presigned.signature(), leader = self.leader.as_deref(), tpu_quic = %self.tpu_quic)
+    )]
+    pub async fn send_presigned_transaction(
+        &self,
+        presigned: &PresignedTransaction,
+        tx_tracker: &TxTracker,
+    ) -> Result<Signature, SendError> {
+        self.send_serialized(presigned.signature(), &presigned.serialized, tx_tracker)
+            .await
+    }
+
+    async fn send_serialized(
+        &self,
+        signature: Signature,
+        serialized_tx: &[u8],
+        tx_tracker: &TxTracker,
+    ) -> Result<Signature, SendError> {
+        let started = Instant::now();
+        let replay = recording::is_replay();
+        if replay || chaos::faults().drops_quic_send() {
+            match replay {
+                true => info!("[ REPLAY ] - Skipped the send of {}", signature),
+                false => info!("[ CHAOS ] - Dropped the send of {}", signature),
+            }
+            tx_tracker.sent(
                 signature,
-                solana_transaction_status::UiTransactionEncoding::Json,
-            )
-            .await;
-        info!("META {:#?}", transaction_with_meta);
-
-        let max_attempts = 10;
-        for _ in 0..max_attempts {
-            let statuses = self
-                .rpc_client
-                .get_signature_statuses(&[*signature])
-                .await
-                .map_err(|e| format!("Failed to get signature statuses: {}", e))?;
+                self.leader.clone().unwrap_or_default(),
+                self.tracker_slot,
+                started.elapsed(),
+            );
+            return Ok(signature);
+        }
+        let send_result = tokio::time::timeout(
+            std::time::Duration::from_secs(60), // Увеличение таймаута до 60 секунд
+            async {
+                self.handshake().await?;
+                self.connection.send_data(serialized_tx).await
+            },
+        )
+        .await;
 
-            if let Some(Some(status)) = statuses.value.first() {
-                if status.confirmations.is_some() {
-                    return Ok(true);
-                } else {
-                    info!("Transaction not confirmed yet, retrying...");
-                }
+        let result = match send_result {
+            Ok(Ok(_)) => {
+                tx_tracker.sent(
+                    signature,
+                    self.leader.clone().unwrap_or_default(),
+                    self.tracker_slot,
+                    started.elapsed(),
+                );
+                return Ok(signature);
             }
+            Ok(Err(e)) => format!("Failed to send transaction via QUIC: {:#?}", e),
+            Err(_) => "Timed out while sending transaction via QUIC".to_string(),
+        };
+        tx_tracker.send_failed(
+            signature,
+            self.leader.clone().unwrap_or_default(),
+            result.clone(),
+        );
+        Err(result.into())
+    }
+}
 
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+/// resubmit resends the identical signed transaction to the then-current leader whenever it went
+/// `after_slots` slots without confirming, as long as its blockhash is valid. Resending the same
+/// bytes can't land the transfer twice. It never returns and is meant to be raced against the
+/// confirmation
+pub async fn resubmit(
+    rpc_client: Arc<RpcClient>,
+    tracker: &impl LeaderTracker,
+    tx_tracker: &TxTracker,
+    transaction: &Transaction,
+    after_slots: u64,
+) {
+    let processed = CommitmentConfig::processed();
+    let rpc: &dyn RpcApi = rpc_client.as_ref();
+    let mut sent_slot = rpc.get_slot(processed).await.unwrap_or_default();
+    loop {
+        tokio::time::sleep(Duration::from_millis(DEFAULT_MS_PER_SLOT)).await;
+        let slot = match rpc.get_slot(processed).await {
+            Ok(slot) => slot,
+            Err(e) => {
+                error!("Failed to get slot: {}", e);
+                continue;
+            }
+        };
+        if slot < sent_slot + after_slots {
+            continue;
         }
+        sent_slot = slot;
 
-        Err("Transaction failed to confirm".to_string())
+        // past its blockhash the transaction can't land anymore, the confirmation reports it
+        if let Ok(false) = rpc
+            .is_blockhash_valid(&transaction.message.recent_blockhash, processed)
+            .await
+        {
+            break;
+        }
+        let result = match QuicManager::for_current_leader(rpc_client.clone(), tracker).await {
+            Ok(manager) => manager
+                .send_signed_transaction(transaction, tx_tracker)
+                .await
+                .map(|_| manager.leader.unwrap_or_default()),
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(leader) => info!(
+                "[ RESUBMIT ] - {} unconfirmed after {} slots, resent to {}",
+                transaction.signatures[0], after_slots, leader
+            ),
+            Err(e) => error!("Failed to resubmit transaction: {}", e),
+        }
     }
+    std::future::pending().await
 }
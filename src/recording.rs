@@ -0,0 +1,374 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, PoisonError},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_client::{
+    client_error::Result as ClientResult,
+    rpc_request::{RpcError, RpcRequest},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_sdk::clock::Slot;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+/// Recording or replay of the process, none unless started
+static SESSION: OnceLock<Session> = OnceLock::new();
+
+/// Traffic is where the RPC responses and the WebSocket slots come from
+#[derive(Debug, Clone, Default)]
+pub enum Traffic {
+    /// From the cluster
+    #[default]
+    Live,
+    /// From the cluster, and written to the file
+    Record(PathBuf),
+    /// From a file written by an earlier run, nothing is sent to the cluster
+    Replay(PathBuf),
+}
+
+/// Entry is one line of a recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry {
+    Rpc {
+        /// Time since the start of the recording, in milliseconds
+        at_ms: u64,
+        method: String,
+        params: Value,
+        #[serde(default)]
+        result: Value,
+        /// Error of the request instead of a result
+        #[serde(default)]
+        error: Option<String>,
+    },
+    Slot {
+        at_ms: u64,
+        slot: Slot,
+    },
+}
+
+enum Session {
+    Record(Recorder),
+    Replay(Recording),
+}
+
+/// start records the traffic of the rest of the process to a file, or replays it from one. Only
+/// the first call has an effect
+pub fn start(traffic: &Traffic) -> Result<(), String> {
+    let session = match traffic {
+        Traffic::Live => return Ok(()),
+        Traffic::Record(path) => {
+            info!(
+                "[ RECORDING ] - Recording the traffic to {}",
+                path.display()
+            );
+            Session::Record(Recorder::create(path)?)
+        }
+        Traffic::Replay(path) => {
+            info!(
+                "[ RECORDING ] - Replaying the traffic from {}, nothing is sent",
+                path.display()
+            );
+            Session::Replay(Recording::read(path)?)
+        }
+    };
+    let _ = SESSION.set(session);
+    Ok(())
+}
+
+/// is_replay tells whether the traffic is replayed, the sends must not reach the cluster then
+pub fn is_replay() -> bool {
+    matches!(SESSION.get(), Some(Session::Replay(_)))
+}
+
+/// rpc_sender returns the sender of the RPC requests to the node at the URL, recording them or
+/// answering them from the recording if one was started
+pub fn rpc_sender(url: String) -> Box<dyn RpcSender + Send + Sync> {
+    match SESSION.get() {
+        Some(Session::Record(recorder)) => Box::new(RecordingSender {
+            inner: Box::new(HttpSender::new(url)),
+            recorder,
+        }),
+        Some(Session::Replay(recording)) => Box::new(ReplaySender { recording, url }),
+        None => Box::new(HttpSender::new(url)),
+    }
+}
+
+/// record_slot records a slot update of the WebSocket
+pub fn record_slot(slot: Slot) {
+    if let Some(Session::Record(recorder)) = SESSION.get() {
+        recorder.write(&Entry::Slot {
+            at_ms: recorder.elapsed_ms(),
+            slot,
+        });
+    }
+}
+
+/// replayed_slots returns the recorded slot updates with their time since the start of the
+/// recording, None unless replaying
+pub fn replayed_slots() -> Option<Vec<(Duration, Slot)>> {
+    match SESSION.get() {
+        Some(Session::Replay(recording)) => Some(recording.slots.clone()),
+        _ => None,
+    }
+}
+
+/// Recorder appends the entries to the recording file
+struct Recorder {
+    path: PathBuf,
+    file: Mutex<File>,
+    started: Instant,
+}
+
+impl Recorder {
+    fn create(path: &Path) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            started: Instant::now(),
+        })
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn write(&self, entry: &Entry) {
+        let line = match serde_json::to_string(entry) {
+            Ok(line) => line + "\n",
+            Err(e) => return error!("[ RECORDING ] - Failed to serialize an entry: {}", e),
+        };
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            error!(
+                "[ RECORDING ] - Failed to write {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Answer is the params of a recorded request and its result or error
+type Answer = (Value, Result<Value, String>);
+
+/// Recording holds the entries of a recording file to answer from
+struct Recording {
+    /// Recorded answers of every method in order, the last one is kept to answer the requests
+    /// past the end of the recording
+    rpc: Mutex<HashMap<String, Vec<Answer>>>,
+    slots: Vec<(Duration, Slot)>,
+}
+
+impl Recording {
+    fn read(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let entries = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("{}: line {}: {}", path.display(), index + 1, e))
+            })
+            .collect::<Result<Vec<Entry>, String>>()?;
+        Ok(Self::new(entries))
+    }
+
+    fn new(entries: Vec<Entry>) -> Self {
+        let mut rpc: HashMap<String, Vec<_>> = HashMap::new();
+        let mut slots = Vec::new();
+        for entry in entries {
+            match entry {
+                Entry::Rpc {
+                    method,
+                    params,
+                    result,
+                    error,
+                    ..
+                } => rpc
+                    .entry(method)
+                    .or_default()
+                    .push((params, error.map_or(Ok(result), Err))),
+                Entry::Slot { at_ms, slot } => slots.push((Duration::from_millis(at_ms), slot)),
+            }
+        }
+        Self {
+            rpc: Mutex::new(rpc),
+            slots,
+        }
+    }
+
+    /// answer takes the next recorded answer of the method, the first one with the same params
+    /// if any, since the requests of a replay don't come in exactly the recorded order
+    fn answer(&self, method: &str, params: &Value) -> Option<Result<Value, String>> {
+        let mut rpc = self.rpc.lock().unwrap_or_else(PoisonError::into_inner);
+        let answers = rpc.get_mut(method)?;
+        let index = answers
+            .iter()
+            .position(|(recorded, _)| recorded == params)
+            .unwrap_or(0);
+        match answers.len() {
+            0 => None,
+            1 => Some(answers[0].1.clone()),
+            _ => Some(answers.remove(index).1),
+        }
+    }
+}
+
+/// RecordingSender records every request and its answer, passing it to the inner sender
+struct RecordingSender {
+    inner: Box<dyn RpcSender + Send + Sync>,
+    recorder: &'static Recorder,
+}
+
+#[async_trait]
+impl RpcSender for RecordingSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let result = self.inner.send(request, params.clone()).await;
+        let (value, error) = match &result {
+            Ok(value) => (value.clone(), None),
+            Err(e) => (Value::Null, Some(e.to_string())),
+        };
+        self.recorder.write(&Entry::Rpc {
+            at_ms: self.recorder.elapsed_ms(),
+            method: request.to_string(),
+            params,
+            result: value,
+            error,
+        });
+        result
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+/// ReplaySender answers every request from the recording
+struct ReplaySender {
+    recording: &'static Recording,
+    url: String,
+}
+
+#[async_trait]
+impl RpcSender for ReplaySender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let method = request.to_string();
+        match self.recording.answer(&method, &params) {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(e)) => Err(RpcError::RpcRequestError(e).into()),
+            None => Err(RpcError::RpcRequestError(format!("{} wasn't recorded", method)).into()),
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn rpc(method: &str, params: Value, result: Value) -> Entry {
+        Entry::Rpc {
+            at_ms: 0,
+            method: method.to_string(),
+            params,
+            result,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn answers_follow_the_recorded_order_and_repeat_the_last() {
+        let recording = Recording::new(vec![
+            rpc("getSlot", json!([]), json!(10)),
+            rpc("getSlot", json!([]), json!(11)),
+        ]);
+
+        let answers: Vec<_> = (0..3)
+            .map(|_| recording.answer("getSlot", &json!([])))
+            .collect();
+        assert_eq!(
+            answers,
+            [
+                Some(Ok(json!(10))),
+                Some(Ok(json!(11))),
+                Some(Ok(json!(11)))
+            ]
+        );
+        assert_eq!(recording.answer("getHealth", &json!([])), None);
+    }
+
+    #[test]
+    fn answers_with_the_same_params_come_first() {
+        let recording = Recording::new(vec![
+            rpc("getBalance", json!(["a"]), json!(1)),
+            rpc("getBalance", json!(["b"]), json!(2)),
+            rpc("getBalance", json!(["a"]), json!(3)),
+        ]);
+
+        assert_eq!(
+            recording.answer("getBalance", &json!(["b"])),
+            Some(Ok(json!(2)))
+        );
+        assert_eq!(
+            recording.answer("getBalance", &json!(["c"])),
+            Some(Ok(json!(1)))
+        );
+    }
+
+    #[test]
+    fn recorded_lines_are_replayed() {
+        let path =
+            std::env::temp_dir().join(format!("quic-recording-{}.jsonl", std::process::id()));
+        let recorder = Recorder::create(&path).unwrap();
+        recorder.write(&Entry::Slot {
+            at_ms: 400,
+            slot: 7,
+        });
+        recorder.write(&Entry::Rpc {
+            at_ms: 500,
+            method: "getSlot".to_string(),
+            params: json!([]),
+            result: Value::Null,
+            error: Some("429 Too Many Requests".to_string()),
+        });
+
+        let recording = Recording::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(recording.slots, [(Duration::from_millis(400), 7)]);
+        assert_eq!(
+            recording.answer("getSlot", &json!([])),
+            Some(Err("429 Too Many Requests".to_string()))
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig, signature::Signature};
+use tokio::sync::broadcast;
+use tracing::error;
+
+use crate::{
+    outcome::FeeLookups,
+    tx_tracker::{TxEvent, TxState},
+};
+
+const CSV_HEADER: &str = "request_id,signature,status,target_slot,landed_slot,latency_ms,fee,error";
+
+/// Row is the result of one transaction in the report
+#[derive(Debug, Clone, Serialize)]
+pub struct Row {
+    pub request_id: String,
+    pub signature: String,
+    /// `confirmed`, `failed` or `expired`
+    pub status: &'static str,
+    /// Slot the leader tracker was at when the transaction was first sent
+    pub target_slot: Option<Slot>,
+    pub landed_slot: Option<Slot>,
+    /// Time from the first send to the final state, in milliseconds
+    pub latency_ms: Option<u64>,
+    /// Fee charged in lamports
+    pub fee: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl Row {
+    fn to_csv(&self) -> String {
+        let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        format!(
+            "{},{},{},{},{},{},{},{}",
+            self.request_id,
+            self.signature,
+            self.status,
+            optional(self.target_slot),
+            optional(self.landed_slot),
+            optional(self.latency_ms),
+            optional(self.fee),
+            csv_field(self.error.as_deref().unwrap_or_default())
+        )
+    }
+}
+
+/// Report writes one row per transaction reaching a final state, as CSV if the path ends with
+/// `.csv` and as JSON lines otherwise
+pub struct Report {
+    path: PathBuf,
+    csv: bool,
+    writer: BufWriter<File>,
+}
+
+impl Report {
+    /// create truncates the file at the path and writes the CSV header if needed
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut report = Self {
+            path: path.to_path_buf(),
+            csv: path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("csv")),
+            writer: BufWriter::new(file),
+        };
+        if report.csv {
+            report.write_line(CSV_HEADER)?;
+        }
+        Ok(report)
+    }
+
+    pub fn write(&mut self, row: &Row) -> Result<(), String> {
+        let line = match self.csv {
+            true => row.to_csv(),
+            false => serde_json::to_string(row)
+                .map_err(|e| format!("Failed to serialize report row: {}", e))?,
+        };
+        self.write_line(&line)
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+
+    /// record writes the row of every transaction reaching a final state until the tracker is
+    /// dropped
+    pub async fn record(
+        mut self,
+        rpc_client: Arc<RpcClient>,
+        commitment: CommitmentConfig,
+        mut events: broadcast::Receiver<TxEvent>,
+    ) {
+        let (fees, mut resolved) = FeeLookups::new(rpc_client, commitment, "REPORT");
+        let events = async move {
+            // slot of the tracker and time of the first send
+            let mut sent: HashMap<Signature, (Option<Slot>, Instant)> = HashMap::new();
+            loop {
+                let (request_id, signature, state) = match events.recv().await {
+                    Ok(TxEvent::Transition {
+                        request_id,
+                        signature,
+                        state,
+                    }) => (request_id, signature, state),
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        error!("[ REPORT ] - {} events skipped", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                let (status, landed_slot, error) = match state {
+                    TxState::Sent { slot, .. } => {
+                        sent.entry(signature)
+                            .or_insert_with(|| (slot, Instant::now()));
+                        continue;
+                    }
+                    TxState::Built => continue,
+                    TxState::Confirmed { slot } => ("confirmed", Some(slot), None),
+                    TxState::Failed { error } => ("failed", None, Some(error)),
+                    TxState::Expired => ("expired", None, None),
+                };
+                let (target_slot, latency_ms) = match sent.remove(&signature) {
+                    Some((slot, sent_at)) => (slot, Some(sent_at.elapsed().as_millis() as u64)),
+                    None => (None, None),
+                };
+                let row = Row {
+                    request_id: request_id.to_string(),
+                    signature: signature.to_string(),
+                    status,
+                    target_slot,
+                    landed_slot,
+                    latency_ms,
+                    fee: None,
+                    error,
+                };
+                fees.lookup(landed_slot.map(|_| signature), row);
+            }
+        };
+        let writer = async {
+            while let Some((mut row, fee)) = resolved.recv().await {
+                row.fee = fee;
+                if let Err(e) = self.write(&row) {
+                    error!("[ REPORT ] - {}", e);
+                }
+            }
+        };
+        tokio::join!(events, writer);
+    }
+}
+
+/// csv_field quotes the value if it contains a separator, a quote or a line break
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
@@ -0,0 +1,437 @@
+use std::net::SocketAddr;
+#[cfg(any(test, feature = "test-utils"))]
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        PoisonError, RwLock,
+    },
+};
+
+#[cfg(any(test, feature = "test-utils"))]
+use futures_util::future;
+use futures_util::future::BoxFuture;
+use serde_json::json;
+use solana_client::{
+    client_error::Result as ClientResult,
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcContextConfig, RpcSimulateTransactionConfig},
+    rpc_request::RpcRequest,
+    rpc_response::{Response, RpcBlockhash, RpcContactInfo, RpcSimulateTransactionResult},
+};
+#[cfg(any(test, feature = "test-utils"))]
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_response::RpcResponseContext,
+};
+#[cfg(any(test, feature = "test-utils"))]
+use solana_sdk::transaction::TransactionError;
+use solana_sdk::{
+    clock::Slot, commitment_config::CommitmentConfig, hash::Hash, pubkey::Pubkey,
+    signature::Signature, transaction::Transaction,
+};
+use solana_transaction_status::TransactionStatus;
+
+/// RpcApi is the part of the RPC node the leader tracking, the blockhash fetch, the simulation and
+/// the status polls depend on, implemented by `RpcClient` and by `MockRpc` for tests
+pub trait RpcApi: Send + Sync {
+    /// get_slot returns the slot the node reached at the commitment level
+    fn get_slot(&self, commitment: CommitmentConfig) -> BoxFuture<'_, ClientResult<Slot>>;
+
+    /// get_slot_leaders returns the leaders of `limit` slots from `start_slot` on, in order
+    fn get_slot_leaders(
+        &self,
+        start_slot: Slot,
+        limit: u64,
+    ) -> BoxFuture<'_, ClientResult<Vec<Pubkey>>>;
+
+    /// get_cluster_nodes returns the contact info of every node in gossip
+    fn get_cluster_nodes(&self) -> BoxFuture<'_, ClientResult<Vec<RpcContactInfo>>>;
+
+    /// get_latest_blockhash returns the latest blockhash at the commitment level, failing if the
+    /// node hasn't reached `min_context_slot` yet
+    fn get_latest_blockhash(
+        &self,
+        commitment: CommitmentConfig,
+        min_context_slot: Option<Slot>,
+    ) -> BoxFuture<'_, ClientResult<Response<RpcBlockhash>>>;
+
+    /// get_signature_statuses returns the statuses of the signatures from the recent status
+    /// cache, None for the unknown ones
+    fn get_signature_statuses<'a>(
+        &'a self,
+        signatures: &'a [Signature],
+    ) -> BoxFuture<'a, ClientResult<Vec<Option<TransactionStatus>>>>;
+
+    /// simulate_transaction runs the signed transaction against the node's bank without
+    /// submitting it
+    fn simulate_transaction<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> BoxFuture<'a, ClientResult<RpcSimulateTransactionResult>>;
+
+    /// is_blockhash_valid returns whether a transaction on the blockhash can still land
+    fn is_blockhash_valid<'a>(
+        &'a self,
+        blockhash: &'a Hash,
+        commitment: CommitmentConfig,
+    ) -> BoxFuture<'a, ClientResult<bool>>;
+}
+
+impl RpcApi for RpcClient {
+    fn get_slot(&self, commitment: CommitmentConfig) -> BoxFuture<'_, ClientResult<Slot>> {
+        Box::pin(self.get_slot_with_commitment(commitment))
+    }
+
+    fn get_slot_leaders(
+        &self,
+        start_slot: Slot,
+        limit: u64,
+    ) -> BoxFuture<'_, ClientResult<Vec<Pubkey>>> {
+        Box::pin(RpcClient::get_slot_leaders(self, start_slot, limit))
+    }
+
+    fn get_cluster_nodes(&self) -> BoxFuture<'_, ClientResult<Vec<RpcContactInfo>>> {
+        Box::pin(RpcClient::get_cluster_nodes(self))
+    }
+
+    fn get_latest_blockhash(
+        &self,
+        commitment: CommitmentConfig,
+        min_context_slot: Option<Slot>,
+    ) -> BoxFuture<'_, ClientResult<Response<RpcBlockhash>>> {
+        Box::pin(self.send(
+            RpcRequest::GetLatestBlockhash,
+            json!([RpcContextConfig {
+                commitment: Some(commitment),
+                min_context_slot,
+            }]),
+        ))
+    }
+
+    fn get_signature_statuses<'a>(
+        &'a self,
+        signatures: &'a [Signature],
+    ) -> BoxFuture<'a, ClientResult<Vec<Option<TransactionStatus>>>> {
+        Box::pin(async move {
+            RpcClient::get_signature_statuses(self, signatures)
+                .await
+                .map(|response| response.value)
+        })
+    }
+
+    fn simulate_transaction<'a>(
+        &'a self,
+        transaction: &'a Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> BoxFuture<'a, ClientResult<RpcSimulateTransactionResult>> {
+        Box::pin(async move {
+            self.simulate_transaction_with_config(transaction, config)
+                .await
+                .map(|response| response.value)
+        })
+    }
+
+    fn is_blockhash_valid<'a>(
+        &'a self,
+        blockhash: &'a Hash,
+        commitment: CommitmentConfig,
+    ) -> BoxFuture<'a, ClientResult<bool>> {
+        Box::pin(RpcClient::is_blockhash_valid(self, blockhash, commitment))
+    }
+}
+
+/// MockRpc is an in-memory RPC node answering from the state the test set up. Every call fails
+/// while it's set to fail. Simulations succeed unless an error is set, only the blockhash set is
+/// valid
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Debug, Default)]
+pub struct MockRpc {
+    slot: AtomicU64,
+    leaders: RwLock<BTreeMap<Slot, Pubkey>>,
+    nodes: RwLock<Vec<RpcContactInfo>>,
+    blockhash: RwLock<(Hash, u64)>,
+    statuses: RwLock<HashMap<Signature, TransactionStatus>>,
+    simulation_error: RwLock<Option<TransactionError>>,
+    failing: AtomicBool,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl MockRpc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_slot(&self, slot: Slot) {
+        self.slot.store(slot, Ordering::Relaxed);
+    }
+
+    /// set_leader_schedule assigns the leaders to the consecutive slots from `first_slot` on
+    pub fn set_leader_schedule(&self, first_slot: Slot, leaders: &[Pubkey]) {
+        let mut schedule = self.leaders.write().unwrap_or_else(PoisonError::into_inner);
+        schedule.extend((first_slot..).zip(leaders.iter().copied()));
+    }
+
+    /// add_node adds a node with the TPU QUIC address to the gossip table
+    pub fn add_node(&self, pubkey: &Pubkey, tpu_quic: SocketAddr) {
+        self.nodes
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(contact_info(pubkey, tpu_quic));
+    }
+
+    /// add_nodes adds the entries to the gossip table
+    pub fn add_nodes(&self, nodes: &[RpcContactInfo]) {
+        self.nodes
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend_from_slice(nodes);
+    }
+
+    pub fn set_blockhash(&self, blockhash: Hash, last_valid_block_height: u64) {
+        *self
+            .blockhash
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = (blockhash, last_valid_block_height);
+    }
+
+    pub fn set_status(&self, signature: Signature, status: TransactionStatus) {
+        self.statuses
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(signature, status);
+    }
+
+    /// set_simulation_error fails the simulations with the error, or lets them succeed if None
+    pub fn set_simulation_error(&self, error: Option<TransactionError>) {
+        *self
+            .simulation_error
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = error;
+    }
+
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::Relaxed);
+    }
+
+    fn respond<T: Send + 'static>(
+        &self,
+        value: impl FnOnce() -> Result<T, String>,
+    ) -> BoxFuture<'static, ClientResult<T>> {
+        let result = match self.failing.load(Ordering::Relaxed) {
+            true => Err("Mock RPC node is failing".to_string()),
+            false => value(),
+        };
+        Box::pin(future::ready(
+            result.map_err(|e| ClientError::from(ClientErrorKind::Custom(e))),
+        ))
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl RpcApi for MockRpc {
+    fn get_slot(&self, _commitment: CommitmentConfig) -> BoxFuture<'_, ClientResult<Slot>> {
+        self.respond(|| Ok(self.slot.load(Ordering::Relaxed)))
+    }
+
+    fn get_slot_leaders(
+        &self,
+        start_slot: Slot,
+        limit: u64,
+    ) -> BoxFuture<'_, ClientResult<Vec<Pubkey>>> {
+        self.respond(|| {
+            Ok(self
+                .leaders
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .range(start_slot..start_slot.saturating_add(limit))
+                .map(|(_, leader)| *leader)
+                .collect())
+        })
+    }
+
+    fn get_cluster_nodes(&self) -> BoxFuture<'_, ClientResult<Vec<RpcContactInfo>>> {
+        self.respond(|| {
+            Ok(self
+                .nodes
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone())
+        })
+    }
+
+    fn get_latest_blockhash(
+        &self,
+        _commitment: CommitmentConfig,
+        min_context_slot: Option<Slot>,
+    ) -> BoxFuture<'_, ClientResult<Response<RpcBlockhash>>> {
+        self.respond(|| {
+            let slot = self.slot.load(Ordering::Relaxed);
+            if min_context_slot.is_some_and(|min_slot| slot < min_slot) {
+                return Err(format!(
+                    "Minimum context slot has not been reached, at slot {}",
+                    slot
+                ));
+            }
+            let (blockhash, last_valid_block_height) = *self
+                .blockhash
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
+            Ok(Response {
+                context: RpcResponseContext {
+                    slot,
+                    api_version: None,
+                },
+                value: RpcBlockhash {
+                    blockhash: blockhash.to_string(),
+                    last_valid_block_height,
+                },
+            })
+        })
+    }
+
+    fn get_signature_statuses<'a>(
+        &'a self,
+        signatures: &'a [Signature],
+    ) -> BoxFuture<'a, ClientResult<Vec<Option<TransactionStatus>>>> {
+        self.respond(|| {
+            let statuses = self.statuses.read().unwrap_or_else(PoisonError::into_inner);
+            Ok(signatures
+                .iter()
+                .map(|signature| statuses.get(signature).cloned())
+                .collect())
+        })
+    }
+
+    fn simulate_transaction<'a>(
+        &'a self,
+        _transaction: &'a Transaction,
+        _config: RpcSimulateTransactionConfig,
+    ) -> BoxFuture<'a, ClientResult<RpcSimulateTransactionResult>> {
+        self.respond(|| {
+            Ok(RpcSimulateTransactionResult {
+                err: self
+                    .simulation_error
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone(),
+                logs: None,
+                accounts: None,
+                units_consumed: None,
+                return_data: None,
+                inner_instructions: None,
+            })
+        })
+    }
+
+    fn is_blockhash_valid<'a>(
+        &'a self,
+        blockhash: &'a Hash,
+        _commitment: CommitmentConfig,
+    ) -> BoxFuture<'a, ClientResult<bool>> {
+        self.respond(|| {
+            let (valid, _) = *self
+                .blockhash
+                .read()
+                .unwrap_or_else(PoisonError::into_inner);
+            Ok(*blockhash == valid)
+        })
+    }
+}
+
+/// contact_info returns the gossip entry of a node reachable only over TPU QUIC
+pub fn contact_info(pubkey: &Pubkey, tpu_quic: SocketAddr) -> RpcContactInfo {
+    RpcContactInfo {
+        pubkey: pubkey.to_string(),
+        gossip: None,
+        tpu: None,
+        tpu_quic: Some(tpu_quic),
+        rpc: None,
+        pubsub: None,
+        version: None,
+        feature_set: None,
+        shred_version: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::commitment_config::CommitmentConfig;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn slot_leaders_cover_the_requested_range() {
+        let rpc = MockRpc::new();
+        let leaders: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        rpc.set_leader_schedule(100, &leaders);
+
+        let found = rpc.get_slot_leaders(101, 2).await.unwrap();
+        assert_eq!(found, leaders[1..3]);
+        assert!(rpc.get_slot_leaders(200, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn blockhash_waits_for_the_min_context_slot() {
+        let rpc = MockRpc::new();
+        let blockhash = Hash::new_unique();
+        rpc.set_blockhash(blockhash, 150);
+        rpc.set_slot(10);
+
+        let processed = CommitmentConfig::processed();
+        assert!(rpc.get_latest_blockhash(processed, Some(11)).await.is_err());
+        let response = rpc.get_latest_blockhash(processed, Some(10)).await.unwrap();
+        assert_eq!(response.context.slot, 10);
+        assert_eq!(response.value.blockhash, blockhash.to_string());
+        assert_eq!(response.value.last_valid_block_height, 150);
+    }
+
+    #[tokio::test]
+    async fn statuses_follow_the_order_of_the_signatures() {
+        let rpc = MockRpc::new();
+        let known = Signature::new_unique();
+        rpc.set_status(
+            known,
+            TransactionStatus {
+                slot: 7,
+                confirmations: None,
+                status: Ok(()),
+                err: None,
+                confirmation_status: None,
+            },
+        );
+
+        let statuses = rpc
+            .get_signature_statuses(&[Signature::new_unique(), known])
+            .await
+            .unwrap();
+        assert!(statuses[0].is_none());
+        assert_eq!(statuses[1].as_ref().map(|status| status.slot), Some(7));
+    }
+
+    #[tokio::test]
+    async fn only_the_blockhash_set_is_valid() {
+        let rpc = MockRpc::new();
+        let blockhash = Hash::new_unique();
+        rpc.set_blockhash(blockhash, 150);
+
+        let processed = CommitmentConfig::processed();
+        assert!(rpc.is_blockhash_valid(&blockhash, processed).await.unwrap());
+        assert!(!rpc
+            .is_blockhash_valid(&Hash::new_unique(), processed)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn failing_node_fails_every_call() {
+        let rpc = MockRpc::new();
+        rpc.set_failing(true);
+        assert!(rpc.get_slot(CommitmentConfig::processed()).await.is_err());
+        assert!(rpc.get_cluster_nodes().await.is_err());
+
+        rpc.set_failing(false);
+        assert!(rpc.get_slot(CommitmentConfig::processed()).await.is_ok());
+    }
+}
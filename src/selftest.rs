@@ -0,0 +1,220 @@
+use std::{fmt, future::Future, net::SocketAddr, sync::Arc, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, signer::Signer};
+use tokio::time::{timeout, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    config::{Config, Network},
+    leader_tracker::{LeaderTracker, LeaderTrackerImpl},
+    quic_manager::QuicManager,
+};
+
+/// Time every check is given before it fails
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Check is the outcome of one step of the self-test
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    /// What was found if the check passed, why it failed otherwise
+    pub detail: String,
+    pub elapsed_ms: u64,
+}
+
+impl Check {
+    fn new(name: &'static str, started: Instant, result: Result<String, String>) -> Self {
+        let passed = result.is_ok();
+        Self {
+            name,
+            passed,
+            detail: result.unwrap_or_else(|e| e),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// SelftestReport holds the checks of a deployment in the order they ran
+#[derive(Debug, Clone, Serialize)]
+pub struct SelftestReport {
+    pub network: &'static str,
+    pub checks: Vec<Check>,
+}
+
+impl SelftestReport {
+    /// passed reports whether every check passed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+}
+
+impl fmt::Display for SelftestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[ SELFTEST - {}", self.network)?;
+        for check in &self.checks {
+            let status = if check.passed { "PASS" } else { "FAIL" };
+            write!(
+                f,
+                "\n\t{} {:<9} {} ({} ms)",
+                status, check.name, check.detail, check.elapsed_ms
+            )?;
+        }
+        write!(f, "\n]")
+    }
+}
+
+/// run checks that the deployment described by the config can send: the sender keypair loads,
+/// the RPC node answers, the WebSocket pushes slot updates, the leader tracker finds the upcoming
+/// leaders and the QUIC handshake with the current one succeeds. Nothing is signed or sent, so no
+/// lamports are spent
+pub async fn run(config: &Config, rpc_client: Arc<RpcClient>) -> SelftestReport {
+    let mut checks = Vec::new();
+
+    let started = Instant::now();
+    checks.push(Check::new("keypair", started, keypairs(config)));
+
+    let started = Instant::now();
+    let rpc = within(rpc(&rpc_client)).await;
+    checks.push(Check::new("rpc", started, rpc));
+
+    // the local validator is tracked without its WebSocket
+    if !matches!(config.network, Network::Localnet) {
+        let started = Instant::now();
+        let websocket = within(websocket(&config.ws_url)).await;
+        checks.push(Check::new("websocket", started, websocket));
+    }
+
+    let started = Instant::now();
+    let leader = within(leader(config, rpc_client.clone())).await;
+    checks.push(Check::new(
+        "leaders",
+        started,
+        leader
+            .as_ref()
+            .map(|(detail, _)| detail.clone())
+            .map_err(Clone::clone),
+    ));
+
+    let started = Instant::now();
+    let quic = match leader {
+        Ok((_, tpu_quic)) => within(handshake(rpc_client, tpu_quic)).await,
+        Err(_) => Err("No leader to connect to".to_string()),
+    };
+    checks.push(Check::new("quic", started, quic));
+
+    SelftestReport {
+        network: config.network.name(),
+        checks,
+    }
+}
+
+/// keypairs loads the sender, the fee payer and the additional signers
+fn keypairs(config: &Config) -> Result<String, String> {
+    let sender = config.try_sender()?;
+    let fee_payer = config.setup_fee_payer()?;
+    let signers = config.setup_signers()?;
+    let mut detail = format!("sender {}", sender);
+    if let Some(fee_payer) = fee_payer {
+        detail += &format!(", fee payer {}", fee_payer.pubkey());
+    }
+    if !signers.is_empty() {
+        detail += &format!(", {} additional signers", signers.len());
+    }
+    Ok(detail)
+}
+
+/// rpc queries the version and the slot of the RPC node
+async fn rpc(rpc_client: &RpcClient) -> Result<String, String> {
+    let version = rpc_client
+        .get_version()
+        .await
+        .map_err(|e| format!("Failed to get the version: {}", e))?;
+    let slot = rpc_client
+        .get_slot_with_commitment(CommitmentConfig::processed())
+        .await
+        .map_err(|e| format!("Failed to get the slot: {}", e))?;
+    Ok(format!(
+        "{} at slot {}, version {}",
+        rpc_client.url(),
+        slot,
+        version.solana_core
+    ))
+}
+
+/// websocket subscribes to the slot updates and waits for the first one
+async fn websocket(ws_url: &str) -> Result<String, String> {
+    let (ws_stream, _) = tokio_tungstenite::connect_async(ws_url)
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", ws_url, e))?;
+    let (mut write, mut read) = ws_stream.split();
+    write
+        .send(Message::Text(
+            r#"{"jsonrpc":"2.0","id":1,"method":"slotSubscribe"}"#.to_string(),
+        ))
+        .await
+        .map_err(|e| format!("Failed to subscribe to the slot updates: {}", e))?;
+    while let Some(message) = read.next().await {
+        let message = message.map_err(|e| format!("WebSocket error: {}", e))?;
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let Ok(response) = serde_json::from_str::<serde_json::Value>(&text) else {
+            continue;
+        };
+        if let Some(slot) = response["params"]["result"]["slot"].as_u64() {
+            return Ok(format!("{} pushed slot {}", ws_url, slot));
+        }
+    }
+    Err(format!("{} closed before the first slot update", ws_url))
+}
+
+/// leader fills the leader cache as a send does and returns the TPU QUIC address of the leader
+/// targeted
+async fn leader(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+) -> Result<(String, SocketAddr), String> {
+    let tracker = match config.network {
+        Network::Localnet => LeaderTrackerImpl::local(rpc_client).await?,
+        _ => {
+            let tracker = LeaderTrackerImpl::new(rpc_client, 4, 0, config.ws_url.clone()).await;
+            tracker.poll_slot_leaders_once().await?;
+            tracker
+        }
+    };
+    let leaders = tracker.get_leaders();
+    let leader = leaders.last().ok_or("No upcoming leader in the schedule")?;
+    let tpu_quic = leader.tpu_quic.ok_or(format!(
+        "The leader {} has no TPU QUIC address",
+        leader.pubkey
+    ))?;
+    let detail = format!(
+        "{} upcoming leaders at slot {}, targeting {} at {}",
+        leaders.len(),
+        tracker.current_slot(),
+        leader.pubkey,
+        tpu_quic
+    );
+    Ok((detail, tpu_quic))
+}
+
+/// handshake opens a QUIC connection to the TPU of the leader without sending a transaction
+async fn handshake(rpc_client: Arc<RpcClient>, tpu_quic: SocketAddr) -> Result<String, String> {
+    QuicManager::new(rpc_client, tpu_quic)
+        .await
+        .handshake()
+        .await
+        .map_err(|e| format!("Handshake with {} failed: {}", tpu_quic, e))?;
+    Ok(format!("handshake with {} done", tpu_quic))
+}
+
+/// within fails the check if it doesn't finish within `CHECK_TIMEOUT`
+async fn within<T>(check: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+    timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("Timed out after {:?}", CHECK_TIMEOUT)))
+}
@@ -0,0 +1,518 @@
+use std::{fmt, net::SocketAddr, sync::Arc};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Slot, commitment_config::CommitmentConfig, signature::Signature,
+    transaction::Transaction,
+};
+use tokio::time::{sleep, Duration};
+use tracing::{error, field, info, instrument, Span};
+
+use crate::{
+    config::Config,
+    confirmation::{self, Confirmation},
+    error::SendError,
+    leader_tracker::LeaderTracker,
+    outcome::{DeliveryPath, SendOutcome},
+    quic_manager::{self, QuicManager},
+    simulation, tx_builder,
+    tx_tracker::{RequestId, TxTracker},
+};
+
+/// DryRun is the transaction a send would have delivered, and where to
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRun {
+    /// Identity of the leader
+    pub leader: String,
+    pub tpu_quic: SocketAddr,
+    pub signature: String,
+    pub blockhash: String,
+    pub last_valid_block_height: u64,
+    /// Size of the serialized transaction, in bytes
+    pub size: usize,
+    /// Serialized transaction in base64
+    pub transaction: String,
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[ DRY RUN\n\tLEADER: {}\n\tTPU QUIC: {}\n\tSIGNATURE: {}\n\tBLOCKHASH: {} (valid until block height {})\n\tSIZE: {} bytes\n\tTRANSACTION: {}\n]",
+            self.leader,
+            self.tpu_quic,
+            self.signature,
+            self.blockhash,
+            self.last_valid_block_height,
+            self.size,
+            self.transaction
+        )
+    }
+}
+
+/// dry_run runs a send up to the QUIC stream: it looks up the leader, fetches the blockhash,
+/// builds, signs, checks and simulates the transaction, but never connects to the leader nor
+/// records the transaction in the audit log
+pub async fn dry_run(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: &impl LeaderTracker,
+) -> Result<DryRun, SendError> {
+    let manager = QuicManager::for_current_leader(rpc_client.clone(), tracker).await?;
+    let (transaction, recent_blockhash) =
+        tx_builder::build_transaction(&rpc_client, config, manager.tracker_slot).await?;
+    simulation::simulate_transaction(rpc_client.as_ref(), &transaction, config.force).await?;
+    let serialized = bincode::serialize(&transaction)
+        .map_err(|e| format!("Failed to serialize transaction: {}", e))?;
+    Ok(DryRun {
+        leader: manager.leader.unwrap_or_default(),
+        tpu_quic: manager.tpu_quic,
+        signature: transaction.signatures[0].to_string(),
+        blockhash: recent_blockhash.blockhash.to_string(),
+        last_valid_block_height: recent_blockhash.last_valid_block_height,
+        size: serialized.len(),
+        transaction: STANDARD.encode(&serialized),
+    })
+}
+
+/// Attempt is a signed transfer that was sent and may still land
+#[derive(Debug, Clone)]
+struct Attempt {
+    signature: Signature,
+    last_valid_block_height: u64,
+    delivery_path: DeliveryPath,
+}
+
+/// Outstanding keeps every attempt of a send whose blockhash hasn't expired yet. Any of them
+/// landing late means the transfer went through, re-signing would transfer a second time
+#[derive(Debug, Default)]
+struct Outstanding(Vec<Attempt>);
+
+impl Outstanding {
+    fn push(&mut self, attempt: Attempt) {
+        self.0.push(attempt);
+    }
+
+    /// landed checks all outstanding attempts with a single status request and returns the first
+    /// that landed, with its slot. Attempts that didn't land and whose blockhash expired can't land
+    /// anymore and are forgotten
+    async fn landed(&mut self, rpc_client: &RpcClient) -> Option<(Attempt, Slot)> {
+        if self.0.is_empty() {
+            return None;
+        }
+        // read before the statuses, an attempt expiring in between is still checked once more
+        let block_height = rpc_client.get_block_height().await;
+        let signatures: Vec<Signature> = self.0.iter().map(|attempt| attempt.signature).collect();
+        if let Some((signature, slot)) = confirmation::first_landed(rpc_client, &signatures).await {
+            return self
+                .0
+                .iter()
+                .find(|attempt| attempt.signature == signature)
+                .map(|attempt| (attempt.clone(), slot));
+        }
+        match block_height {
+            Ok(block_height) => self
+                .0
+                .retain(|attempt| attempt.last_valid_block_height >= block_height),
+            Err(e) => error!("Failed to get block height: {}", e),
+        }
+        None
+    }
+}
+
+/// send_with_retry sends the transfer to the current leader until it's confirmed, rebuilding it
+/// for every attempt. Cancelling the config stops it between any two steps
+#[instrument(
+    name = "submission",
+    skip_all,
+    fields(request_id = %tx_tracker.request_id(), attempt = field::Empty)
+)]
+pub async fn send_with_retry(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: &impl LeaderTracker,
+    tx_tracker: &TxTracker,
+) -> Result<SendOutcome, SendError> {
+    config
+        .until_cancelled(send_attempts(config, rpc_client, tracker, tx_tracker))
+        .await
+}
+
+async fn send_attempts(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: &impl LeaderTracker,
+    tx_tracker: &TxTracker,
+) -> Result<SendOutcome, SendError> {
+    let mut attempts = 0;
+    let mut outstanding = Outstanding::default();
+    let mut last_error = SendError::Other("No attempts made".to_string());
+    while attempts < config.retry {
+        Span::current().record("attempt", attempts + 1);
+        if attempts > 0 {
+            tx_tracker.retry(attempts + 1, last_error.to_string());
+        }
+        // a re-signed transaction would transfer a second time if the previous one landed late
+        if let Some((attempt, slot)) = outstanding.landed(&rpc_client).await {
+            info!(
+                "Previous transaction {} landed in slot {}, not re-sending",
+                attempt.signature, slot
+            );
+            match confirmation::check_confirm_transaction(
+                &rpc_client,
+                config,
+                &attempt.signature,
+                Some(attempt.last_valid_block_height),
+            )
+            .await
+            {
+                Ok(confirmation) => {
+                    tx_tracker.confirmed(attempt.signature, confirmation.slot);
+                    return finish(
+                        config,
+                        &rpc_client,
+                        tx_tracker.request_id(),
+                        attempt.signature,
+                        confirmation,
+                        attempt.delivery_path,
+                        attempts,
+                    )
+                    .await;
+                }
+                // it landed, re-sending can only transfer twice
+                Err(e) => return Err(e),
+            }
+        }
+        let attempt_config = config.for_attempt(attempts);
+        match QuicManager::for_current_leader(rpc_client.clone(), tracker).await {
+            Ok(manager) => match manager.send_transaction(&attempt_config, tx_tracker).await {
+                Ok((transaction, last_valid_block_height)) => {
+                    let signature = transaction.signatures[0];
+                    if let Some(previous) = outstanding.0.last() {
+                        tx_tracker.superseded(&previous.signature);
+                        info!(
+                            "Transaction rebuilt with a fresh blockhash and re-signed, signature changed from {} to {}",
+                            previous.signature, signature
+                        );
+                    }
+                    outstanding.push(Attempt {
+                        signature,
+                        last_valid_block_height,
+                        delivery_path: manager.delivery_path(),
+                    });
+                    info!("Transaction sent. Confirmation...");
+                    match confirm_with_resubmit(
+                        config,
+                        rpc_client.clone(),
+                        tracker,
+                        tx_tracker,
+                        &transaction,
+                        Some(last_valid_block_height),
+                    )
+                    .await
+                    {
+                        Ok(confirmation) => {
+                            tx_tracker.confirmed(signature, confirmation.slot);
+                            info!(
+                                "Transaction confirmed successfully in {:?}.",
+                                confirmation.elapsed
+                            );
+                            return finish(
+                                config,
+                                &rpc_client,
+                                tx_tracker.request_id(),
+                                signature,
+                                confirmation,
+                                manager.delivery_path(),
+                                attempts + 1,
+                            )
+                            .await;
+                        }
+                        Err(e @ SendError::BlockhashExpired { .. }) => {
+                            tx_tracker.expired(signature);
+                            info!("{}, rebuilding with a fresh blockhash", e);
+                            last_error = e;
+                        }
+                        Err(e) => {
+                            tx_tracker.failed(signature, e.to_string());
+                            error!("Error confirming transaction: {}", e);
+                            last_error = e;
+                        }
+                    }
+                }
+                Err(
+                    e @ (SendError::InsufficientFunds { .. } | SendError::SimulationFailed { .. }),
+                ) => {
+                    // retrying can't help, fail fast
+                    return Err(e);
+                }
+                Err(e) => {
+                    error!("Error sending transaction: {}", e);
+                    last_error = e;
+                }
+            },
+            Err(e) => {
+                error!("{}", e);
+                last_error = e.into();
+            }
+        }
+        attempts += 1;
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    info!("Maximum number of attempts reached, stopping the application.");
+    Err(last_error)
+}
+
+/// submit_with_retry delivers an already signed transaction to the current leader until it's
+/// confirmed. Resending the identical bytes can't land the transfer twice. Cancelling the config
+/// stops it between any two steps
+#[instrument(
+    name = "submission",
+    skip_all,
+    fields(
+        request_id = %tx_tracker.request_id(),
+        signature = %transaction.signatures[0],
+        attempt = field::Empty
+    )
+)]
+pub async fn submit_with_retry(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: &impl LeaderTracker,
+    tx_tracker: &TxTracker,
+    transaction: &Transaction,
+) -> Result<SendOutcome, SendError> {
+    config
+        .until_cancelled(submit_attempts(
+            config,
+            rpc_client,
+            tracker,
+            tx_tracker,
+            transaction,
+        ))
+        .await
+}
+
+async fn submit_attempts(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: &impl LeaderTracker,
+    tx_tracker: &TxTracker,
+    transaction: &Transaction,
+) -> Result<SendOutcome, SendError> {
+    tx_tracker.built(transaction.signatures[0]);
+    config.audit(transaction)?;
+    let mut attempts = 0;
+    let mut last_error = SendError::Other("No attempts made".to_string());
+    while attempts < config.retry {
+        Span::current().record("attempt", attempts + 1);
+        if attempts > 0 {
+            tx_tracker.retry(attempts + 1, last_error.to_string());
+        }
+        // the signature covers the blockhash, so an expired transaction can't be salvaged
+        let blockhash = &transaction.message.recent_blockhash;
+        if let Ok(false) = rpc_client
+            .is_blockhash_valid(blockhash, CommitmentConfig::processed())
+            .await
+        {
+            tx_tracker.expired(transaction.signatures[0]);
+            return Err(format!(
+                "Blockhash {} expired, build and sign a new transaction",
+                blockhash
+            )
+            .into());
+        }
+        match QuicManager::for_current_leader(rpc_client.clone(), tracker).await {
+            Ok(manager) => match manager
+                .send_signed_transaction(transaction, tx_tracker)
+                .await
+            {
+                Ok(signature) => {
+                    info!("Transaction sent. Confirmation...");
+                    match confirm_with_resubmit(
+                        config,
+                        rpc_client.clone(),
+                        tracker,
+                        tx_tracker,
+                        transaction,
+                        None,
+                    )
+                    .await
+                    {
+                        Ok(confirmation) => {
+                            tx_tracker.confirmed(signature, confirmation.slot);
+                            info!(
+                                "Transaction confirmed successfully in {:?}.",
+                                confirmation.elapsed
+                            );
+                            return finish(
+                                config,
+                                &rpc_client,
+                                tx_tracker.request_id(),
+                                signature,
+                                confirmation,
+                                manager.delivery_path(),
+                                attempts + 1,
+                            )
+                            .await;
+                        }
+                        Err(e @ SendError::BlockhashExpired { .. }) => {
+                            tx_tracker.expired(signature);
+                            return Err(e);
+                        }
+                        Err(e) => {
+                            // resending the same bytes may still land it
+                            error!("Error confirming transaction: {}", e);
+                            last_error = e;
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Error sending transaction: {}", e);
+                    last_error = e;
+                }
+            },
+            Err(e) => {
+                error!("{}", e);
+                last_error = e.into();
+            }
+        }
+        attempts += 1;
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    info!("Maximum number of attempts reached, stopping the application.");
+    Err(last_error)
+}
+
+/// finish waits for finalization if asked to and completes the outcome of a confirmed transaction
+async fn finish(
+    config: &Config,
+    rpc_client: &RpcClient,
+    request_id: RequestId,
+    signature: Signature,
+    confirmation: Confirmation,
+    delivery_path: DeliveryPath,
+    attempts: u8,
+) -> Result<SendOutcome, SendError> {
+    let finalized_slot = match config.wait_finalized {
+        true => Some(
+            confirmation::wait_finalized(rpc_client, config, &signature, confirmation.slot).await?,
+        ),
+        false => None,
+    };
+
+    let mut outcome = SendOutcome::fetch(
+        rpc_client,
+        config.confirm_commitment,
+        request_id,
+        signature,
+        confirmation,
+        delivery_path,
+        attempts,
+    )
+    .await;
+    outcome.finalized_slot = finalized_slot;
+    Ok(outcome)
+}
+
+/// confirm_with_resubmit waits for the confirmation of a sent transaction, resending its identical
+/// bytes to the current leader while it's pending if resubmission is enabled
+pub async fn confirm_with_resubmit(
+    config: &Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: &impl LeaderTracker,
+    tx_tracker: &TxTracker,
+    transaction: &Transaction,
+    last_valid_block_height: Option<u64>,
+) -> Result<Confirmation, SendError> {
+    let confirming = confirmation::check_confirm_transaction(
+        &rpc_client,
+        config,
+        &transaction.signatures[0],
+        last_valid_block_height,
+    );
+    let Some(after_slots) = config.resubmit_after_slots else {
+        return confirming.await;
+    };
+
+    tokio::select! {
+        result = confirming => result,
+        _ = quic_manager::resubmit(
+            rpc_client.clone(),
+            tracker,
+            tx_tracker,
+            transaction,
+            after_slots,
+        ) => unreachable!("resubmit never returns"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+    use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+
+    use super::*;
+    use crate::test_utils::rpc_server::MockRpcServer;
+
+    fn attempt(last_valid_block_height: u64) -> Attempt {
+        Attempt {
+            signature: Signature::new_unique(),
+            last_valid_block_height,
+            delivery_path: DeliveryPath::Quic {
+                leader: "leader".to_string(),
+                tpu_quic: "127.0.0.1:8009".parse().unwrap(),
+            },
+        }
+    }
+
+    fn landed(slot: Slot) -> TransactionStatus {
+        TransactionStatus {
+            slot,
+            confirmations: Some(1),
+            status: Ok(()),
+            err: None,
+            confirmation_status: Some(TransactionConfirmationStatus::Confirmed),
+        }
+    }
+
+    #[tokio::test]
+    async fn an_older_attempt_landing_late_is_found() {
+        let server = MockRpcServer::start().await;
+        server.set_result("getBlockHeight", json!(100));
+        let (first, second) = (attempt(150), attempt(200));
+        server.set_signature_statuses(42, HashMap::from([(first.signature, landed(41))]));
+        let mut outstanding = Outstanding(vec![first.clone(), second.clone()]);
+
+        let (found, slot) = outstanding.landed(&server.rpc_client()).await.unwrap();
+
+        assert_eq!(found.signature, first.signature);
+        assert_eq!(slot, 41);
+        let requests = server.requests("getSignatureStatuses").await;
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0][0],
+            json!([first.signature.to_string(), second.signature.to_string()])
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_attempts_that_did_not_land_are_forgotten() {
+        let server = MockRpcServer::start().await;
+        server.set_result("getBlockHeight", json!(175));
+        server.set_signature_statuses(42, HashMap::new());
+        let (expired, valid) = (attempt(150), attempt(200));
+        let mut outstanding = Outstanding(vec![expired, valid.clone()]);
+
+        assert!(outstanding.landed(&server.rpc_client()).await.is_none());
+
+        assert_eq!(outstanding.0.len(), 1);
+        assert_eq!(outstanding.0[0].signature, valid.signature);
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    fmt,
+    io::{self, Read},
+    sync::Arc,
+};
+
+use bip39::{Language, Mnemonic, Seed};
+use solana_remote_wallet::{
+    locator::Locator,
+    remote_keypair::{generate_remote_keypair, RemoteKeypair},
+    remote_wallet::maybe_wallet_manager,
+};
+use solana_sdk::{
+    derivation_path::DerivationPath,
+    pubkey::Pubkey,
+    signature::{keypair_from_seed_and_derivation_path, Keypair, Signature},
+    signer::Signer,
+};
+use tracing::info;
+
+use crate::keystore;
+
+#[cfg(feature = "aws-kms")]
+mod kms;
+mod remote;
+
+/// Environment variable holding the sender keypair as base58 or a JSON byte array
+pub const SENDER_KEYPAIR_ENV: &str = "SENDER_KEYPAIR";
+
+/// Default Solana BIP-44 derivation path, the one used by solana-cli `prompt://?key=0/0` and most
+/// wallets
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+/// LoadedKeypair is a keypair resolved once at startup, e.g. from a seed phrase, shared by every
+/// transaction of the run. Its debug output only shows the public key
+#[derive(Clone)]
+pub struct LoadedKeypair(Arc<Keypair>);
+
+impl LoadedKeypair {
+    pub fn new(keypair: Keypair) -> Self {
+        Self(Arc::new(keypair))
+    }
+
+    pub fn keypair(&self) -> Keypair {
+        Keypair::from_bytes(&self.0.to_bytes()).expect("keypair bytes are valid")
+    }
+}
+
+impl fmt::Debug for LoadedKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LoadedKeypair({})", self.0.pubkey())
+    }
+}
+
+/// ExternalSigner is a sender key held outside the process, on a hardware wallet, in a cloud KMS
+/// or behind a signing service, that signs the serialized message without exposing the private key
+pub trait ExternalSigner: fmt::Debug + Send + Sync {
+    fn pubkey(&self) -> Pubkey;
+
+    /// sign_message signs the serialized transaction message, blocking until the signature is
+    /// returned
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, String>;
+}
+
+/// is_external_signer reports whether a keypair source points at an external signer rather than
+/// a local keypair
+pub fn is_external_signer(source: &str) -> bool {
+    ["usb://", KMS_PREFIX, "http://", "https://"]
+        .iter()
+        .any(|prefix| source.starts_with(prefix))
+}
+
+const KMS_PREFIX: &str = "kms://";
+
+/// connect_external_signer connects to the signer of a `usb://ledger?key=N`, `kms://<key id>` or
+/// `http(s)://` remote signer source and reads its public key
+pub async fn connect_external_signer(source: &str) -> Result<Arc<dyn ExternalSigner>, String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        return Ok(Arc::new(remote::RemoteSigner::connect(source).await?));
+    }
+    if let Some(key_id) = source.strip_prefix(KMS_PREFIX) {
+        #[cfg(feature = "aws-kms")]
+        return Ok(Arc::new(kms::KmsSigner::connect(key_id).await?));
+        #[cfg(not(feature = "aws-kms"))]
+        return Err(format!(
+            "Can't use KMS key {}: built without the `aws-kms` feature",
+            key_id
+        ));
+    }
+    Ok(Arc::new(LedgerSigner::connect(source)?))
+}
+
+/// LedgerSigner is a sender key held on a Ledger device, addressed by a `usb://ledger?key=N`
+/// locator. The device is opened again for every signature, so the config stays `Send`
+#[derive(Debug, Clone)]
+pub struct LedgerSigner {
+    locator: String,
+    pubkey: Pubkey,
+}
+
+impl LedgerSigner {
+    /// connect opens the device and reads the public key of the derivation path
+    pub fn connect(locator: &str) -> Result<Self, String> {
+        let pubkey = remote_keypair(locator)?.pubkey;
+        info!("[ LEDGER ] - {} {}", locator, pubkey);
+        Ok(Self {
+            locator: locator.to_string(),
+            pubkey,
+        })
+    }
+}
+
+impl ExternalSigner for LedgerSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    /// sign_message asks the device to sign the message, blocking until the transaction is
+    /// approved or rejected on-device
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, String> {
+        let keypair = remote_keypair(&self.locator)?;
+        if keypair.pubkey != self.pubkey {
+            return Err(format!(
+                "Ledger key changed from {} to {}",
+                self.pubkey, keypair.pubkey
+            ));
+        }
+        info!("[ LEDGER ] - approve the transaction on the device");
+        keypair
+            .try_sign_message(message)
+            .map_err(|e| format!("Ledger signing failed: {}", e))
+    }
+}
+
+fn remote_keypair(locator: &str) -> Result<RemoteKeypair, String> {
+    let (path, query) = locator.split_once('?').unwrap_or((locator, ""));
+    let derivation_path = match query.strip_prefix("key=") {
+        Some(key) => DerivationPath::from_key_str(key)
+            .map_err(|e| format!("Invalid Ledger key {}: {}", key, e))?,
+        None if query.is_empty() => DerivationPath::default(),
+        None => return Err(format!("Unsupported Ledger locator query: {}", query)),
+    };
+    let wallet_locator = Locator::new_from_path(path)
+        .map_err(|e| format!("Invalid Ledger locator {}: {}", locator, e))?;
+
+    let wallet_manager = maybe_wallet_manager()
+        .map_err(|e| format!("Failed to open hardware wallets: {}", e))?
+        .ok_or("No Ledger device found")?;
+    generate_remote_keypair(
+        wallet_locator,
+        derivation_path,
+        &wallet_manager,
+        false,
+        "sender",
+    )
+    .map_err(|e| format!("Failed to connect to the Ledger: {}", e))
+}
+
+/// keypair_from_mnemonic derives a keypair from a BIP-39 seed phrase and passphrase along a BIP-44
+/// derivation path, matching solana-cli
+pub fn keypair_from_mnemonic(
+    phrase: &str,
+    passphrase: &str,
+    derivation_path: &str,
+) -> Result<Keypair, String> {
+    let mnemonic = Mnemonic::from_phrase(phrase.trim(), Language::English)
+        .map_err(|e| format!("Invalid seed phrase: {}", e))?;
+    let seed = Seed::new(&mnemonic, passphrase);
+    let derivation_path = DerivationPath::from_absolute_path_str(derivation_path)
+        .map_err(|e| format!("Invalid derivation path {}: {}", derivation_path, e))?;
+
+    keypair_from_seed_and_derivation_path(seed.as_bytes(), Some(derivation_path))
+        .map_err(|e| format!("Failed to derive keypair: {}", e))
+}
+
+/// prompt_mnemonic reads the seed phrase and its optional passphrase from the terminal without
+/// echoing them and derives the keypair
+pub fn prompt_mnemonic(derivation_path: &str) -> Result<Keypair, String> {
+    let phrase = rpassword::prompt_password("Seed phrase: ")
+        .map_err(|e| format!("Failed to read seed phrase: {}", e))?;
+    let passphrase = rpassword::prompt_password(
+        "If this seed phrase has an associated passphrase, enter it now. Otherwise, press ENTER to continue: ",
+    )
+    .map_err(|e| format!("Failed to read passphrase: {}", e))?;
+
+    keypair_from_mnemonic(&phrase, &passphrase, derivation_path)
+}
+
+/// keypair_from_str parses a keypair given either as a JSON byte array, the format of solana-cli
+/// keypair files, or as a base58 string
+pub fn keypair_from_str(keypair: &str) -> Result<Keypair, String> {
+    let keypair = keypair.trim();
+    let bytes = if keypair.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(keypair)
+            .map_err(|e| format!("Invalid JSON keypair: {}", e))?
+    } else {
+        bs58::decode(keypair)
+            .into_vec()
+            .map_err(|e| format!("Invalid base58 keypair: {}", e))?
+    };
+    Keypair::from_bytes(&bytes).map_err(|e| format!("Invalid keypair: {}", e))
+}
+
+/// read_keypair_source reads a keypair from a plaintext or encrypted file, or from stdin if the
+/// source is `-`
+pub fn read_keypair_source(source: &str) -> Result<Keypair, String> {
+    if source == "-" {
+        let mut keypair = String::new();
+        io::stdin()
+            .read_to_string(&mut keypair)
+            .map_err(|e| format!("Failed to read keypair from stdin: {}", e))?;
+        return keypair_from_str(&keypair);
+    }
+    keystore::read_keypair(source)
+}
@@ -0,0 +1,81 @@
+use aws_config::BehaviorVersion;
+use aws_sdk_kms::{
+    primitives::Blob,
+    types::{MessageType, SigningAlgorithmSpec},
+    Client,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use tracing::info;
+
+use super::ExternalSigner;
+
+/// DER prefix of an ed25519 SubjectPublicKeyInfo, followed by the 32 byte public key
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+const ED25519_SIGNING_ALGORITHM: &str = "ED25519_SHA_512";
+
+/// KmsSigner signs with an `ECC_NIST_EDWARDS25519` AWS KMS key, the private key never leaves KMS.
+/// Credentials and region come from the standard AWS environment
+#[derive(Debug)]
+pub struct KmsSigner {
+    client: Client,
+    key_id: String,
+    pubkey: Pubkey,
+}
+
+impl KmsSigner {
+    pub async fn connect(key_id: &str) -> Result<Self, String> {
+        let aws_config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+        let client = Client::new(&aws_config);
+
+        let public_key = client
+            .get_public_key()
+            .key_id(key_id)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get KMS public key {}: {}", key_id, e))?
+            .public_key
+            .ok_or_else(|| format!("KMS key {} has no public key", key_id))?;
+        let pubkey = public_key
+            .as_ref()
+            .strip_prefix(&ED25519_SPKI_PREFIX)
+            .and_then(|key| Pubkey::try_from(key).ok())
+            .ok_or_else(|| format!("KMS key {} is not an ed25519 key", key_id))?;
+        info!("[ KMS ] - {} {}", key_id, pubkey);
+
+        Ok(Self {
+            client,
+            key_id: key_id.to_string(),
+            pubkey,
+        })
+    }
+}
+
+impl ExternalSigner for KmsSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, String> {
+        let request = self
+            .client
+            .sign()
+            .key_id(&self.key_id)
+            .message(Blob::new(message))
+            .message_type(MessageType::Raw)
+            .signing_algorithm(SigningAlgorithmSpec::from(ED25519_SIGNING_ALGORITHM))
+            .send();
+        // signing is synchronous for every signer, so wait for KMS on the current runtime
+        let response = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                request
+                    .await
+                    .map_err(|e| format!("KMS signing failed: {}", e))
+            })
+        })?;
+
+        let signature = response.signature.ok_or("KMS returned no signature")?;
+        Signature::try_from(signature.as_ref()).map_err(|e| format!("Invalid KMS signature: {}", e))
+    }
+}
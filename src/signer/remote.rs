@@ -0,0 +1,111 @@
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use tracing::info;
+
+use super::ExternalSigner;
+
+/// Environment variable holding the bearer token sent to the remote signer
+pub const REMOTE_SIGNER_TOKEN_ENV: &str = "REMOTE_SIGNER_TOKEN";
+
+#[derive(Deserialize)]
+struct PubkeyResponse {
+    /// Base58 public key of the signing key
+    pubkey: String,
+}
+
+#[derive(Serialize)]
+struct SignRequest<'a> {
+    /// Base64 serialized transaction message
+    message: &'a str,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    /// Base58 ed25519 signature of the message
+    signature: String,
+}
+
+/// RemoteSigner delegates signing to an HTTP signing service implementing:
+///
+/// - `GET <url>/pubkey` returning `{"pubkey": "<base58>"}`
+/// - `POST <url>/sign` with `{"message": "<base64>"}` returning `{"signature": "<base58>"}`
+///
+/// Requests carry `Authorization: Bearer $REMOTE_SIGNER_TOKEN` if the variable is set
+#[derive(Debug)]
+pub struct RemoteSigner {
+    client: Client,
+    url: String,
+    token: Option<String>,
+    pubkey: Pubkey,
+}
+
+impl RemoteSigner {
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let mut signer = Self {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            token: std::env::var(REMOTE_SIGNER_TOKEN_ENV).ok(),
+            pubkey: Pubkey::default(),
+        };
+
+        let response: PubkeyResponse = signer
+            .authorize(signer.client.get(format!("{}/pubkey", signer.url)))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to get remote signer public key: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid remote signer public key response: {}", e))?;
+        signer.pubkey = Pubkey::from_str(&response.pubkey)
+            .map_err(|e| format!("Invalid remote signer public key: {}", e))?;
+        info!("[ REMOTE SIGNER ] - {} {}", signer.url, signer.pubkey);
+
+        Ok(signer)
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn sign(&self, message: &[u8]) -> Result<Signature, String> {
+        let message = STANDARD.encode(message);
+        let response: SignResponse = self
+            .authorize(self.client.post(format!("{}/sign", self.url)))
+            .json(&SignRequest { message: &message })
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Remote signing failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid remote signer response: {}", e))?;
+
+        Signature::from_str(&response.signature)
+            .map_err(|e| format!("Invalid remote signature: {}", e))
+    }
+}
+
+impl ExternalSigner for RemoteSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, String> {
+        let signature = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.sign(message))
+        })?;
+        // a misbehaving service must not get a transaction rejected by the cluster
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err("Remote signer returned an invalid signature".to_string());
+        }
+        Ok(signature)
+    }
+}
@@ -0,0 +1,78 @@
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::transaction::Transaction;
+use tracing::{error, info};
+
+use crate::{error::SendError, rpc_api::RpcApi, tx_error};
+
+/// simulate_transaction runs `simulateTransaction` for the signed transaction and prints the
+/// program logs and consumed compute units. A failed simulation is an error unless `force` is set
+pub async fn simulate_transaction(
+    rpc_client: &dyn RpcApi,
+    transaction: &Transaction,
+    force: bool,
+) -> Result<(), SendError> {
+    let result = rpc_client
+        .simulate_transaction(
+            transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: true,
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await
+        .map_err(|e| format!("Error simulating transaction: {}", e))?;
+
+    info!(
+        "[ SIMULATION ] - units consumed: {}",
+        result
+            .units_consumed
+            .map_or("unknown".to_string(), |units| units.to_string())
+    );
+    for log in result.logs.unwrap_or_default() {
+        info!("\t{}", log);
+    }
+
+    let Some(err) = result.err else {
+        return Ok(());
+    };
+    let program_ids: Vec<_> = transaction
+        .message
+        .instructions
+        .iter()
+        .map(|instruction| *instruction.program_id(&transaction.message.account_keys))
+        .collect();
+    let explanation = tx_error::explain(&err, &program_ids);
+
+    if force {
+        error!("Simulation failed, sending anyway: {}", explanation);
+        return Ok(());
+    }
+    Err(SendError::SimulationFailed { err, explanation })
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::transaction::TransactionError;
+
+    use super::*;
+    use crate::rpc_api::MockRpc;
+
+    #[tokio::test]
+    async fn failed_simulation_stops_the_send_unless_forced() {
+        let rpc = MockRpc::new();
+        let transaction = Transaction::default();
+        assert!(simulate_transaction(&rpc, &transaction, false)
+            .await
+            .is_ok());
+
+        rpc.set_simulation_error(Some(TransactionError::AccountNotFound));
+        assert!(matches!(
+            simulate_transaction(&rpc, &transaction, false).await,
+            Err(SendError::SimulationFailed {
+                err: TransactionError::AccountNotFound,
+                ..
+            })
+        ));
+        assert!(simulate_transaction(&rpc, &transaction, true).await.is_ok());
+    }
+}
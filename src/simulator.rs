@@ -0,0 +1,342 @@
+use std::{collections::HashMap, fmt, fs, path::Path, sync::Arc, time::Duration};
+
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Slot,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    system_transaction,
+};
+use tokio::time::{interval, sleep, sleep_until, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::{
+    bench::Percentiles,
+    leader_tracker::{LeaderTracker, LeaderTrackerImpl},
+    quic_manager::ConnectionPool,
+    rpc_api::{MockRpc, RpcApi},
+    test_utils::tpu_server::MockTpuServer,
+    tx_tracker::TxTracker,
+};
+
+/// Slot time of the synthetic clock unless configured, the one of the cluster
+pub const DEFAULT_SLOT_MS: u64 = 400;
+/// Slots a transaction can wait for a leader it reached, about the lifetime of its blockhash
+const MAX_WAIT_SLOTS: u64 = 150;
+/// Number of leaders `getSlotLeaders` returns at most
+const MAX_SLOT_LEADERS: u64 = 5_000;
+/// Time the last transactions are given to reach the servers after the clock stopped
+const DRAIN_TIME: Duration = Duration::from_secs(1);
+/// The simulated sends never reach the RPC node
+const UNREACHABLE_RPC_URL: &str = "http://127.0.0.1:1";
+
+/// CapturedSchedule is the leader of every slot of a range, captured from a cluster to be replayed
+/// offline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedSchedule {
+    pub first_slot: Slot,
+    /// Leader of every slot from `first_slot` on, in order
+    pub leaders: Vec<String>,
+}
+
+impl CapturedSchedule {
+    /// capture fetches the leaders of the next `slots` slots from the processed slot on
+    pub async fn capture(rpc_client: &dyn RpcApi, slots: u64) -> Result<Self, String> {
+        let first_slot = rpc_client
+            .get_slot(CommitmentConfig::processed())
+            .await
+            .map_err(|e| format!("Failed to get the slot: {}", e))?;
+        let mut leaders = Vec::new();
+        while (leaders.len() as u64) < slots {
+            let limit = (slots - leaders.len() as u64).min(MAX_SLOT_LEADERS);
+            let chunk = rpc_client
+                .get_slot_leaders(first_slot + leaders.len() as u64, limit)
+                .await
+                .map_err(|e| format!("Failed to get the slot leaders: {}", e))?;
+            if chunk.is_empty() {
+                break;
+            }
+            leaders.extend(chunk.iter().map(Pubkey::to_string));
+        }
+        Ok(Self {
+            first_slot,
+            leaders,
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid schedule {}: {}", path.display(), e))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize the schedule: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    fn pubkeys(&self) -> Result<Vec<Pubkey>, String> {
+        self.leaders
+            .iter()
+            .map(|leader| {
+                leader
+                    .parse()
+                    .map_err(|e| format!("Invalid leader {}: {}", leader, e))
+            })
+            .collect()
+    }
+}
+
+/// Strategy is how the sender picks the leaders a transaction goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Strategy {
+    /// Number of distinct upcoming leaders the tracker keeps, the sender targets the last one
+    pub num_leaders: usize,
+    /// Slots the leader window starts after the current slot
+    pub leader_offset: i64,
+    /// Number of the upcoming leaders every transaction is sent to, from the last one back
+    pub fanout: usize,
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} leaders, offset {}, fanout {}",
+            self.num_leaders, self.leader_offset, self.fanout
+        )
+    }
+}
+
+/// Settings are the load and the clock of a simulation
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Settings {
+    /// Transactions sent per second
+    pub tps: f64,
+    /// Number of slots of the schedule replayed
+    pub slots: u64,
+    /// Duration of a slot of the synthetic clock
+    pub slot_ms: u64,
+}
+
+/// SimulationReport is how the transactions sent with a strategy would have landed
+#[derive(Debug, Serialize)]
+pub struct SimulationReport {
+    pub strategy: Strategy,
+    pub sent: u64,
+    pub send_failures: u64,
+    /// Transactions that reached a leader before the end of its slots
+    pub landed: u64,
+    /// Transactions that only reached leaders whose slots were over
+    pub missed: u64,
+    /// In percent of the sent transactions
+    pub landing_rate: f64,
+    /// Slots between the arrival of a landed transaction and the first slot of a leader holding it
+    pub wait_slots: Option<Percentiles>,
+}
+
+/// simulate replays the schedule on a synthetic slot clock and sends transactions with the
+/// strategy at the rate of the settings, each leader being a mock TPU server on a local port.
+/// Nothing reaches a cluster and no lamports are spent
+pub async fn simulate(
+    schedule: &CapturedSchedule,
+    strategy: Strategy,
+    settings: Settings,
+) -> Result<SimulationReport, String> {
+    let leaders = schedule.pubkeys()?;
+    let slots = settings.slots.min(leaders.len() as u64);
+    if slots == 0 {
+        return Err("The schedule has no slots to replay".to_string());
+    }
+    let first_slot = schedule.first_slot;
+
+    // a server for every leader a transaction of the run may reach
+    let horizon = (slots + MAX_WAIT_SLOTS).min(leaders.len() as u64) as usize;
+    let rpc = Arc::new(MockRpc::new());
+    let mut servers = HashMap::new();
+    for leader in &leaders[..horizon] {
+        if servers.contains_key(leader) {
+            continue;
+        }
+        let server = MockTpuServer::start()?;
+        rpc.add_node(leader, server.addr());
+        servers.insert(*leader, server);
+    }
+    rpc.set_slot(first_slot);
+    rpc.set_leader_schedule(first_slot, &leaders);
+
+    let num_leaders = strategy.num_leaders.max(strategy.fanout);
+    // the polls of the tracker stop with the run
+    let cancel = CancellationToken::new();
+    let _stop = cancel.clone().drop_guard();
+    let tracker =
+        LeaderTrackerImpl::driven(rpc.clone(), num_leaders, strategy.leader_offset, cancel).await;
+    tracker.poll_slot_leaders_once().await?;
+
+    let slot_time = Duration::from_millis(settings.slot_ms);
+    let started = Instant::now();
+    let clock = {
+        let (rpc, tracker) = (rpc.clone(), tracker.clone());
+        tokio::spawn(async move {
+            for slot in first_slot + 1..first_slot + slots {
+                sleep_until(started + slot_time * (slot - first_slot) as u32).await;
+                rpc.set_slot(slot);
+                tracker.set_slot(slot);
+            }
+        })
+    };
+
+    let (sent, send_failures) = send(
+        &tracker,
+        strategy,
+        settings,
+        started + slot_time * slots as u32,
+    )
+    .await;
+    let _ = clock.await;
+    sleep(DRAIN_TIME).await;
+
+    // the first slot at or after the arrival of every copy, held by the leader it reached
+    let mut waits: HashMap<Signature, u64> = HashMap::new();
+    for (leader, server) in &servers {
+        for (at, transaction) in server.arrivals() {
+            let arrival_slot = first_slot + (at - started).as_millis() as u64 / settings.slot_ms;
+            let wait = (arrival_slot..arrival_slot + MAX_WAIT_SLOTS)
+                .position(|slot| leaders.get((slot - first_slot) as usize) == Some(leader));
+            if let Some(wait) = wait {
+                let best = waits.entry(transaction.signatures[0]).or_insert(u64::MAX);
+                *best = (*best).min(wait as u64);
+            }
+        }
+    }
+
+    let mut histogram = Histogram::<u64>::new(3).expect("3 significant figures are supported");
+    for wait in waits.values() {
+        histogram.saturating_record(*wait);
+    }
+    let landed = waits.len() as u64;
+    Ok(SimulationReport {
+        strategy,
+        sent,
+        send_failures,
+        landed,
+        missed: sent.saturating_sub(landed),
+        landing_rate: match sent {
+            0 => 0.0,
+            sent => landed as f64 * 100.0 / sent as f64,
+        },
+        wait_slots: Percentiles::of(&histogram, 1),
+    })
+}
+
+/// send sends transfers at the rate of the settings until the deadline, each to the last `fanout`
+/// upcoming leaders, and returns the number of transactions sent and failed
+async fn send(
+    tracker: &LeaderTrackerImpl,
+    strategy: Strategy,
+    settings: Settings,
+    deadline: Instant,
+) -> (u64, u64) {
+    let rpc_client = Arc::new(RpcClient::new(UNREACHABLE_RPC_URL.to_string()));
+    let pool = ConnectionPool::default();
+    let tx_tracker = TxTracker::new();
+    let payer = Keypair::new();
+    let mut ticks = interval(Duration::from_secs_f64(1.0 / settings.tps.max(0.001)));
+    let mut sends = Vec::new();
+    while ticks.tick().await < deadline {
+        let leaders = tracker.get_leaders();
+        let transaction =
+            system_transaction::transfer(&payer, &Pubkey::new_unique(), 1, Hash::new_unique());
+        let managers: Vec<_> = leaders
+            .iter()
+            .rev()
+            .take(strategy.fanout.max(1))
+            .filter_map(|leader| leader.tpu_quic)
+            .map(|tpu_quic| pool.manager(rpc_client.clone(), tpu_quic))
+            .collect();
+        let tx_tracker = tx_tracker.clone();
+        sends.push(tokio::spawn(async move {
+            let mut delivered = false;
+            for manager in managers {
+                delivered |= manager
+                    .send_signed_transaction(&transaction, &tx_tracker)
+                    .await
+                    .is_ok();
+            }
+            delivered
+        }));
+    }
+
+    let (mut sent, mut failed) = (0, 0);
+    for send in sends {
+        match send.await {
+            Ok(true) => sent += 1,
+            _ => failed += 1,
+        }
+    }
+    if failed > 0 {
+        warn!("[ SIMULATION ] - {} transactions reached no leader", failed);
+    }
+    (sent, failed)
+}
+
+/// log_reports prints the report of every strategy, one line each
+pub fn log_reports(reports: &[SimulationReport]) {
+    let lines = reports
+        .iter()
+        .map(|report| {
+            let wait = report.wait_slots.as_ref().map_or("-".to_string(), |wait| {
+                format!("p50 {} p90 {} max {}", wait.p50, wait.p90, wait.max)
+            });
+            format!(
+                "\n\t{}: {}/{} landed ({:.1}%), {} missed, {} failed, wait slots {}",
+                report.strategy,
+                report.landed,
+                report.sent,
+                report.landing_rate,
+                report.missed,
+                report.send_failures,
+                wait
+            )
+        })
+        .collect::<String>();
+    info!("[ SIMULATION{}\n]", lines);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::fixtures;
+
+    #[tokio::test]
+    async fn capture_reads_the_schedule_in_chunks() {
+        let rpc = MockRpc::new();
+        rpc.set_slot(1_000);
+        let leaders = fixtures::pubkeys(1, 3);
+        let slots: Vec<Pubkey> = leaders.iter().cycle().take(12_000).copied().collect();
+        rpc.set_leader_schedule(1_000, &slots);
+
+        let schedule = CapturedSchedule::capture(&rpc, 11_000).await.unwrap();
+
+        assert_eq!(schedule.first_slot, 1_000);
+        assert_eq!(schedule.leaders.len(), 11_000);
+        assert_eq!(schedule.pubkeys().unwrap(), slots[..11_000]);
+    }
+
+    #[tokio::test]
+    async fn capture_stops_at_the_end_of_the_schedule() {
+        let rpc = MockRpc::new();
+        rpc.set_slot(10);
+        rpc.set_leader_schedule(10, &fixtures::pubkeys(2, 4));
+
+        let schedule = CapturedSchedule::capture(&rpc, 100).await.unwrap();
+        assert_eq!(schedule.leaders.len(), 4);
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use reqwest::Url;
+use serde::Deserialize;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::config::Network;
+
+/// default_path returns `~/.config/solana/cli/config.yml`, where solana-cli keeps its settings
+pub fn default_path() -> PathBuf {
+    let home = std::env::var_os("HOME").unwrap_or_else(|| ".".into());
+    Path::new(&home)
+        .join(".config")
+        .join("solana")
+        .join("cli")
+        .join("config.yml")
+}
+
+/// SolanaCliConfig holds the settings of the solana-cli config file used as defaults: the
+/// cluster it points at and the default keypair. solana-cli writes empty strings for unset values
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SolanaCliConfig {
+    #[serde(default)]
+    pub json_rpc_url: String,
+    #[serde(default)]
+    pub websocket_url: String,
+    #[serde(default)]
+    pub keypair_path: String,
+    #[serde(default)]
+    pub commitment: String,
+}
+
+impl SolanaCliConfig {
+    /// load reads the config file, None if there is none
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&content)
+            .map(Some)
+            .map_err(|e| format!("Invalid solana-cli config {}: {}", path.display(), e))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        serde_yaml::from_str(content).map_err(|e| e.to_string())
+    }
+
+    pub fn rpc_url(&self) -> Option<&str> {
+        Some(self.json_rpc_url.as_str()).filter(|url| !url.is_empty())
+    }
+
+    /// ws_url returns the WebSocket URL of the file, or derives it from the RPC URL as solana-cli
+    /// does: `ws` for `http`, `wss` for `https`, and the port after the RPC port if one is given
+    pub fn ws_url(&self) -> Option<String> {
+        if !self.websocket_url.is_empty() {
+            return Some(self.websocket_url.clone());
+        }
+        let mut url = Url::parse(self.rpc_url()?).ok()?;
+        let scheme = match url.scheme() {
+            "https" => "wss",
+            _ => "ws",
+        };
+        url.set_scheme(scheme).ok()?;
+        if let Some(port) = url.port() {
+            url.set_port(Some(port.checked_add(1)?)).ok()?;
+        }
+        Some(url.to_string())
+    }
+
+    pub fn keypair_path(&self) -> Option<&str> {
+        Some(self.keypair_path.as_str()).filter(|path| !path.is_empty())
+    }
+
+    /// commitment returns the commitment level of the file, if set
+    pub fn commitment(&self) -> Result<Option<CommitmentConfig>, String> {
+        if self.commitment.is_empty() {
+            return Ok(None);
+        }
+        CommitmentConfig::from_str(&self.commitment)
+            .map(Some)
+            .map_err(|_| format!("Invalid commitment {}", self.commitment))
+    }
+
+    /// network guesses the cluster of the RPC URL. Unknown hosts count as mainnet, so sends to
+    /// them are confirmed before signing
+    pub fn network(&self) -> Option<Network> {
+        let host = Url::parse(self.rpc_url()?).ok()?.host_str()?.to_string();
+        Some(match host.as_str() {
+            "localhost" | "127.0.0.1" | "0.0.0.0" => Network::Localnet,
+            host if host.contains("devnet") => Network::Devnet,
+            host if host.contains("testnet") => Network::Testnet,
+            _ => Network::Mainnet,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // as written by `solana config set`
+    const CONFIG: &str = "---
+json_rpc_url: \"https://api.devnet.solana.com\"
+websocket_url: \"\"
+keypair_path: /home/user/.config/solana/id.json
+address_labels:
+  \"11111111111111111111111111111111\": System Program
+commitment: confirmed
+";
+
+    #[test]
+    fn reads_the_defaults_of_solana_cli() {
+        let config = SolanaCliConfig::parse(CONFIG).unwrap();
+
+        assert_eq!(config.rpc_url(), Some("https://api.devnet.solana.com"));
+        assert_eq!(
+            config.ws_url().as_deref(),
+            Some("wss://api.devnet.solana.com/")
+        );
+        assert_eq!(
+            config.keypair_path(),
+            Some("/home/user/.config/solana/id.json")
+        );
+        assert_eq!(
+            config.commitment().unwrap(),
+            Some(CommitmentConfig::confirmed())
+        );
+        assert!(matches!(config.network(), Some(Network::Devnet)));
+    }
+
+    #[test]
+    fn websocket_port_follows_the_rpc_port() {
+        let config = SolanaCliConfig {
+            json_rpc_url: "http://localhost:8899".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(config.ws_url().as_deref(), Some("ws://localhost:8900/"));
+        assert!(matches!(config.network(), Some(Network::Localnet)));
+    }
+
+    #[test]
+    fn unset_values_are_none() {
+        let config = SolanaCliConfig::parse("---\njson_rpc_url: \"\"\n").unwrap();
+
+        assert_eq!(config.rpc_url(), None);
+        assert_eq!(config.ws_url(), None);
+        assert_eq!(config.keypair_path(), None);
+        assert_eq!(config.commitment().unwrap(), None);
+        assert!(config.network().is_none());
+    }
+
+    #[test]
+    fn testnet_is_not_taken_for_devnet() {
+        let config = SolanaCliConfig {
+            json_rpc_url: "https://api.testnet.solana.com".to_string(),
+            ..Default::default()
+        };
+
+        assert!(matches!(config.network(), Some(Network::Testnet)));
+    }
+
+    #[test]
+    fn no_websocket_port_follows_the_last_port() {
+        let config = SolanaCliConfig {
+            json_rpc_url: "http://localhost:65535".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(config.ws_url(), None);
+    }
+}
@@ -0,0 +1,344 @@
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    future::Future,
+    sync::{Arc, LazyLock, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use serde::Serialize;
+use serde_json::json;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::{sleep, Instant},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+/// Delay before the first restart of a failed or exited task, doubled on every further restart
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// A task running this long before it failed restarts after the initial backoff again
+const HEALTHY_RUN: Duration = Duration::from_secs(60);
+/// Time a readiness probe has to send its request
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+static SUPERVISOR: LazyLock<Supervisor> = LazyLock::new(Supervisor::default);
+
+/// global returns the supervisor of the background tasks of the process
+pub fn global() -> &'static Supervisor {
+    &SUPERVISOR
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    Running,
+    /// Waiting for the backoff after a panic, a failure or an exit
+    Restarting,
+}
+
+/// TaskHealth is the state of a supervised task with its failures so far
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskHealth {
+    pub name: &'static str,
+    pub state: TaskState,
+    pub restarts: u32,
+    /// Panic message or error of the latest failure
+    pub last_failure: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Tasks {
+    next_id: u64,
+    /// Tasks still supervised by their spawn order
+    health: BTreeMap<u64, TaskHealth>,
+}
+
+/// Supervisor runs background tasks and restarts them with an exponential backoff whenever they
+/// panic, fail or exit, keeping their health for `debug dump` and the readiness endpoint
+#[derive(Debug, Clone, Default)]
+pub struct Supervisor {
+    tasks: Arc<Mutex<Tasks>>,
+}
+
+/// Registration is the health entry of a supervised task, removed once the task is gone for good
+struct Registration {
+    supervisor: Supervisor,
+    id: u64,
+}
+
+impl Registration {
+    fn update(&self, update: impl FnOnce(&mut TaskHealth)) {
+        if let Some(health) = self.supervisor.lock().health.get_mut(&self.id) {
+            update(health);
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.supervisor.lock().health.remove(&self.id);
+    }
+}
+
+impl Supervisor {
+    /// spawn runs the task built by `task` in the background. Whenever it returns or panics it's
+    /// restarted with a new future from `task` after the backoff, only cancelling `cancel` aborts
+    /// and ends it. Ended tasks are no longer listed in the health
+    pub fn spawn<F, Fut>(&self, name: &'static str, cancel: CancellationToken, task: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let registration = {
+            let mut tasks = self.lock();
+            let id = tasks.next_id;
+            tasks.next_id += 1;
+            tasks.health.insert(
+                id,
+                TaskHealth {
+                    name,
+                    state: TaskState::Running,
+                    restarts: 0,
+                    last_failure: None,
+                },
+            );
+            Registration {
+                supervisor: self.clone(),
+                id,
+            }
+        };
+        tokio::spawn(async move {
+            let mut backoff = INITIAL_BACKOFF;
+            loop {
+                let started = Instant::now();
+                let mut running = tokio::spawn(task());
+                let result = tokio::select! {
+                    result = &mut running => result,
+                    _ = cancel.cancelled() => {
+                        running.abort();
+                        return;
+                    }
+                };
+                let failure = match result {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(e),
+                    Err(e) if e.is_panic() => {
+                        Some(format!("panicked: {}", panic_message(e.into_panic())))
+                    }
+                    // the runtime shuts down
+                    Err(_) => return,
+                };
+                if started.elapsed() >= HEALTHY_RUN {
+                    backoff = INITIAL_BACKOFF;
+                }
+                match &failure {
+                    Some(failure) => error!(
+                        "[ SUPERVISOR ] - Task {} {}, restarting in {:?}",
+                        name, failure, backoff
+                    ),
+                    None => warn!(
+                        "[ SUPERVISOR ] - Task {} exited, restarting in {:?}",
+                        name, backoff
+                    ),
+                }
+                registration.update(|health| {
+                    health.state = TaskState::Restarting;
+                    if failure.is_some() {
+                        health.last_failure = failure;
+                    }
+                });
+                tokio::select! {
+                    _ = sleep(backoff) => {}
+                    _ = cancel.cancelled() => return,
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                registration.update(|health| {
+                    health.state = TaskState::Running;
+                    health.restarts += 1;
+                });
+            }
+        });
+    }
+
+    /// health returns the state of every running or restarting task in the order they were spawned
+    pub fn health(&self) -> Vec<TaskHealth> {
+        self.lock().health.values().cloned().collect()
+    }
+
+    /// healthy reports whether no task is waiting to be restarted
+    pub fn healthy(&self) -> bool {
+        self.lock()
+            .health
+            .values()
+            .all(|task| task.state != TaskState::Restarting)
+    }
+
+    /// serve_readiness answers `GET /ready` on the listener with 200 while the supervisor is
+    /// healthy and 503 otherwise, the health of the tasks in a JSON body. It never returns
+    pub async fn serve_readiness(self, listener: TcpListener) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(self.clone().answer_probe(stream));
+                }
+                Err(e) => error!("[ SUPERVISOR ] - Failed to accept a readiness probe: {}", e),
+            }
+        }
+    }
+
+    async fn answer_probe(self, mut stream: TcpStream) {
+        let mut request = [0; 1024];
+        let read = match tokio::time::timeout(PROBE_TIMEOUT, stream.read(&mut request)).await {
+            Ok(Ok(read)) => read,
+            _ => return,
+        };
+        let request = String::from_utf8_lossy(&request[..read]);
+        let (status, body) = match request.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+            ["GET", "/ready"] => {
+                let ready = self.healthy();
+                let status = match ready {
+                    true => "200 OK",
+                    false => "503 Service Unavailable",
+                };
+                (status, json!({ "ready": ready, "tasks": self.health() }))
+            }
+            _ => ("404 Not Found", json!({ "error": "not found" })),
+        };
+        let body = body.to_string();
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        if let Err(e) = stream.write_all(response.as_bytes()).await {
+            error!("[ SUPERVISOR ] - Failed to answer a readiness probe: {}", e);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tasks> {
+        self.tasks.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// panic_message returns the message a task panicked with, if it was a string
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "without a message".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn panicking_task_is_restarted_with_backoff() {
+        let supervisor = Supervisor::default();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counted = runs.clone();
+        supervisor.spawn("flaky", CancellationToken::new(), move || {
+            let run = counted.fetch_add(1, Ordering::Relaxed);
+            async move {
+                if run < 2 {
+                    panic!("run {}", run);
+                }
+                std::future::pending::<()>().await;
+                Ok(())
+            }
+        });
+
+        // the first restart after 1s, the second 2s later
+        sleep(Duration::from_millis(500)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), 1);
+        assert!(!supervisor.healthy());
+        sleep(Duration::from_secs(1)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), 2);
+        sleep(Duration::from_secs(2)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), 3);
+
+        let health = &supervisor.health()[0];
+        assert_eq!(health.state, TaskState::Running);
+        assert_eq!(health.restarts, 2);
+        assert_eq!(health.last_failure.as_deref(), Some("panicked: run 1"));
+        assert!(supervisor.healthy());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exited_task_is_restarted_with_backoff() {
+        let supervisor = Supervisor::default();
+        let cancel = CancellationToken::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counted = runs.clone();
+        supervisor.spawn("short-lived", cancel.clone(), move || {
+            counted.fetch_add(1, Ordering::Relaxed);
+            async { Ok(()) }
+        });
+
+        // restarts after 1s, 2s and 4s
+        sleep(Duration::from_millis(500)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), 1);
+        let health = &supervisor.health()[0];
+        assert_eq!(health.state, TaskState::Restarting);
+        assert_eq!(health.last_failure, None);
+        sleep(Duration::from_secs(1)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), 2);
+        sleep(Duration::from_secs(6)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), 4);
+
+        cancel.cancel();
+        sleep(Duration::from_secs(60)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), 4);
+        assert!(supervisor.health().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelled_task_is_aborted_and_not_restarted() {
+        let supervisor = Supervisor::default();
+        let cancel = CancellationToken::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counted = runs.clone();
+        supervisor.spawn("endless", cancel.clone(), move || {
+            counted.fetch_add(1, Ordering::Relaxed);
+            std::future::pending()
+        });
+
+        sleep(Duration::from_secs(1)).await;
+        cancel.cancel();
+        sleep(Duration::from_secs(60)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), 1);
+        assert!(supervisor.health().is_empty());
+        assert!(supervisor.healthy());
+    }
+
+    #[tokio::test]
+    async fn readiness_follows_the_restarting_tasks() {
+        let supervisor = Supervisor::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/ready", listener.local_addr().unwrap());
+        tokio::spawn(supervisor.clone().serve_readiness(listener));
+
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        supervisor.spawn("failing", CancellationToken::new(), || async {
+            Err("refused".to_string())
+        });
+        sleep(Duration::from_millis(100)).await;
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["tasks"][0]["name"], "failing");
+        assert_eq!(body["tasks"][0]["last_failure"], "refused");
+    }
+}
@@ -0,0 +1,35 @@
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::{
+    runtime,
+    trace::{self, Tracer},
+    Resource,
+};
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Service name the traces are reported under
+pub const SERVICE_NAME: &str = "quic";
+
+/// layer returns the tracing layer exporting the spans over OTLP/gRPC to the collector at
+/// `OTEL_EXPORTER_OTLP_ENDPOINT`, `http://localhost:4317` by default
+pub fn layer<S>() -> Result<OpenTelemetryLayer<S, Tracer>, String>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic())
+        .with_trace_config(
+            trace::config()
+                .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)])),
+        )
+        .install_batch(runtime::Tokio)
+        .map_err(|e| format!("Failed to set up the OTLP exporter: {}", e))?;
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// shutdown exports the spans still buffered, the process must not exit before
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
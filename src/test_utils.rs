@@ -0,0 +1,8 @@
+//! Test doubles of the cluster, to test the sender and applications embedding it without a
+//! validator. Built with the `test-utils` feature
+
+pub mod fixtures;
+pub mod rpc_server;
+pub mod snapshot;
+pub mod tpu_server;
+pub mod ws_server;
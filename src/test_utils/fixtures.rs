@@ -0,0 +1,95 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use solana_client::rpc_response::RpcContactInfo;
+use solana_sdk::{
+    hash::{hashv, Hash},
+    pubkey::Pubkey,
+    signature::{keypair_from_seed, Keypair, Signer},
+};
+
+use crate::{
+    config::{Config, Network},
+    rpc_api,
+    signer::LoadedKeypair,
+};
+
+/// TPU QUIC port of the first node of `contact_infos`, the next ones follow
+pub const FIRST_TPU_QUIC_PORT: u16 = 8001;
+
+/// keypair returns the keypair derived from the seed, the same on every run
+pub fn keypair(seed: u64) -> Keypair {
+    nth_keypair(seed, 0)
+}
+
+/// keypairs returns `count` distinct keypairs derived from the seed, the same on every run
+pub fn keypairs(seed: u64, count: usize) -> Vec<Keypair> {
+    (0..count as u64)
+        .map(|index| nth_keypair(seed, index))
+        .collect()
+}
+
+/// pubkeys returns the public keys of `keypairs(seed, count)`
+pub fn pubkeys(seed: u64, count: usize) -> Vec<Pubkey> {
+    keypairs(seed, count).iter().map(Signer::pubkey).collect()
+}
+
+/// contact_infos returns the gossip entries of `count` nodes with the public keys of
+/// `pubkeys(seed, count)`, reachable only over TPU QUIC on consecutive local ports from
+/// `FIRST_TPU_QUIC_PORT` on
+pub fn contact_infos(seed: u64, count: usize) -> Vec<RpcContactInfo> {
+    pubkeys(seed, count)
+        .iter()
+        .zip(FIRST_TPU_QUIC_PORT..)
+        .map(|(pubkey, port)| rpc_api::contact_info(pubkey, tpu_quic(port)))
+        .collect()
+}
+
+/// tpu_quic returns the local TPU QUIC address on the port
+pub fn tpu_quic(port: u16) -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::LOCALHOST, port))
+}
+
+/// blockhash returns a blockhash derived from the seed, the same on every run
+pub fn blockhash(seed: u64) -> Hash {
+    hashv(&[b"quic-fixture-blockhash", &seed.to_le_bytes()])
+}
+
+/// config returns the devnet config of a transfer from `keypairs(seed, 2)[0]` to the public key
+/// of `keypairs(seed, 2)[1]`, so the built transactions are the same on every run
+pub fn config(seed: u64) -> Config {
+    let [sender, receiver] = [0, 1].map(|index| nth_keypair(seed, index));
+    Config {
+        sender_keypair: Some(LoadedKeypair::new(sender)),
+        receiver_key: receiver.pubkey().to_string(),
+        ..Config::new(Network::Devnet, 0)
+    }
+}
+
+fn nth_keypair(seed: u64, index: u64) -> Keypair {
+    let secret = hashv(&[b"quic-fixture", &seed.to_le_bytes(), &index.to_le_bytes()]);
+    keypair_from_seed(secret.as_ref()).expect("a hash is a valid keypair seed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_are_reproducible() {
+        assert_eq!(keypair(1).to_bytes(), keypair(1).to_bytes());
+        assert_eq!(keypair(1).pubkey(), pubkeys(1, 1)[0]);
+        assert_ne!(keypair(1).pubkey(), keypair(2).pubkey());
+
+        let nodes = contact_infos(7, 3);
+        assert_eq!(nodes, contact_infos(7, 3));
+        assert_eq!(nodes[2].tpu_quic, Some(tpu_quic(FIRST_TPU_QUIC_PORT + 2)));
+    }
+
+    #[test]
+    fn keypairs_of_a_seed_are_distinct() {
+        let mut pubkeys = pubkeys(3, 16);
+        pubkeys.sort();
+        pubkeys.dedup();
+        assert_eq!(pubkeys.len(), 16);
+    }
+}
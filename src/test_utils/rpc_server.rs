@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{clock::Slot, hash::Hash, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::TransactionStatus;
+use wiremock::{matchers, Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use crate::rpc_api;
+
+/// JSON-RPC error code of the methods without an answer
+const METHOD_NOT_FOUND: i64 = -32601;
+/// Version of the node reported by `getVersion`, the one of the client crates
+const SOLANA_CORE_VERSION: &str = "1.18.13";
+
+/// Answer computes the result or the error of a request from its params
+type Answer = Box<dyn Fn(&Value) -> Result<Value, (i64, String)> + Send + Sync>;
+
+type Answers = Arc<Mutex<HashMap<String, Answer>>>;
+
+/// MockRpcServer is an HTTP JSON-RPC server on a local port answering every method with the
+/// result set by the test, so a real `RpcClient` and everything built on it can be tested without
+/// a cluster. `getVersion`, which the client queries before some methods, is answered from the
+/// start, the other methods without an answer fail with "Method not found". It stops when dropped
+pub struct MockRpcServer {
+    server: MockServer,
+    answers: Answers,
+}
+
+impl MockRpcServer {
+    /// start binds the server to a free local port
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        let answers = Answers::default();
+        Mock::given(matchers::method("POST"))
+            .respond_with(Responder(answers.clone()))
+            .mount(&server)
+            .await;
+        let server = Self { server, answers };
+        server.set_result(
+            "getVersion",
+            json!({ "solana-core": SOLANA_CORE_VERSION, "feature-set": 0 }),
+        );
+        server
+    }
+
+    /// url returns the HTTP URL of the server
+    pub fn url(&self) -> String {
+        self.server.uri()
+    }
+
+    /// rpc_client returns a client of the server
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new(self.url())
+    }
+
+    /// set_result answers every request of the method with the result
+    pub fn set_result(&self, method: &str, result: Value) {
+        self.answer(method, move |_| Ok(result.clone()));
+    }
+
+    /// set_error fails every request of the method with the JSON-RPC error
+    pub fn set_error(&self, method: &str, code: i64, message: &str) {
+        let message = message.to_string();
+        self.answer(method, move |_| Err((code, message.clone())));
+    }
+
+    pub fn set_slot(&self, slot: Slot) {
+        self.set_result("getSlot", json!(slot));
+    }
+
+    /// set_slot_leaders answers `getSlotLeaders` with the leaders of the consecutive slots from
+    /// `first_slot` on, as many as requested from the requested slot
+    pub fn set_slot_leaders(&self, first_slot: Slot, leaders: &[Pubkey]) {
+        let leaders = leaders.to_vec();
+        self.answer("getSlotLeaders", move |params| {
+            let start = params[0].as_u64().unwrap_or(first_slot);
+            let limit = params[1].as_u64().unwrap_or(leaders.len() as u64);
+            let skipped = start.saturating_sub(first_slot) as usize;
+            let requested: Vec<Pubkey> = leaders
+                .iter()
+                .skip(skipped)
+                .take(limit as usize)
+                .copied()
+                .collect();
+            Ok(slot_leaders(&requested))
+        });
+    }
+
+    /// set_cluster_nodes answers `getClusterNodes` with nodes reachable only over TPU QUIC
+    pub fn set_cluster_nodes(&self, nodes: &[(Pubkey, SocketAddr)]) {
+        self.set_result("getClusterNodes", cluster_nodes(nodes));
+    }
+
+    /// set_signature_statuses answers `getSignatureStatuses` at the slot with the statuses of the
+    /// requested signatures, null for the unknown ones
+    pub fn set_signature_statuses(
+        &self,
+        slot: Slot,
+        statuses: HashMap<Signature, TransactionStatus>,
+    ) {
+        self.answer("getSignatureStatuses", move |params| {
+            let requested: Vec<Option<TransactionStatus>> = params[0]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|signature| {
+                    let signature = signature.as_str()?.parse().ok()?;
+                    statuses.get(&signature).cloned()
+                })
+                .collect();
+            Ok(signature_statuses(slot, &requested))
+        });
+    }
+
+    /// set_blockhash answers `getLatestBlockhash` at the slot with the blockhash
+    pub fn set_blockhash(&self, slot: Slot, blockhash: Hash, last_valid_block_height: u64) {
+        self.set_result(
+            "getLatestBlockhash",
+            json!({
+                "context": { "slot": slot },
+                "value": {
+                    "blockhash": blockhash.to_string(),
+                    "lastValidBlockHeight": last_valid_block_height,
+                },
+            }),
+        );
+    }
+
+    /// requests returns the params of every request of the method received so far, in order
+    pub async fn requests(&self, method: &str) -> Vec<Value> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|request| serde_json::from_slice::<Value>(&request.body).ok())
+            .filter(|body| body["method"] == method)
+            .map(|body| body["params"].clone())
+            .collect()
+    }
+
+    fn answer(
+        &self,
+        method: &str,
+        answer: impl Fn(&Value) -> Result<Value, (i64, String)> + Send + Sync + 'static,
+    ) {
+        lock(&self.answers).insert(method.to_string(), Box::new(answer));
+    }
+}
+
+/// Responder answers the JSON-RPC requests from the answers set by the test, echoing their id
+struct Responder(Answers);
+
+impl Respond for Responder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let Ok(body) = serde_json::from_slice::<Value>(&request.body) else {
+            return ResponseTemplate::new(400);
+        };
+        let method = body["method"].as_str().unwrap_or_default();
+        let answer = match lock(&self.0).get(method) {
+            Some(answer) => answer(&body["params"]),
+            None => Err((METHOD_NOT_FOUND, "Method not found".to_string())),
+        };
+        let response = match answer {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": body["id"], "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": body["id"],
+                "error": { "code": code, "message": message },
+            }),
+        };
+        ResponseTemplate::new(200).set_body_json(response)
+    }
+}
+
+/// slot_leaders returns the result of `getSlotLeaders` for the leaders
+pub fn slot_leaders(leaders: &[Pubkey]) -> Value {
+    json!(leaders.iter().map(Pubkey::to_string).collect::<Vec<_>>())
+}
+
+/// cluster_nodes returns the result of `getClusterNodes` for nodes reachable only over TPU QUIC
+pub fn cluster_nodes(nodes: &[(Pubkey, SocketAddr)]) -> Value {
+    json!(nodes
+        .iter()
+        .map(|(pubkey, tpu_quic)| rpc_api::contact_info(pubkey, *tpu_quic))
+        .collect::<Vec<_>>())
+}
+
+/// signature_statuses returns the result of `getSignatureStatuses` at the slot
+pub fn signature_statuses(slot: Slot, statuses: &[Option<TransactionStatus>]) -> Value {
+    json!({ "context": { "slot": slot }, "value": statuses })
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
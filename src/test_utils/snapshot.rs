@@ -0,0 +1,89 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_sdk::{
+    compute_budget, message::VersionedMessage, pubkey::Pubkey, system_program,
+    transaction::VersionedTransaction,
+};
+
+/// describe renders the transaction for a snapshot: its version, the header and the account keys
+/// with their roles, the instructions with their program resolved, the address table lookups and
+/// the signatures, followed by the wire format. A snapshot diff thus shows which part of a built
+/// transaction changed, and that its bytes did. Built transactions are the same on every run with
+/// the keys and the blockhash of `fixtures`
+pub fn describe(transaction: impl Into<VersionedTransaction>) -> Value {
+    let transaction = transaction.into();
+    let message = &transaction.message;
+    let keys = message.static_account_keys();
+    let header = message.header();
+    let version = match message {
+        VersionedMessage::Legacy(_) => json!("legacy"),
+        VersionedMessage::V0(_) => json!(0),
+    };
+    let account_keys: Vec<Value> = keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| {
+            json!({
+                "pubkey": key.to_string(),
+                "signer": message.is_signer(index),
+                "writable": message.is_maybe_writable(index),
+            })
+        })
+        .collect();
+    let instructions: Vec<Value> = message
+        .instructions()
+        .iter()
+        .map(|instruction| {
+            json!({
+                "program": keys
+                    .get(instruction.program_id_index as usize)
+                    .map_or("<lookup>".to_string(), program_name),
+                "accounts": instruction.accounts,
+                "data": STANDARD.encode(&instruction.data),
+            })
+        })
+        .collect();
+    let lookups: Vec<Value> = message
+        .address_table_lookups()
+        .unwrap_or_default()
+        .iter()
+        .map(|lookup| {
+            json!({
+                "table": lookup.account_key.to_string(),
+                "writable": lookup.writable_indexes,
+                "readonly": lookup.readonly_indexes,
+            })
+        })
+        .collect();
+    let wire = bincode::serialize(&transaction).expect("a transaction serializes");
+
+    json!({
+        "version": version,
+        "header": {
+            "required_signatures": header.num_required_signatures,
+            "readonly_signed": header.num_readonly_signed_accounts,
+            "readonly_unsigned": header.num_readonly_unsigned_accounts,
+        },
+        "account_keys": account_keys,
+        "recent_blockhash": message.recent_blockhash().to_string(),
+        "instructions": instructions,
+        "address_table_lookups": lookups,
+        "signatures": transaction.signatures.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        "size": wire.len(),
+        "wire": STANDARD.encode(&wire),
+    })
+}
+
+/// program_name names the programs the sender builds instructions for, others by their id
+fn program_name(program_id: &Pubkey) -> String {
+    let name = match *program_id {
+        id if id == system_program::id() => "system",
+        id if id == compute_budget::id() => "compute-budget",
+        id if id == spl_memo::id() => "memo",
+        id if id == spl_token::id() => "spl-token",
+        id if id == spl_token_2022::id() => "spl-token-2022",
+        id if id == spl_associated_token_account::id() => "associated-token-account",
+        id => return id.to_string(),
+    };
+    name.to_string()
+}
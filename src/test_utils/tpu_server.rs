@@ -0,0 +1,201 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    time::Duration,
+};
+
+use quinn::{Connecting, Endpoint, ServerConfig, TransportConfig};
+use solana_sdk::{packet::PACKET_DATA_SIZE, signature::Keypair, transaction::Transaction};
+use solana_streamer::{
+    nonblocking::quic::ALPN_TPU_PROTOCOL_ID, quic::SkipClientVerification,
+    tls_certificates::new_self_signed_tls_certificate,
+};
+use tokio::time::{sleep, Instant};
+
+/// Interval `wait_for` checks the received transactions at
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Faults the server injects into every received transaction
+#[derive(Debug, Clone, Copy, Default)]
+struct Faults {
+    /// Share of the transactions dropped after reading, from 0 to 1
+    drop_rate: f64,
+    /// Delay before a transaction is recorded
+    latency: Duration,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Bytes of the recorded transactions with the time they were recorded at
+    received: Mutex<Vec<(Instant, Vec<u8>)>>,
+    faults: Mutex<Faults>,
+    connections: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+/// MockTpuServer is an in-process QUIC server speaking the TPU protocol of the validators on a
+/// local port. It records the bytes of every transaction streamed to it, and can drop a share of
+/// them or record them late, so the QUIC sender can be tested without a validator. It stops when
+/// dropped.
+///
+/// The QUIC endpoint of the sender runs on the runtime of its first connection, the tests sending
+/// to the server must share one runtime rather than each start its own with `#[tokio::test]`
+pub struct MockTpuServer {
+    endpoint: Endpoint,
+    state: Arc<State>,
+}
+
+impl MockTpuServer {
+    /// start binds the server to a free local port and accepts connections in the background
+    pub fn start() -> Result<Self, String> {
+        let endpoint =
+            Endpoint::server(server_config()?, SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+                .map_err(|e| format!("Failed to bind the mock TPU server: {}", e))?;
+        let state = Arc::new(State::default());
+
+        let accepting = endpoint.clone();
+        let accepted = state.clone();
+        tokio::spawn(async move {
+            while let Some(connecting) = accepting.accept().await {
+                tokio::spawn(serve(connecting, accepted.clone()));
+            }
+        });
+        Ok(Self { endpoint, state })
+    }
+
+    /// addr returns the TPU QUIC address of the server
+    pub fn addr(&self) -> SocketAddr {
+        self.endpoint
+            .local_addr()
+            .expect("the endpoint is bound to a local address")
+    }
+
+    /// set_drop_rate drops the share of the next transactions, from 0 to 1
+    pub fn set_drop_rate(&self, drop_rate: f64) {
+        lock(&self.state.faults).drop_rate = drop_rate.clamp(0.0, 1.0);
+    }
+
+    /// set_latency records the next transactions only after the delay
+    pub fn set_latency(&self, latency: Duration) {
+        lock(&self.state.faults).latency = latency;
+    }
+
+    /// received returns the bytes of the recorded transactions in the order they arrived
+    pub fn received(&self) -> Vec<Vec<u8>> {
+        lock(&self.state.received)
+            .iter()
+            .map(|(_, bytes)| bytes.clone())
+            .collect()
+    }
+
+    /// arrivals returns the recorded transactions with the time they were recorded at, skipping
+    /// the bytes that aren't one
+    pub fn arrivals(&self) -> Vec<(Instant, Transaction)> {
+        lock(&self.state.received)
+            .iter()
+            .filter_map(|(at, bytes)| Some((*at, bincode::deserialize(bytes).ok()?)))
+            .collect()
+    }
+
+    /// transactions returns the recorded transactions, skipping the bytes that aren't one
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.received()
+            .iter()
+            .filter_map(|bytes| bincode::deserialize(bytes).ok())
+            .collect()
+    }
+
+    /// connections returns the number of connections accepted so far
+    pub fn connections(&self) -> usize {
+        self.state.connections.load(Ordering::Relaxed)
+    }
+
+    /// dropped returns the number of transactions dropped by the injected faults
+    pub fn dropped(&self) -> usize {
+        self.state.dropped.load(Ordering::Relaxed)
+    }
+
+    /// wait_for waits until at least `count` transactions were recorded and returns them
+    pub async fn wait_for(
+        &self,
+        count: usize,
+        timeout: Duration,
+    ) -> Result<Vec<Transaction>, String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let transactions = self.transactions();
+            if transactions.len() >= count {
+                return Ok(transactions);
+            }
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "{} of {} transactions received within {:?}",
+                    transactions.len(),
+                    count,
+                    timeout
+                ));
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for MockTpuServer {
+    fn drop(&mut self) {
+        self.endpoint.close(0u32.into(), b"stopped");
+    }
+}
+
+/// serve reads every unidirectional stream of the connection as one transaction, like the TPU
+async fn serve(connecting: Connecting, state: Arc<State>) {
+    let Ok(connection) = connecting.await else {
+        return;
+    };
+    state.connections.fetch_add(1, Ordering::Relaxed);
+    while let Ok(mut stream) = connection.accept_uni().await {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let Ok(bytes) = stream.read_to_end(PACKET_DATA_SIZE).await else {
+                return;
+            };
+            // the sender opens the connection with an empty stream
+            if bytes.is_empty() {
+                return;
+            }
+            let faults = *lock(&state.faults);
+            if rand::random::<f64>() < faults.drop_rate {
+                state.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            sleep(faults.latency).await;
+            lock(&state.received).push((Instant::now(), bytes));
+        });
+    }
+}
+
+/// server_config accepts any client certificate over the TPU ALPN, with a self-signed server
+/// certificate the sender doesn't verify
+fn server_config() -> Result<ServerConfig, String> {
+    let (cert, key) =
+        new_self_signed_tls_certificate(&Keypair::new(), IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .map_err(|e| format!("Failed to create the TLS certificate: {}", e))?;
+    let mut crypto = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(SkipClientVerification::new())
+        .with_single_cert(vec![cert], key)
+        .map_err(|e| format!("Invalid TLS certificate: {}", e))?;
+    crypto.alpn_protocols = vec![ALPN_TPU_PROTOCOL_ID.to_vec()];
+
+    let mut transport = TransportConfig::default();
+    transport.max_concurrent_bidi_streams(0u32.into());
+    let mut config = ServerConfig::with_crypto(Arc::new(crypto));
+    config.transport_config(Arc::new(transport));
+    Ok(config)
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,174 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use solana_sdk::clock::Slot;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::{broadcast, watch},
+    task::JoinHandle,
+};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Slot notifications a slow subscriber can fall behind by before it misses some
+const SLOT_BUFFER: usize = 64;
+
+#[derive(Debug)]
+struct State {
+    slots: broadcast::Sender<Slot>,
+    /// False while the server is killed
+    alive: watch::Sender<bool>,
+    /// Connections accepted while alive
+    connections: AtomicUsize,
+}
+
+/// MockSlotServer is a WebSocket server on a local port answering `slotSubscribe` and pushing the
+/// slots the test sends as `slotNotification`s, as the PubSub endpoint of a node does. `kill`
+/// closes every connection and refuses the new ones until `revive`, to test how the subscribers
+/// survive outages. It stops when dropped
+pub struct MockSlotServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    accepting: JoinHandle<()>,
+}
+
+impl MockSlotServer {
+    /// start binds the server to a free local port and accepts connections in the background
+    pub async fn start() -> Result<Self, String> {
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .map_err(|e| format!("Failed to bind the mock slot server: {}", e))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to get the mock slot server address: {}", e))?;
+        let state = Arc::new(State {
+            slots: broadcast::channel(SLOT_BUFFER).0,
+            alive: watch::channel(true).0,
+            connections: AtomicUsize::new(0),
+        });
+
+        let accepted = state.clone();
+        let accepting = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                // a killed server drops the connections before the handshake
+                if *accepted.alive.borrow() {
+                    accepted.connections.fetch_add(1, Ordering::Relaxed);
+                    tokio::spawn(serve(stream, accepted.clone()));
+                }
+            }
+        });
+        Ok(Self {
+            addr,
+            state,
+            accepting,
+        })
+    }
+
+    /// url returns the WebSocket URL of the server
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+
+    /// send_slot notifies every subscriber of the slot
+    pub fn send_slot(&self, slot: Slot) {
+        let _ = self.state.slots.send(slot);
+    }
+
+    /// kill closes every connection and refuses the new ones until `revive`
+    pub fn kill(&self) {
+        self.state.alive.send_replace(false);
+    }
+
+    /// revive accepts connections again after `kill`
+    pub fn revive(&self) {
+        self.state.alive.send_replace(true);
+    }
+
+    /// connections returns the number of connections accepted so far, reconnections included
+    pub fn connections(&self) -> usize {
+        self.state.connections.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for MockSlotServer {
+    fn drop(&mut self) {
+        self.kill();
+        self.accepting.abort();
+    }
+}
+
+/// serve answers the subscription of the connection and pushes the slots until the server is
+/// killed or the client leaves
+async fn serve(stream: TcpStream, state: Arc<State>) {
+    let Ok(ws_stream) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    let (mut write, mut read) = ws_stream.split();
+    let mut slots = state.slots.subscribe();
+    let mut alive = state.alive.subscribe();
+    let mut subscription = None;
+    loop {
+        tokio::select! {
+            message = read.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let Ok(request) = serde_json::from_str::<serde_json::Value>(&text) else {
+                        continue;
+                    };
+                    if request["method"] == "slotSubscribe" {
+                        let response = json!({ "jsonrpc": "2.0", "result": 0, "id": request["id"] });
+                        if write.send(Message::Text(response.to_string())).await.is_err() {
+                            return;
+                        }
+                        subscription = Some(0);
+                    }
+                }
+                Some(Ok(_)) => {}
+                _ => return,
+            },
+            slot = slots.recv() => match slot {
+                Ok(slot) => {
+                    let Some(subscription) = subscription else {
+                        continue;
+                    };
+                    if write.send(Message::Text(notification(slot, subscription))).await.is_err() {
+                        return;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = killed(&mut alive) => {
+                let _ = write.close().await;
+                return;
+            }
+        }
+    }
+}
+
+/// killed waits until the server is killed
+async fn killed(alive: &mut watch::Receiver<bool>) {
+    let _ = alive.wait_for(|alive| !alive).await;
+}
+
+/// notification returns the `slotNotification` of the slot
+fn notification(slot: Slot, subscription: u64) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "slotNotification",
+        "params": {
+            "result": {
+                "parent": slot.saturating_sub(1),
+                "root": slot.saturating_sub(32),
+                "slot": slot,
+            },
+            "subscription": subscription,
+        },
+    })
+    .to_string()
+}
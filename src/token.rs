@@ -0,0 +1,189 @@
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token_2022::{
+    extension::{
+        transfer_fee::{instruction::transfer_checked_with_fee, TransferFeeConfig},
+        BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+    },
+    state::{Account, Mint},
+};
+use tracing::info;
+
+#[derive(Debug, Clone)]
+pub struct TokenTransfer {
+    pub mint: Pubkey,
+    /// Amount in the token's base units
+    pub amount: u64,
+    pub decimals: u8,
+    /// Token program owning the mint, either SPL Token or Token-2022
+    pub program_id: Pubkey,
+    /// Fee withheld by the Token-2022 transfer fee extension
+    pub transfer_fee: Option<u64>,
+    /// Create the receiver's associated token account in the same transaction
+    pub create_destination: bool,
+    /// Data length of a token account for the mint, determines the rent of a created account
+    pub account_len: usize,
+}
+
+impl TokenTransfer {
+    /// new fetches the mint account to resolve the token program, the decimals required by
+    /// `transfer_checked` and the transfer fee of Token-2022 mints
+    pub async fn new(rpc_client: &RpcClient, mint: Pubkey, amount: u64) -> Result<Self, String> {
+        let account = rpc_client
+            .get_account(&mint)
+            .await
+            .map_err(|e| format!("Error getting mint account {}: {}", mint, e))?;
+
+        let program_id = account.owner;
+        if program_id != spl_token::id() && program_id != spl_token_2022::id() {
+            return Err(format!(
+                "Mint {} is owned by {}, not a token program",
+                mint, program_id
+            ));
+        }
+
+        // Token-2022 mints share the base layout, legacy mints simply have no extensions
+        let mint_state = StateWithExtensions::<Mint>::unpack(&account.data)
+            .map_err(|e| format!("Account {} is not a token mint: {}", mint, e))?;
+
+        let account_len = if program_id == spl_token_2022::id() {
+            let mint_extensions = mint_state
+                .get_extension_types()
+                .map_err(|e| format!("Invalid extensions on mint {}: {}", mint, e))?;
+            // associated token accounts of Token-2022 are always created with an immutable owner
+            let mut account_extensions =
+                ExtensionType::get_required_init_account_extensions(&mint_extensions);
+            account_extensions.push(ExtensionType::ImmutableOwner);
+            ExtensionType::try_calculate_account_len::<Account>(&account_extensions)
+                .map_err(|e| format!("Error calculating token account size: {}", e))?
+        } else {
+            Account::LEN
+        };
+
+        let transfer_fee = match mint_state.get_extension::<TransferFeeConfig>() {
+            Ok(transfer_fee_config) => {
+                let epoch = rpc_client
+                    .get_epoch_info()
+                    .await
+                    .map_err(|e| format!("Error getting epoch info: {}", e))?
+                    .epoch;
+                let fee = transfer_fee_config
+                    .calculate_epoch_fee(epoch, amount)
+                    .ok_or_else(|| format!("Transfer fee overflow for amount {}", amount))?;
+                Some(fee)
+            }
+            Err(_) => None,
+        };
+
+        let token_transfer = Self {
+            mint,
+            amount,
+            decimals: mint_state.base.decimals,
+            program_id,
+            transfer_fee,
+            create_destination: false,
+            account_len,
+        };
+        if let Some(fee) = token_transfer.transfer_fee {
+            info!(
+                "[ TRANSFER FEE ] - {} withheld, {} received",
+                fee,
+                token_transfer.amount_received()
+            );
+        }
+        Ok(token_transfer)
+    }
+
+    /// check_destination verifies the receiver's associated token account exists. A missing
+    /// account is created in the transfer transaction if `create_ata` is set, otherwise it's an
+    /// error since the transfer would fail on-chain
+    pub async fn check_destination(
+        &mut self,
+        rpc_client: &RpcClient,
+        receiver: &Pubkey,
+        create_ata: bool,
+    ) -> Result<(), String> {
+        let destination =
+            get_associated_token_address_with_program_id(receiver, &self.mint, &self.program_id);
+        let account = rpc_client
+            .get_account_with_commitment(&destination, rpc_client.commitment())
+            .await
+            .map_err(|e| format!("Error getting token account {}: {}", destination, e))?
+            .value;
+
+        match account {
+            Some(_) => Ok(()),
+            None if create_ata => {
+                info!("[ CREATE ATA ] - {} for {}", destination, receiver);
+                self.create_destination = true;
+                Ok(())
+            }
+            None => Err(format!(
+                "Receiver {} has no token account for mint {}, use --create-ata",
+                receiver, self.mint
+            )),
+        }
+    }
+
+    /// amount_received returns the amount credited to the receiver after the transfer fee
+    pub fn amount_received(&self) -> u64 {
+        self.amount - self.transfer_fee.unwrap_or(0)
+    }
+
+    /// token_accounts returns the sender and receiver associated token accounts for the mint
+    pub fn token_accounts(&self, sender: &Pubkey, receiver: &Pubkey) -> (Pubkey, Pubkey) {
+        (
+            get_associated_token_address_with_program_id(sender, &self.mint, &self.program_id),
+            get_associated_token_address_with_program_id(receiver, &self.mint, &self.program_id),
+        )
+    }
+
+    /// instructions returns the transfer, preceded by the idempotent creation of the receiver's
+    /// associated token account if requested
+    pub fn instructions(&self, sender: &Pubkey, receiver: &Pubkey) -> Vec<Instruction> {
+        let mut instructions = Vec::with_capacity(2);
+        if self.create_destination {
+            instructions.push(create_associated_token_account_idempotent(
+                sender,
+                receiver,
+                &self.mint,
+                &self.program_id,
+            ));
+        }
+        instructions.push(self.transfer_instruction(sender, receiver));
+        instructions
+    }
+
+    fn transfer_instruction(&self, sender: &Pubkey, receiver: &Pubkey) -> Instruction {
+        let (source, destination) = self.token_accounts(sender, receiver);
+        match self.transfer_fee {
+            // asserts the fee on-chain, so a fee change between epochs fails instead of overpaying
+            Some(fee) => transfer_checked_with_fee(
+                &self.program_id,
+                &source,
+                &self.mint,
+                &destination,
+                sender,
+                &[],
+                self.amount,
+                self.decimals,
+                fee,
+            ),
+            None => spl_token_2022::instruction::transfer_checked(
+                &self.program_id,
+                &source,
+                &self.mint,
+                &destination,
+                sender,
+                &[],
+                self.amount,
+                self.decimals,
+            ),
+        }
+        .expect("program id is checked to be a token program")
+    }
+}
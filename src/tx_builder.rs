@@ -0,0 +1,272 @@
+use std::{
+    fs,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Slot,
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::Transaction,
+};
+use tracing::{info, instrument};
+
+use crate::{
+    amount, blockhash_cache::BlockhashCache, config::Config, error::SendError, fees,
+    rpc_api::RpcApi,
+};
+
+/// build_transaction builds the message described by the config and signs it, returning it with
+/// the blockhash it was built on
+pub async fn build_transaction(
+    rpc_client: &RpcClient,
+    config: &Config,
+    tracker_slot: Option<Slot>,
+) -> Result<(Transaction, RecentBlockhash), SendError> {
+    let (message, recent_blockhash) = build_message(rpc_client, config, tracker_slot).await?;
+    let blockhash = message.recent_blockhash;
+    let transaction = config.sign_message(message, blockhash)?;
+    Ok((transaction, recent_blockhash))
+}
+
+/// build_message fetches a blockhash and resolves the compute budget for the message described by
+/// the config, after checking the fee limit, the packet size and the balances. The blockhash is
+/// returned with the message
+pub async fn build_message(
+    rpc_client: &RpcClient,
+    config: &Config,
+    tracker_slot: Option<Slot>,
+) -> Result<(Message, RecentBlockhash), SendError> {
+    let recent_blockhash = recent_blockhash(rpc_client, config, tracker_slot).await?;
+    let message =
+        build_message_with_blockhash(rpc_client, config, recent_blockhash.blockhash).await?;
+    Ok((message, recent_blockhash))
+}
+
+/// build_message_with_blockhash is `build_message` on a blockhash fetched earlier
+pub async fn build_message_with_blockhash(
+    rpc_client: &RpcClient,
+    config: &Config,
+    blockhash: Hash,
+) -> Result<Message, SendError> {
+    let compute_unit_limit = fees::get_compute_unit_limit(rpc_client, config, blockhash).await;
+    let compute_unit_price = fees::get_compute_unit_price(rpc_client, config).await;
+    let mut message = config.create_message(blockhash, compute_unit_limit, compute_unit_price)?;
+    let fee = fees::expected_fee(&message, compute_unit_limit, compute_unit_price);
+    let mut lamports = config.amount;
+    if config.sweep {
+        // the fee doesn't depend on the amount, so the message is rebuilt with the remainder
+        lamports = amount::sweep_amount(rpc_client, config, fee).await?;
+        let sweep_config = Config {
+            amount: lamports,
+            ..config.clone()
+        };
+        message = sweep_config.create_message(blockhash, compute_unit_limit, compute_unit_price)?;
+    }
+
+    if let Some(max_fee) = config.max_fee_lamports {
+        if fee > max_fee {
+            return Err(SendError::FeeTooHigh { fee, max_fee });
+        }
+    }
+    config.check_size(&message)?;
+    amount::check_balance(rpc_client, config, lamports, fee).await?;
+
+    Ok(message)
+}
+
+/// PresignedTransaction is a transaction built and signed ahead of its send window, held with its
+/// serialized bytes so sending it is a single stream write, see
+/// `QuicManager::send_presigned_transaction`
+#[derive(Debug, Clone)]
+pub struct PresignedTransaction {
+    pub transaction: Transaction,
+    /// Wire format of the transaction
+    pub serialized: Vec<u8>,
+    /// Last block height the transaction can land at
+    pub last_valid_block_height: u64,
+}
+
+impl PresignedTransaction {
+    pub fn signature(&self) -> Signature {
+        self.transaction.signatures[0]
+    }
+}
+
+/// presign builds the transaction described by the config on a cached blockhash, signs and
+/// serializes it, and records it in the audit log, so none of that is left for the send. The
+/// compute budget and the balance are still resolved over RPC, ahead of the send
+pub async fn presign(
+    rpc_client: &RpcClient,
+    config: &Config,
+    recent_blockhash: &RecentBlockhash,
+) -> Result<PresignedTransaction, SendError> {
+    let blockhash = recent_blockhash.blockhash;
+    let message = build_message_with_blockhash(rpc_client, config, blockhash).await?;
+    let transaction = config.sign_message(message, blockhash)?;
+    let serialized = bincode::serialize(&transaction)
+        .map_err(|e| format!("Failed to serialize transaction: {}", e))?;
+    config.audit(&transaction)?;
+    Ok(PresignedTransaction {
+        transaction,
+        serialized,
+        last_valid_block_height: recent_blockhash.last_valid_block_height,
+    })
+}
+
+/// RecentBlockhash is a blockhash with the last block height at which transactions using it are
+/// still accepted
+#[derive(Debug, Clone, Copy)]
+pub struct RecentBlockhash {
+    pub blockhash: Hash,
+    pub last_valid_block_height: u64,
+    /// How long fetching it took
+    pub latency: Duration,
+}
+
+/// recent_blockhash takes the blockhash from the WebSocket cache of the config if it's recent
+/// enough for the tracker's slot, like `get_blockhash` requires from the RPC node, and fetches it
+/// otherwise
+pub async fn recent_blockhash(
+    rpc_client: &RpcClient,
+    config: &Config,
+    tracker_slot: Option<Slot>,
+) -> Result<RecentBlockhash, String> {
+    let min_slot =
+        tracker_slot.map(|slot| slot.saturating_sub(commitment_lag(config.commitment_level)));
+    let cached = config
+        .blockhash_cache
+        .as_ref()
+        .and_then(BlockhashCache::latest)
+        .filter(|cached| min_slot.is_none_or(|min_slot| cached.slot >= min_slot));
+    match cached {
+        Some(cached) => {
+            info!(
+                "[ BLOCKHASH ] - {} from the WebSocket (slot {}, min {:?}, valid until block height {})",
+                cached.blockhash, cached.slot, min_slot, cached.last_valid_block_height
+            );
+            Ok(RecentBlockhash {
+                blockhash: cached.blockhash,
+                last_valid_block_height: cached.last_valid_block_height,
+                latency: Duration::ZERO,
+            })
+        }
+        None => get_blockhash(rpc_client, config.commitment_level, tracker_slot).await,
+    }
+}
+
+/// get_blockhash fetches the latest blockhash at the commitment level. With the tracker's slot known
+/// it also requires the RPC node to have reached it, minus the usual lag of that commitment, so a
+/// lagging node can't hand out an old blockhash with a shorter validity window
+#[instrument(name = "blockhash_fetch", skip_all)]
+pub async fn get_blockhash(
+    rpc_client: &dyn RpcApi,
+    commitment: CommitmentConfig,
+    tracker_slot: Option<Slot>,
+) -> Result<RecentBlockhash, String> {
+    let min_context_slot = tracker_slot.map(|slot| slot.saturating_sub(commitment_lag(commitment)));
+    let started = Instant::now();
+    let response = rpc_client
+        .get_latest_blockhash(commitment, min_context_slot)
+        .await
+        .map_err(|e| format!("Failed to get blockhash: {}", e))?;
+
+    let blockhash = Hash::from_str(&response.value.blockhash)
+        .map_err(|e| format!("Invalid blockhash: {}", e))?;
+    let last_valid_block_height = response.value.last_valid_block_height;
+    info!(
+        "[ BLOCKHASH ] - {} (context slot {}, min {:?}, valid until block height {})",
+        blockhash, response.context.slot, min_context_slot, last_valid_block_height
+    );
+    Ok(RecentBlockhash {
+        blockhash,
+        last_valid_block_height,
+        latency: started.elapsed(),
+    })
+}
+
+/// commitment_lag is how many slots the bank of a commitment level may trail the slot seen by the
+/// tracker, with some headroom
+fn commitment_lag(commitment: CommitmentConfig) -> u64 {
+    match commitment.commitment {
+        CommitmentLevel::Processed => 4,
+        CommitmentLevel::Confirmed => 8,
+        _ => 48,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct InstructionSpec {
+    program_id: String,
+    #[serde(default)]
+    accounts: Vec<AccountSpec>,
+    /// Instruction data encoded as base64
+    #[serde(default)]
+    data: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountSpec {
+    pubkey: String,
+    #[serde(default)]
+    is_signer: bool,
+    #[serde(default)]
+    is_writable: bool,
+}
+
+/// read_instructions parses a JSON array of instructions, each with a program id, its accounts
+/// with signer/writable flags and base64 data
+pub fn read_instructions(path: &str) -> Result<Vec<Instruction>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let specs: Vec<InstructionSpec> = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse instructions from {}: {}", path, e))?;
+
+    if specs.is_empty() {
+        return Err(format!("No instructions found in {}", path));
+    }
+
+    specs
+        .into_iter()
+        .enumerate()
+        .map(|(index, spec)| {
+            parse_instruction(spec).map_err(|e| format!("Instruction {}: {}", index, e))
+        })
+        .collect()
+}
+
+fn parse_instruction(spec: InstructionSpec) -> Result<Instruction, String> {
+    let program_id = parse_pubkey(&spec.program_id)?;
+    let accounts = spec
+        .accounts
+        .iter()
+        .map(|account| {
+            Ok(AccountMeta {
+                pubkey: parse_pubkey(&account.pubkey)?,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    let data = STANDARD
+        .decode(&spec.data)
+        .map_err(|e| format!("Invalid base64 data: {}", e))?;
+
+    Ok(Instruction {
+        program_id,
+        accounts,
+        data,
+    })
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(pubkey).map_err(|e| format!("Invalid pubkey {}: {}", pubkey, e))
+}
@@ -0,0 +1,85 @@
+use num_traits::FromPrimitive;
+use solana_sdk::{
+    compute_budget, instruction::InstructionError, pubkey::Pubkey, system_instruction::SystemError,
+    system_program, transaction::TransactionError,
+};
+use spl_associated_token_account::error::AssociatedTokenAccountError;
+
+/// explain turns a transaction error into a readable explanation. `program_ids` are the programs
+/// invoked by each instruction of the transaction, used to decode custom program errors
+pub fn explain(err: &TransactionError, program_ids: &[Pubkey]) -> String {
+    match err {
+        TransactionError::InstructionError(index, instruction_error) => {
+            let program_id = program_ids.get(*index as usize);
+            format!(
+                "instruction {} ({}) failed: {}",
+                index,
+                program_id.map_or("unknown program".to_string(), program_name),
+                explain_instruction_error(instruction_error, program_id)
+            )
+        }
+        TransactionError::BlockhashNotFound => {
+            format!(
+                "{} — it expired before the transaction landed, rebuild with a fresh blockhash",
+                err
+            )
+        }
+        TransactionError::AccountNotFound => {
+            format!("{} — the fee payer has no SOL", err)
+        }
+        TransactionError::InsufficientFundsForFee => {
+            format!("{} — the fee payer can't cover the fee", err)
+        }
+        TransactionError::InsufficientFundsForRent { account_index } => format!(
+            "{} — account {} would be left below the rent-exempt minimum",
+            err, account_index
+        ),
+        TransactionError::AlreadyProcessed => {
+            format!("{} — an identical transaction has already landed", err)
+        }
+        _ => err.to_string(),
+    }
+}
+
+fn explain_instruction_error(err: &InstructionError, program_id: Option<&Pubkey>) -> String {
+    let InstructionError::Custom(code) = err else {
+        return err.to_string();
+    };
+
+    let decoded = program_id.and_then(|program_id| {
+        if *program_id == system_program::id() {
+            SystemError::from_u32(*code).map(|e| e.to_string())
+        } else if *program_id == spl_token::id() {
+            spl_token::error::TokenError::from_u32(*code).map(|e| e.to_string())
+        } else if *program_id == spl_token_2022::id() {
+            spl_token_2022::error::TokenError::from_u32(*code).map(|e| e.to_string())
+        } else if *program_id == spl_associated_token_account::id() {
+            AssociatedTokenAccountError::from_u32(*code).map(|e| e.to_string())
+        } else {
+            None
+        }
+    });
+
+    match decoded {
+        Some(decoded) => format!("custom program error {:#x} — {}", code, decoded),
+        None => format!("custom program error {:#x}", code),
+    }
+}
+
+fn program_name(program_id: &Pubkey) -> String {
+    if *program_id == system_program::id() {
+        "System Program".to_string()
+    } else if *program_id == compute_budget::id() {
+        "Compute Budget".to_string()
+    } else if *program_id == spl_token::id() {
+        "SPL Token".to_string()
+    } else if *program_id == spl_token_2022::id() {
+        "Token-2022".to_string()
+    } else if *program_id == spl_associated_token_account::id() {
+        "Associated Token Account".to_string()
+    } else if *program_id == spl_memo::id() {
+        "Memo".to_string()
+    } else {
+        program_id.to_string()
+    }
+}
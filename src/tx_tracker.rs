@@ -0,0 +1,388 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use dashmap::DashMap;
+use solana_sdk::{clock::Slot, hash::Hash, signature::Signature, transaction::Transaction};
+use tokio::sync::broadcast;
+use tracing::info;
+
+use crate::tx_builder::RecentBlockhash;
+
+const EVENT_CAPACITY: usize = 1024;
+/// Transactions in flight whose wire format is kept for the resends, the ones beyond are
+/// serialized again for every send
+const MAX_SERIALIZED: usize = 4096;
+
+/// RequestId correlates the spans, log lines, events and result of one submission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestId(u64);
+
+impl RequestId {
+    pub fn generate() -> Self {
+        Self(rand::random())
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// TxState is the lifecycle state of a submitted transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxState {
+    /// Signed, not sent yet
+    Built,
+    /// Sent to a leader while the tracker was at the slot, delivering it took `latency`
+    Sent {
+        leader: String,
+        slot: Option<Slot>,
+        latency: Duration,
+    },
+    /// Reached the confirmation commitment, landed in the slot
+    Confirmed {
+        slot: Slot,
+    },
+    Failed {
+        error: String,
+    },
+    /// The blockhash expired before confirmation, it can no longer land
+    Expired,
+}
+
+impl TxState {
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TxState::Confirmed { .. } | TxState::Failed { .. } | TxState::Expired
+        )
+    }
+}
+
+/// TxEvent is the progress of a send, emitted as it happens, with the ID of the submission
+#[derive(Debug, Clone)]
+pub enum TxEvent {
+    /// A blockhash was fetched to build the next transaction on, fetching it took `latency`
+    BlockhashFetched {
+        request_id: RequestId,
+        blockhash: Hash,
+        last_valid_block_height: u64,
+        latency: Duration,
+    },
+    /// A signature moved to a new lifecycle state
+    Transition {
+        request_id: RequestId,
+        signature: Signature,
+        state: TxState,
+    },
+    /// The attempt failed and the next one starts
+    Retry {
+        request_id: RequestId,
+        attempt: u8,
+        error: String,
+    },
+    /// Delivering the transaction to the leader failed
+    SendFailed {
+        request_id: RequestId,
+        signature: Signature,
+        leader: String,
+        error: String,
+    },
+}
+
+impl TxEvent {
+    pub fn request_id(&self) -> RequestId {
+        match self {
+            TxEvent::BlockhashFetched { request_id, .. }
+            | TxEvent::Transition { request_id, .. }
+            | TxEvent::Retry { request_id, .. }
+            | TxEvent::SendFailed { request_id, .. } => *request_id,
+        }
+    }
+}
+
+/// TxStates is a read-only view of the states of a tracker
+#[derive(Debug, Clone)]
+pub struct TxStates(Arc<DashMap<Signature, TxState>>);
+
+impl TxStates {
+    /// snapshot returns the current state of every tracked signature
+    pub fn snapshot(&self) -> Vec<(Signature, TxState)> {
+        self.0
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect()
+    }
+}
+
+/// TxTracker owns the lifecycle state of every submitted signature. The sender and confirmation
+/// paths report their progress, which can be queried by signature or followed as events. The
+/// events are tagged with the request ID of the tracker, see `for_request`
+#[derive(Debug, Clone)]
+pub struct TxTracker {
+    states: Arc<DashMap<Signature, TxState>>,
+    /// Wire format of the transactions in flight, serialized once for every send and resend
+    serialized: Arc<DashMap<Signature, Arc<[u8]>>>,
+    events: broadcast::Sender<TxEvent>,
+    request_id: RequestId,
+}
+
+impl Default for TxTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TxTracker {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            states: Arc::new(DashMap::new()),
+            serialized: Arc::new(DashMap::new()),
+            events,
+            request_id: RequestId::generate(),
+        }
+    }
+
+    /// for_request returns a tracker sharing the states and the events of this one, tagging them
+    /// with a new request ID, to follow another submission through it
+    pub fn for_request(&self) -> Self {
+        Self {
+            request_id: RequestId::generate(),
+            ..self.clone()
+        }
+    }
+
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+
+    /// subscribe returns a receiver of the events emitted from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<TxEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn get(&self, signature: &Signature) -> Option<TxState> {
+        self.states.get(signature).map(|state| state.clone())
+    }
+
+    /// snapshot returns the current state of every tracked signature
+    pub fn snapshot(&self) -> Vec<(Signature, TxState)> {
+        self.states().snapshot()
+    }
+
+    /// states returns a read-only view of the states, which unlike a clone of the tracker doesn't
+    /// keep the listeners waiting for events
+    pub fn states(&self) -> TxStates {
+        TxStates(self.states.clone())
+    }
+
+    /// serialized returns the wire format of the transaction, serialized on its first send and
+    /// shared by the following ones until it reaches a final state
+    pub fn serialized(&self, transaction: &Transaction) -> Result<Arc<[u8]>, String> {
+        let signature = *transaction
+            .signatures
+            .first()
+            .ok_or("No signature found in the transaction")?;
+        if let Some(serialized) = self.serialized.get(&signature) {
+            return Ok(serialized.clone());
+        }
+        let serialized: Arc<[u8]> = bincode::serialize(transaction)
+            .map_err(|e| format!("Failed to serialize transaction: {}", e))?
+            .into();
+        if self.serialized.len() < MAX_SERIALIZED
+            && !self.get(&signature).is_some_and(|state| state.is_final())
+        {
+            self.serialized.insert(signature, serialized.clone());
+        }
+        Ok(serialized)
+    }
+
+    /// superseded drops the wire format of a transaction replaced by a re-signed one, which is
+    /// never sent again even though it may still land
+    pub fn superseded(&self, signature: &Signature) {
+        self.serialized.remove(signature);
+    }
+
+    pub fn blockhash_fetched(&self, recent_blockhash: &RecentBlockhash) {
+        self.emit(TxEvent::BlockhashFetched {
+            request_id: self.request_id,
+            blockhash: recent_blockhash.blockhash,
+            last_valid_block_height: recent_blockhash.last_valid_block_height,
+            latency: recent_blockhash.latency,
+        });
+    }
+
+    /// retry reports that attempt number `attempt` starts after the previous one failed
+    pub fn retry(&self, attempt: u8, error: String) {
+        self.emit(TxEvent::Retry {
+            request_id: self.request_id,
+            attempt,
+            error,
+        });
+    }
+
+    pub fn send_failed(&self, signature: Signature, leader: String, error: String) {
+        self.emit(TxEvent::SendFailed {
+            request_id: self.request_id,
+            signature,
+            leader,
+            error,
+        });
+    }
+
+    pub fn built(&self, signature: Signature) {
+        self.transition(signature, TxState::Built);
+    }
+
+    pub fn sent(
+        &self,
+        signature: Signature,
+        leader: String,
+        slot: Option<Slot>,
+        latency: Duration,
+    ) {
+        self.transition(
+            signature,
+            TxState::Sent {
+                leader,
+                slot,
+                latency,
+            },
+        );
+    }
+
+    pub fn confirmed(&self, signature: Signature, slot: Slot) {
+        self.transition(signature, TxState::Confirmed { slot });
+    }
+
+    pub fn failed(&self, signature: Signature, error: String) {
+        self.transition(signature, TxState::Failed { error });
+    }
+
+    pub fn expired(&self, signature: Signature) {
+        self.transition(signature, TxState::Expired);
+    }
+
+    /// transition records the new state, a final state is never left since a late report about a
+    /// resent copy can't undo it
+    fn transition(&self, signature: Signature, state: TxState) {
+        if let Some(current) = self.states.get(&signature) {
+            if current.is_final() {
+                return;
+            }
+        }
+        if state.is_final() {
+            self.serialized.remove(&signature);
+        }
+        self.states.insert(signature, state.clone());
+        self.emit(TxEvent::Transition {
+            request_id: self.request_id,
+            signature,
+            state,
+        });
+    }
+
+    fn emit(&self, event: TxEvent) {
+        // nobody listening is fine
+        let _ = self.events.send(event);
+    }
+}
+
+/// log_events logs every event until the tracker is dropped, with the request ID, signature,
+/// leader, slot and attempt as fields
+pub async fn log_events(mut events: broadcast::Receiver<TxEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                info!(skipped, "[ TX ] - events skipped");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let request_id = event.request_id();
+        match event {
+            TxEvent::BlockhashFetched {
+                blockhash,
+                last_valid_block_height,
+                latency,
+                ..
+            } => info!(
+                %request_id,
+                %blockhash,
+                last_valid_block_height,
+                ?latency,
+                "[ TX ] - blockhash fetched"
+            ),
+            TxEvent::Transition {
+                signature, state, ..
+            } => match state {
+                TxState::Built => info!(%request_id, %signature, "[ TX ] - built"),
+                TxState::Sent {
+                    leader,
+                    slot,
+                    latency,
+                } => info!(%request_id, %signature, %leader, slot, ?latency, "[ TX ] - sent"),
+                TxState::Confirmed { slot } => {
+                    info!(%request_id, %signature, slot, "[ TX ] - confirmed")
+                }
+                TxState::Failed { error } => {
+                    info!(%request_id, %signature, %error, "[ TX ] - failed")
+                }
+                TxState::Expired => info!(%request_id, %signature, "[ TX ] - expired"),
+            },
+            TxEvent::Retry { attempt, error, .. } => {
+                info!(%request_id, attempt, %error, "[ TX ] - retrying")
+            }
+            TxEvent::SendFailed {
+                signature,
+                leader,
+                error,
+                ..
+            } => info!(%request_id, %signature, %leader, %error, "[ TX ] - not delivered"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Keypair, system_transaction};
+
+    use super::*;
+
+    fn transfer(lamports: u64) -> Transaction {
+        system_transaction::transfer(
+            &Keypair::new(),
+            &Pubkey::new_unique(),
+            lamports,
+            Hash::default(),
+        )
+    }
+
+    #[test]
+    fn wire_format_is_dropped_once_superseded_or_final() {
+        let tx_tracker = TxTracker::new();
+        let (superseded, expired) = (transfer(1), transfer(2));
+        tx_tracker.serialized(&superseded).unwrap();
+        tx_tracker.serialized(&expired).unwrap();
+        assert_eq!(tx_tracker.serialized.len(), 2);
+
+        tx_tracker.superseded(&superseded.signatures[0]);
+        tx_tracker.expired(expired.signatures[0]);
+
+        assert!(tx_tracker.serialized.is_empty());
+    }
+
+    #[test]
+    fn wire_format_cache_is_bounded() {
+        let tx_tracker = TxTracker::new();
+        for lamports in 0..MAX_SERIALIZED as u64 + 10 {
+            let transaction = transfer(lamports);
+            let serialized = tx_tracker.serialized(&transaction).unwrap();
+            assert_eq!(*serialized, *bincode::serialize(&transaction).unwrap());
+        }
+
+        assert_eq!(tx_tracker.serialized.len(), MAX_SERIALIZED);
+    }
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::Serialize;
+use sha2::Sha256;
+use solana_sdk::clock::Slot;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{error, info};
+
+use crate::tx_tracker::{TxEvent, TxState};
+
+/// Environment variable holding the secret the webhook payloads are signed with
+pub const WEBHOOK_SECRET_ENV: &str = "WEBHOOK_SECRET";
+/// Header carrying the hex HMAC-SHA256 of the payload, prefixed with `sha256=`
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+const MAX_ATTEMPTS: u32 = 3;
+/// Time a single POST may take before it's retried
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Payload is the JSON body posted for every transaction reaching a final state
+#[derive(Debug, Serialize)]
+pub struct Payload {
+    pub request_id: String,
+    pub signature: String,
+    /// `confirmed`, `failed` or `expired`
+    pub status: &'static str,
+    pub slot: Option<Slot>,
+    pub error: Option<String>,
+}
+
+impl Payload {
+    /// from_event returns the payload of a transition to a final state
+    pub fn from_event(event: &TxEvent) -> Option<Self> {
+        let TxEvent::Transition {
+            request_id,
+            signature,
+            state,
+        } = event
+        else {
+            return None;
+        };
+        let (status, slot, error) = match state {
+            TxState::Confirmed { slot } => ("confirmed", Some(*slot), None),
+            TxState::Failed { error } => ("failed", None, Some(error.clone())),
+            TxState::Expired => ("expired", None, None),
+            TxState::Built | TxState::Sent { .. } => return None,
+        };
+        Some(Self {
+            request_id: request_id.to_string(),
+            signature: signature.to_string(),
+            status,
+            slot,
+            error,
+        })
+    }
+}
+
+/// Alert is the JSON body posted when something needs attention outside of a transaction
+#[derive(Debug, Serialize)]
+pub struct Alert {
+    /// Kind of the alert, e.g. `slot_lag`
+    pub alert: &'static str,
+    pub message: String,
+    /// Value that crossed the threshold
+    pub value: i64,
+}
+
+/// Webhook posts send outcomes to a URL, signing the payload with HMAC-SHA256 when
+/// `WEBHOOK_SECRET` is set
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    client: Client,
+    url: String,
+    secret: Option<String>,
+}
+
+impl Webhook {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("HTTP client builds with the default TLS backend"),
+            url,
+            secret: std::env::var(WEBHOOK_SECRET_ENV).ok(),
+        }
+    }
+
+    /// notify posts the payload, retrying with a growing delay on errors
+    pub async fn notify(&self, payload: &impl Serialize) -> Result<(), String> {
+        let body = serde_json::to_vec(payload)
+            .map_err(|e| format!("Failed to serialize webhook payload: {}", e))?;
+
+        let mut last_error = String::new();
+        for attempt in 0..MAX_ATTEMPTS {
+            if attempt > 0 {
+                tokio::time::sleep(Duration::from_secs(1 << (attempt - 1))).await;
+            }
+            let mut request = self
+                .client
+                .post(&self.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(secret) = &self.secret {
+                request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+            }
+            match request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+            {
+                Ok(_) => return Ok(()),
+                Err(e) => last_error = e.to_string(),
+            }
+        }
+        Err(format!(
+            "Failed to notify webhook {} after {} attempts: {}",
+            self.url, MAX_ATTEMPTS, last_error
+        ))
+    }
+
+    /// run posts every final state reported by the tracker until it's dropped. The payloads are
+    /// queued and posted apart from the events, so a slow or retried POST never makes the listener
+    /// fall behind the tracker and skip outcomes
+    pub async fn run(self, mut events: broadcast::Receiver<TxEvent>) {
+        let (queue, mut queued) = mpsc::unbounded_channel();
+        let events = async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        error!("[ WEBHOOK ] - {} events skipped", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if let Some(payload) = Payload::from_event(&event) {
+                    let _ = queue.send(payload);
+                }
+            }
+        };
+        let poster = async {
+            while let Some(payload) = queued.recv().await {
+                match self.notify(&payload).await {
+                    Ok(()) => info!("[ WEBHOOK ] - {} {}", payload.signature, payload.status),
+                    Err(e) => error!("[ WEBHOOK ] - {}", e),
+                }
+            }
+        };
+        tokio::join!(events, poster);
+    }
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signature::Signature;
+    use wiremock::{matchers, Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::tx_tracker::RequestId;
+
+    #[tokio::test]
+    async fn slow_posts_do_not_skip_outcomes() {
+        let server = MockServer::start().await;
+        Mock::given(matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(50)))
+            .mount(&server)
+            .await;
+        let (events, receiver) = broadcast::channel(2);
+        let webhook = tokio::spawn(Webhook::new(server.uri()).run(receiver));
+
+        let request_id = RequestId::generate();
+        for slot in 0..10 {
+            events
+                .send(TxEvent::Transition {
+                    request_id,
+                    signature: Signature::new_unique(),
+                    state: TxState::Confirmed { slot },
+                })
+                .unwrap();
+            tokio::task::yield_now().await;
+        }
+        drop(events);
+        webhook.await.unwrap();
+
+        assert_eq!(server.received_requests().await.unwrap().len(), 10);
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use futures_util::future::join_all;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{signature::Signature, transaction::Transaction};
+use tracing::{error, info};
+
+use crate::{
+    config::Config,
+    error::SendError,
+    fees,
+    leader_tracker::LeaderTracker,
+    quic_manager::{self, ConnectionPool, QuicManager},
+    tx_tracker::TxTracker,
+};
+
+/// Number of concurrent sender workers, unless configured
+pub const DEFAULT_WORKERS: usize = 8;
+
+/// SenderPool sends signed transactions to the upcoming leaders with a number of workers pulling
+/// them from a shared queue. The workers share the QUIC connections, the leader tracker and the
+/// transaction tracker, so a slow leader only holds up the worker sending to it
+pub struct SenderPool<'a, T> {
+    config: &'a Config,
+    rpc_client: Arc<RpcClient>,
+    tracker: &'a T,
+    tx_tracker: &'a TxTracker,
+    connections: ConnectionPool,
+}
+
+impl<'a, T: LeaderTracker> SenderPool<'a, T> {
+    pub fn new(
+        config: &'a Config,
+        rpc_client: Arc<RpcClient>,
+        tracker: &'a T,
+        tx_tracker: &'a TxTracker,
+    ) -> Self {
+        Self {
+            config,
+            rpc_client,
+            tracker,
+            tx_tracker,
+            connections: quic_manager::connection_pool(),
+        }
+    }
+
+    /// send_all sends the transactions with `config.workers` workers and returns their results in
+    /// the order of the transactions. A transaction costing more than `max_fee_lamports` at the
+    /// compute unit price isn't sent
+    pub async fn send_all(
+        &self,
+        transactions: &[&Transaction],
+        compute_unit_price: u64,
+    ) -> Vec<Result<Signature, SendError>> {
+        let queue = Mutex::new(
+            transactions
+                .iter()
+                .copied()
+                .enumerate()
+                .collect::<VecDeque<_>>(),
+        );
+        let results = Mutex::new(
+            (0..transactions.len())
+                .map(|_| None)
+                .collect::<Vec<Option<Result<Signature, SendError>>>>(),
+        );
+        let workers = self.config.workers.clamp(1, transactions.len().max(1));
+
+        join_all((0..workers).map(|_| async {
+            loop {
+                // the queue is unlocked again before the send
+                let Some((index, transaction)) = lock(&queue).pop_front() else {
+                    break;
+                };
+                let result = self.send(transaction, compute_unit_price).await;
+                if let Err(e) = &result {
+                    error!("Error sending transaction: {}", e);
+                }
+                lock(&results)[index] = Some(result);
+            }
+        }))
+        .await;
+        info!(
+            "[ WORKERS ] - {} transactions sent by {} workers over {} connections",
+            transactions.len(),
+            workers,
+            self.connections.len()
+        );
+
+        results
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|| Err(SendError::Other("Transaction not sent".to_string())))
+            })
+            .collect()
+    }
+
+    /// send delivers the transaction to the current leader, retrying with the then-current one
+    async fn send(
+        &self,
+        transaction: &Transaction,
+        compute_unit_price: u64,
+    ) -> Result<Signature, SendError> {
+        let config = self.config;
+        if let Some(max_fee) = config.max_fee_lamports {
+            let fee = fees::expected_fee(
+                &transaction.message,
+                config.compute_unit_limit,
+                compute_unit_price,
+            );
+            if fee > max_fee {
+                return Err(SendError::FeeTooHigh { fee, max_fee });
+            }
+        }
+        config.audit(transaction)?;
+
+        let mut last_error = SendError::Other("No attempts made".to_string());
+        for attempt in 0..config.retry.max(1) {
+            if attempt > 0 {
+                self.tx_tracker.retry(attempt + 1, last_error.to_string());
+            }
+            match QuicManager::for_current_leader_in(
+                self.rpc_client.clone(),
+                self.tracker,
+                Some(&self.connections),
+            )
+            .await
+            {
+                Ok(manager) => match manager
+                    .send_signed_transaction(transaction, self.tx_tracker)
+                    .await
+                {
+                    Ok(signature) => return Ok(signature),
+                    Err(e) => last_error = e,
+                },
+                Err(e) => last_error = e.into(),
+            }
+        }
+        Err(last_error)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
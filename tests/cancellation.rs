@@ -0,0 +1,105 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use quic::{
+    config::{Config, Network},
+    confirmation,
+    error::SendError,
+    leader_tracker::LeaderTrackerImpl,
+    sender,
+    test_utils::rpc_server::MockRpcServer,
+    tx_tracker::TxTracker,
+};
+use serde_json::json;
+use solana_sdk::signature::Signature;
+use tokio::time::{sleep, timeout};
+use tokio_util::sync::CancellationToken;
+
+/// Nothing listens there, confirmations fall back to polling the statuses
+const WS_URL: &str = "ws://127.0.0.1:1";
+/// Far below the confirmation timeout, a cancelled operation must be done by then
+const PROMPTLY: Duration = Duration::from_secs(5);
+
+/// config polls the mock node for statuses that never arrive
+fn config() -> Config {
+    Config {
+        ws_url: WS_URL.to_string(),
+        retry: u8::MAX,
+        confirm_poll_interval: Duration::from_millis(50),
+        ..Config::new(Network::Devnet, 1)
+    }
+}
+
+fn cancel_after(cancel: &CancellationToken, delay: Duration) {
+    let cancel = cancel.clone();
+    tokio::spawn(async move {
+        sleep(delay).await;
+        cancel.cancel();
+    });
+}
+
+#[tokio::test]
+async fn cancelled_confirmation_stops_waiting() {
+    let server = MockRpcServer::start().await;
+    server.set_signature_statuses(1, HashMap::new());
+    let config = config();
+    cancel_after(&config.cancel, Duration::from_millis(200));
+
+    let result = timeout(
+        PROMPTLY,
+        confirmation::check_confirm_transaction(
+            &server.rpc_client(),
+            &config,
+            &Signature::new_unique(),
+            None,
+        ),
+    )
+    .await
+    .expect("the confirmation wasn't cancelled");
+
+    assert!(matches!(result, Err(SendError::Cancelled)));
+}
+
+#[tokio::test]
+async fn pending_signatures_end_cancelled() {
+    let server = MockRpcServer::start().await;
+    server.set_signature_statuses(1, HashMap::new());
+    server.set_result("getBlockHeight", json!(1));
+    let config = config();
+    let signatures = [Signature::new_unique(), Signature::new_unique()];
+    cancel_after(&config.cancel, Duration::from_millis(200));
+
+    let mut reported = 0;
+    let results = timeout(
+        PROMPTLY,
+        confirmation::confirm_many(&server.rpc_client(), &config, &signatures, 100, |_, _| {
+            reported += 1
+        }),
+    )
+    .await
+    .expect("the confirmations weren't cancelled");
+
+    assert_eq!(reported, 2);
+    assert!(signatures
+        .iter()
+        .all(|signature| matches!(results[signature], Err(SendError::Cancelled))));
+}
+
+#[tokio::test]
+async fn cancelled_send_stops_retrying() {
+    let server = MockRpcServer::start().await;
+    server.set_slot(100);
+    let rpc_client = Arc::new(server.rpc_client());
+    let config = config();
+    // no leader in the schedule, every attempt fails and is retried
+    let tracker = LeaderTrackerImpl::driven(rpc_client.clone(), 1, 0, config.cancel.clone()).await;
+    cancel_after(&config.cancel, Duration::from_millis(200));
+
+    let result = timeout(
+        PROMPTLY,
+        sender::send_with_retry(&config, rpc_client, &tracker, &TxTracker::new()),
+    )
+    .await
+    .expect("the send wasn't cancelled");
+
+    assert!(matches!(result, Err(SendError::Cancelled)));
+}
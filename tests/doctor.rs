@@ -0,0 +1,42 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use quic::{
+    doctor::{self, Status},
+    test_utils::{fixtures, rpc_server::MockRpcServer, tpu_server::MockTpuServer},
+};
+use serde_json::json;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn network_checks_pass_against_a_reachable_cluster() {
+    let tpu = MockTpuServer::start().unwrap();
+    let rpc = MockRpcServer::start().await;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    rpc.set_slot(100);
+    rpc.set_result("getBlockTime", json!(now.as_secs()));
+    rpc.set_result("getHealth", json!("ok"));
+    rpc.set_cluster_nodes(&[(fixtures::keypair(1).pubkey(), tpu.addr())]);
+
+    let report = doctor::diagnose(Arc::new(rpc.rpc_client())).await;
+
+    let statuses: Vec<_> = report
+        .diagnoses
+        .iter()
+        .map(|diagnosis| (diagnosis.name, diagnosis.status))
+        .filter(|(name, _)| !matches!(*name, "udp-buffers" | "open-files"))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("clock", Status::Ok),
+            ("rpc-rate-limits", Status::Ok),
+            ("tpu-reachability", Status::Ok),
+        ],
+        "{}",
+        report
+    );
+    assert!(report.healthy());
+}
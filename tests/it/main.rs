@@ -0,0 +1,6 @@
+//! End-to-end tests against a local `solana-test-validator`. They are ignored by default and run
+//! with `cargo test --test it -- --ignored`, looking for the validator on the `PATH` unless
+//! `SOLANA_TEST_VALIDATOR` points to it
+
+mod transfer;
+mod validator;
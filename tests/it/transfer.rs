@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use quic::{
+    config::{Config, Network},
+    leader_tracker::LeaderTrackerImpl,
+    sender,
+    tx_tracker::TxTracker,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey,
+};
+
+use crate::validator::TestValidator;
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs solana-test-validator"]
+async fn transfer_is_confirmed_through_the_leader_tpu() {
+    let validator = TestValidator::start().await.unwrap();
+    let (_, sender_key) = validator
+        .funded_keypair(10 * LAMPORTS_PER_SOL)
+        .await
+        .unwrap();
+    let receiver = Pubkey::new_unique();
+
+    let mut config = Config::new(Network::Devnet, 3);
+    config.rpc_url = validator.rpc_url();
+    config.ws_url = validator.ws_url();
+    config.sender_key = sender_key.display().to_string();
+    config.receiver_key = receiver.to_string();
+    // enough for the new account to be rent exempt
+    config.amount = LAMPORTS_PER_SOL;
+    config.commitment_level = CommitmentConfig::confirmed();
+
+    let rpc_client = Arc::new(validator.rpc_client());
+    let tracker = LeaderTrackerImpl::new(rpc_client.clone(), 4, 0, config.ws_url.clone()).await;
+    tracker.poll_slot_leaders_once().await.unwrap();
+
+    let outcome = sender::send_with_retry(&config, rpc_client.clone(), &tracker, &TxTracker::new())
+        .await
+        .unwrap();
+
+    assert!(outcome.slot > 0);
+    let balance = rpc_client.get_balance(&receiver).await.unwrap();
+    assert_eq!(balance, LAMPORTS_PER_SOL);
+}
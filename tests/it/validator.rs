@@ -0,0 +1,110 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::{write_keypair_file, Keypair, Signer},
+};
+use tokio::time::sleep;
+
+/// Ports away from the defaults, so a validator already running for development isn't disturbed.
+/// The WebSocket listens on the RPC port + 1
+const RPC_PORT: u16 = 18899;
+const FAUCET_PORT: u16 = 19900;
+const GOSSIP_PORT: u16 = 18001;
+const DYNAMIC_PORT_RANGE: &str = "18002-18030";
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(90);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// TestValidator is a `solana-test-validator` on a fresh ledger, killed and removed when dropped
+pub struct TestValidator {
+    process: Child,
+    ledger: PathBuf,
+}
+
+impl TestValidator {
+    /// start starts the validator and waits until its RPC node is healthy and past the first slot
+    pub async fn start() -> Result<Self, String> {
+        let ledger = env::temp_dir().join(format!("quic-it-{}", std::process::id()));
+        let binary =
+            env::var("SOLANA_TEST_VALIDATOR").unwrap_or_else(|_| "solana-test-validator".into());
+        let process = Command::new(&binary)
+            .arg("--reset")
+            .arg("--quiet")
+            .arg("--ledger")
+            .arg(&ledger)
+            .args(["--rpc-port", &RPC_PORT.to_string()])
+            .args(["--faucet-port", &FAUCET_PORT.to_string()])
+            .args(["--gossip-port", &GOSSIP_PORT.to_string()])
+            .args(["--dynamic-port-range", DYNAMIC_PORT_RANGE])
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", binary, e))?;
+        let validator = Self { process, ledger };
+
+        let rpc_client = validator.rpc_client();
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            let healthy = rpc_client.get_health().await.is_ok();
+            if healthy && rpc_client.get_slot().await.is_ok_and(|slot| slot > 0) {
+                return Ok(validator);
+            }
+            if Instant::now() >= deadline {
+                return Err(format!("Validator not ready within {:?}", STARTUP_TIMEOUT));
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    pub fn rpc_url(&self) -> String {
+        format!("http://127.0.0.1:{}", RPC_PORT)
+    }
+
+    pub fn ws_url(&self) -> String {
+        format!("ws://127.0.0.1:{}", RPC_PORT + 1)
+    }
+
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.rpc_url(), CommitmentConfig::confirmed())
+    }
+
+    /// funded_keypair airdrops the lamports to a new keypair and writes it to a file of the
+    /// ledger directory, returning the keypair and the file
+    pub async fn funded_keypair(&self, lamports: u64) -> Result<(Keypair, PathBuf), String> {
+        let keypair = Keypair::new();
+        let path = self.ledger.join(format!("{}.json", keypair.pubkey()));
+        write_keypair_file(&keypair, &path)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        let rpc_client = self.rpc_client();
+        rpc_client
+            .request_airdrop(&keypair.pubkey(), lamports)
+            .await
+            .map_err(|e| format!("Airdrop failed: {}", e))?;
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        while rpc_client.get_balance(&keypair.pubkey()).await.unwrap_or(0) < lamports {
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "Airdrop not confirmed within {:?}",
+                    STARTUP_TIMEOUT
+                ));
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+        Ok((keypair, path))
+    }
+}
+
+impl Drop for TestValidator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = fs::remove_dir_all(&self.ledger);
+    }
+}
@@ -0,0 +1,124 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use quic::{
+    leader_tracker::{LeaderTracker, LeaderTrackerImpl},
+    outcome::{self, FeeLookups},
+    test_utils::rpc_server::MockRpcServer,
+};
+use serde_json::json;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+
+/// Nothing listens there, the WebSocket listener of the tracker keeps failing to connect
+const WS_URL: &str = "ws://127.0.0.1:1";
+
+#[tokio::test]
+async fn leader_tracker_follows_the_served_schedule() {
+    let server = MockRpcServer::start().await;
+    let leader = Pubkey::new_unique();
+    let tpu_quic = SocketAddr::from(([127, 0, 0, 1], 8009));
+    server.set_slot(100);
+    server.set_slot_leaders(100, &[leader; 8]);
+    server.set_cluster_nodes(&[(leader, tpu_quic)]);
+
+    let tracker =
+        LeaderTrackerImpl::new(Arc::new(server.rpc_client()), 1, 0, WS_URL.to_string()).await;
+    tracker.poll_slot_leaders_once().await.unwrap();
+
+    assert_eq!(tracker.current_slot(), 100);
+    let leaders = tracker.get_leaders();
+    assert_eq!(leaders.len(), 1);
+    assert_eq!(leaders[0].pubkey, leader.to_string());
+    assert_eq!(leaders[0].tpu_quic, Some(tpu_quic));
+    // the background poll of the tracker asks for the same slots
+    assert!(server
+        .requests("getSlotLeaders")
+        .await
+        .iter()
+        .all(|params| *params == json!([100, 1000])));
+}
+
+#[tokio::test]
+async fn statuses_are_served_for_the_requested_signatures() {
+    let server = MockRpcServer::start().await;
+    let confirmed = Signature::new_unique();
+    let status = TransactionStatus {
+        slot: 7,
+        confirmations: Some(1),
+        status: Ok(()),
+        err: None,
+        confirmation_status: Some(TransactionConfirmationStatus::Confirmed),
+    };
+    server.set_signature_statuses(7, HashMap::from([(confirmed, status)]));
+
+    let response = server
+        .rpc_client()
+        .get_signature_statuses(&[Signature::new_unique(), confirmed])
+        .await
+        .unwrap();
+
+    assert_eq!(response.context.slot, 7);
+    assert!(response.value[0].is_none());
+    let status = response.value[1].as_ref().unwrap();
+    assert_eq!(status.slot, 7);
+    assert_eq!(
+        status.confirmation_status,
+        Some(TransactionConfirmationStatus::Confirmed)
+    );
+}
+
+#[tokio::test]
+async fn unanswered_and_failing_methods_return_errors() {
+    let server = MockRpcServer::start().await;
+    let client = server.rpc_client();
+
+    let error = client.get_slot().await.unwrap_err();
+    assert!(error.to_string().contains("Method not found"));
+
+    server.set_error("getSlot", -32005, "Node is behind");
+    let error = client.get_slot().await.unwrap_err();
+    assert!(error.to_string().contains("Node is behind"));
+
+    server.set_slot(42);
+    assert_eq!(client.get_slot().await.unwrap(), 42);
+    assert_eq!(server.requests("getSlot").await.len(), 3);
+}
+
+#[tokio::test]
+async fn paid_fee_is_looked_up_at_the_confirmation_commitment_until_served() {
+    let server = MockRpcServer::start().await;
+    server.set_error("getTransaction", -32009, "Transaction not yet available");
+
+    let error = outcome::fetch_paid_fee(
+        &server.rpc_client(),
+        &Signature::default(),
+        CommitmentConfig::processed(),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.contains("after 5 attempts"));
+
+    let requests = server.requests("getTransaction").await;
+    assert_eq!(requests.len(), 5);
+    assert_eq!(requests[0][1]["commitment"], "confirmed");
+    assert_eq!(requests[0][1]["maxSupportedTransactionVersion"], 0);
+}
+
+#[tokio::test]
+async fn fee_lookups_do_not_hold_back_the_other_items() {
+    let server = MockRpcServer::start().await;
+    server.set_error("getTransaction", -32009, "Transaction not yet available");
+    let (fees, mut resolved) = FeeLookups::new(
+        Arc::new(server.rpc_client()),
+        CommitmentConfig::confirmed(),
+        "TEST",
+    );
+
+    fees.lookup(Some(Signature::default()), "confirmed");
+    fees.lookup(None, "expired");
+    drop(fees);
+
+    assert_eq!(resolved.recv().await, Some(("expired", None)));
+    assert_eq!(resolved.recv().await, Some(("confirmed", None)));
+    assert_eq!(resolved.recv().await, None);
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use quic::{
+    config::Config,
+    selftest,
+    test_utils::{
+        fixtures, rpc_server::MockRpcServer, tpu_server::MockTpuServer, ws_server::MockSlotServer,
+    },
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signature::Signer;
+use tokio::time::{sleep, Duration};
+
+/// Nothing listens there
+const UNREACHABLE_WS_URL: &str = "ws://127.0.0.1:1";
+
+#[tokio::test]
+async fn every_check_passes_against_a_healthy_cluster() {
+    let tpu = MockTpuServer::start().unwrap();
+    let slots = MockSlotServer::start().await.unwrap();
+    let rpc = MockRpcServer::start().await;
+    let leader = fixtures::keypair(5).pubkey();
+    rpc.set_slot(100);
+    rpc.set_slot_leaders(100, &[leader; 8]);
+    rpc.set_cluster_nodes(&[(leader, tpu.addr())]);
+    let config = Config {
+        rpc_url: rpc.url(),
+        ws_url: slots.url(),
+        ..fixtures::config(1)
+    };
+
+    // the WebSocket check waits for the first slot update
+    let pushing = tokio::spawn(async move {
+        for slot in 100.. {
+            slots.send_slot(slot);
+            sleep(Duration::from_millis(50)).await;
+        }
+    });
+    let report = selftest::run(&config, Arc::new(rpc.rpc_client())).await;
+    pushing.abort();
+
+    assert!(report.passed(), "{}", report);
+    let names: Vec<_> = report.checks.iter().map(|check| check.name).collect();
+    assert_eq!(names, ["keypair", "rpc", "websocket", "leaders", "quic"]);
+    // the server counts the connection once its side of the handshake is done
+    for _ in 0..100 {
+        if tpu.connections() > 0 {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(tpu.connections(), 1);
+}
+
+#[tokio::test]
+async fn an_unreachable_cluster_fails_every_network_check() {
+    let config = Config {
+        rpc_url: "http://127.0.0.1:1".to_string(),
+        ws_url: UNREACHABLE_WS_URL.to_string(),
+        ..fixtures::config(1)
+    };
+    let rpc_client = Arc::new(RpcClient::new(config.rpc_url.clone()));
+
+    let report = selftest::run(&config, rpc_client).await;
+
+    assert!(!report.passed());
+    let failed: Vec<_> = report
+        .checks
+        .iter()
+        .filter(|check| !check.passed)
+        .map(|check| check.name)
+        .collect();
+    assert_eq!(failed, ["rpc", "websocket", "leaders", "quic"]);
+}
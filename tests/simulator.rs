@@ -0,0 +1,39 @@
+use quic::{
+    simulator::{self, CapturedSchedule, Settings, Strategy},
+    test_utils::fixtures,
+};
+
+/// Four slots per leader, as on the cluster
+fn schedule(leaders: usize) -> CapturedSchedule {
+    CapturedSchedule {
+        first_slot: 1_000,
+        leaders: fixtures::pubkeys(1, leaders)
+            .iter()
+            .flat_map(|leader| std::iter::repeat_n(leader.to_string(), 4))
+            .collect(),
+    }
+}
+
+#[tokio::test]
+async fn replayed_transactions_reach_the_leaders() {
+    let settings = Settings {
+        tps: 50.0,
+        slots: 24,
+        slot_ms: 50,
+    };
+    let strategy = Strategy {
+        num_leaders: 1,
+        leader_offset: 0,
+        fanout: 1,
+    };
+
+    let report = simulator::simulate(&schedule(10), strategy, settings)
+        .await
+        .unwrap();
+
+    assert!(report.sent > 0);
+    assert_eq!(report.send_failures, 0);
+    assert_eq!(report.landed + report.missed, report.sent);
+    assert!(report.landed > 0);
+    assert!(report.wait_slots.is_some());
+}
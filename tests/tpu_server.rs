@@ -0,0 +1,183 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use quic::{
+    config::{Config, Network},
+    leader_tracker::LeaderTracker,
+    quic_manager::{ConnectionPool, QuicManager},
+    rpc_api,
+    test_utils::tpu_server::MockTpuServer,
+    tx_tracker::TxTracker,
+    worker_pool::SenderPool,
+};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_response::RpcContactInfo};
+use solana_sdk::{
+    clock::Slot,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_transaction,
+    transaction::Transaction,
+};
+use tokio::runtime::Runtime;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The QUIC endpoint of the process runs on the runtime of its first connection, so every test
+/// runs on the same one
+static RUNTIME: LazyLock<Runtime> =
+    LazyLock::new(|| Runtime::new().expect("failed to start the test runtime"));
+
+fn run(test: impl Future<Output = ()>) {
+    RUNTIME.block_on(test)
+}
+
+/// The sends under test never reach the RPC node
+fn rpc_client() -> Arc<RpcClient> {
+    Arc::new(RpcClient::new("http://127.0.0.1:1".to_string()))
+}
+
+fn transfer(lamports: u64) -> Transaction {
+    let payer = Keypair::new();
+    system_transaction::transfer(&payer, &payer.pubkey(), lamports, Hash::new_unique())
+}
+
+/// StaticTracker always has the same upcoming leaders
+struct StaticTracker(Arc<[RpcContactInfo]>);
+
+impl StaticTracker {
+    fn new(tpu_quic: &[SocketAddr]) -> Self {
+        Self(
+            tpu_quic
+                .iter()
+                .map(|addr| rpc_api::contact_info(&Pubkey::new_unique(), *addr))
+                .collect(),
+        )
+    }
+}
+
+impl LeaderTracker for StaticTracker {
+    fn get_leaders(&self) -> Arc<[RpcContactInfo]> {
+        self.0.clone()
+    }
+
+    fn current_slot(&self) -> Slot {
+        100
+    }
+}
+
+#[test]
+fn signed_transaction_reaches_the_leader() {
+    run(async {
+        let server = MockTpuServer::start().unwrap();
+        let transaction = transfer(1);
+
+        let manager = QuicManager::new(rpc_client(), server.addr()).await;
+        let signature = manager
+            .send_signed_transaction(&transaction, &TxTracker::new())
+            .await
+            .unwrap();
+
+        assert_eq!(signature, transaction.signatures[0]);
+        assert_eq!(server.wait_for(1, TIMEOUT).await.unwrap(), [transaction]);
+    });
+}
+
+#[test]
+fn pooled_managers_share_the_connection() {
+    run(async {
+        let server = MockTpuServer::start().unwrap();
+        let pool = ConnectionPool::default();
+        let tx_tracker = TxTracker::new();
+        pool.manager(rpc_client(), server.addr());
+        assert_eq!(pool.handshakes(), [(server.addr(), false)]);
+
+        for lamports in 1..=3 {
+            let manager = pool.manager(rpc_client(), server.addr());
+            manager
+                .send_signed_transaction(&transfer(lamports), &tx_tracker)
+                .await
+                .unwrap();
+        }
+
+        server.wait_for(3, TIMEOUT).await.unwrap();
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.handshakes(), [(server.addr(), true)]);
+        assert_eq!(server.connections(), 1);
+    });
+}
+
+#[test]
+fn sender_pool_delivers_every_transaction_to_the_current_leader() {
+    run(async {
+        let (upcoming, current) = (
+            MockTpuServer::start().unwrap(),
+            MockTpuServer::start().unwrap(),
+        );
+        // the manager targets the last of the upcoming leaders
+        let tracker = StaticTracker::new(&[upcoming.addr(), current.addr()]);
+        let config = Config::new(Network::Devnet, 1);
+        let tx_tracker = TxTracker::new();
+        let transactions: Vec<Transaction> = (1..=20).map(transfer).collect();
+        let references: Vec<&Transaction> = transactions.iter().collect();
+
+        let results = SenderPool::new(&config, rpc_client(), &tracker, &tx_tracker)
+            .send_all(&references, 0)
+            .await;
+
+        assert!(results.iter().all(Result::is_ok));
+        let mut received = current.wait_for(20, TIMEOUT).await.unwrap();
+        received.sort_by_key(|transaction| transaction.signatures[0]);
+        let mut expected = transactions;
+        expected.sort_by_key(|transaction| transaction.signatures[0]);
+        assert_eq!(received, expected);
+        assert!(upcoming.received().is_empty());
+    });
+}
+
+#[test]
+fn dropped_transactions_are_sent_but_not_received() {
+    run(async {
+        let server = MockTpuServer::start().unwrap();
+        server.set_drop_rate(1.0);
+        let manager = QuicManager::new(rpc_client(), server.addr()).await;
+
+        manager
+            .send_signed_transaction(&transfer(1), &TxTracker::new())
+            .await
+            .unwrap();
+
+        tokio::time::timeout(TIMEOUT, async {
+            while server.dropped() == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(server.received().is_empty());
+    });
+}
+
+#[test]
+fn late_transactions_are_received_after_the_latency() {
+    run(async {
+        let server = MockTpuServer::start().unwrap();
+        server.set_latency(Duration::from_millis(300));
+        let manager = QuicManager::new(rpc_client(), server.addr()).await;
+
+        manager
+            .send_signed_transaction(&transfer(1), &TxTracker::new())
+            .await
+            .unwrap();
+
+        assert!(server
+            .wait_for(1, Duration::from_millis(100))
+            .await
+            .is_err());
+        server.wait_for(1, TIMEOUT).await.unwrap();
+    });
+}
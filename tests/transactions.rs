@@ -0,0 +1,80 @@
+use quic::{
+    config::Config,
+    test_utils::{fixtures, snapshot},
+    token::TokenTransfer,
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    signature::Signer,
+};
+
+const COMPUTE_UNIT_LIMIT: u32 = 450;
+const COMPUTE_UNIT_PRICE: u64 = 10_000;
+
+/// signed builds and signs the message of the config on the fixture blockhash
+fn signed(config: &Config) -> serde_json::Value {
+    let blockhash = fixtures::blockhash(1);
+    let message = config
+        .create_message(blockhash, COMPUTE_UNIT_LIMIT, COMPUTE_UNIT_PRICE)
+        .unwrap();
+    snapshot::describe(config.sign_message(message, blockhash).unwrap())
+}
+
+#[test]
+fn transfer() {
+    insta::assert_json_snapshot!(signed(&fixtures::config(1)));
+}
+
+#[test]
+fn transfer_without_compute_budget() {
+    let config = Config {
+        skip_compute_budget: true,
+        ..fixtures::config(1)
+    };
+    insta::assert_json_snapshot!(signed(&config));
+}
+
+#[test]
+fn transfer_with_memo() {
+    let config = Config {
+        memo: Some("quic snapshot".to_string()),
+        ..fixtures::config(1)
+    };
+    insta::assert_json_snapshot!(signed(&config));
+}
+
+#[test]
+fn token_transfer_creating_the_destination() {
+    let config = Config {
+        token_transfer: Some(TokenTransfer {
+            mint: fixtures::keypair(2).pubkey(),
+            amount: 1_500_000,
+            decimals: 6,
+            program_id: spl_token::id(),
+            transfer_fee: None,
+            create_destination: true,
+            account_len: spl_token::state::Account::LEN,
+        }),
+        ..fixtures::config(1)
+    };
+    insta::assert_json_snapshot!(signed(&config));
+}
+
+#[test]
+fn custom_instructions() {
+    let [program, account] = [3, 4].map(|seed| fixtures::keypair(seed).pubkey());
+    let sender = fixtures::config(1).sender();
+    let config = Config {
+        instructions: Some(vec![Instruction {
+            program_id: program,
+            accounts: vec![
+                AccountMeta::new(sender, true),
+                AccountMeta::new_readonly(account, false),
+            ],
+            data: vec![1, 2, 3],
+        }]),
+        ..fixtures::config(1)
+    };
+    insta::assert_json_snapshot!(signed(&config));
+}
@@ -0,0 +1,53 @@
+use std::{sync::Arc, time::Duration};
+
+use quic::{
+    leader_tracker::{LeaderTracker, LeaderTrackerImpl},
+    rpc_api::MockRpc,
+    test_utils::ws_server::MockSlotServer,
+};
+use solana_sdk::clock::Slot;
+use tokio::time::{sleep, Instant};
+
+/// Longer than a reconnection plus the staleness threshold of the tracker
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// wait_for_slot waits until the tracker is at the slot, sending it over the WebSocket meanwhile
+async fn wait_for_slot(tracker: &LeaderTrackerImpl, server: &MockSlotServer, slot: Slot) {
+    let deadline = Instant::now() + TIMEOUT;
+    while tracker.current_slot() != slot {
+        assert!(
+            Instant::now() < deadline,
+            "the tracker is at slot {} instead of {}",
+            tracker.current_slot(),
+            slot
+        );
+        server.send_slot(slot);
+        sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[tokio::test]
+async fn slot_view_survives_websocket_restarts() {
+    let server = MockSlotServer::start().await.unwrap();
+    let rpc = Arc::new(MockRpc::new());
+    rpc.set_slot(100);
+    let tracker = LeaderTrackerImpl::new(rpc.clone(), 1, 0, server.url()).await;
+
+    let mut slot = 100;
+    for restart in 1..=3 {
+        slot += 10;
+        wait_for_slot(&tracker, &server, slot).await;
+
+        // the slot keeps moving over RPC while the WebSocket is down
+        server.kill();
+        slot += 10;
+        rpc.set_slot(slot);
+        wait_for_slot(&tracker, &server, slot).await;
+
+        // and over the WebSocket again once it reconnected
+        server.revive();
+        slot += 10;
+        wait_for_slot(&tracker, &server, slot).await;
+        assert_eq!(server.connections(), restart + 1);
+    }
+}